@@ -0,0 +1,95 @@
+use crate::utils::res::{Res, res_json_err, res_json_ok, res_not_found};
+use crate::worker::reconcile_worker::ReconcileState;
+use common::domain::dto::chain_status_dto::ChainStatusDto;
+use common::domain::dto::reconcile_dto::ReconcileReportDto;
+use common::domain::entity::tx_outbox::TxOutboxEntryDto;
+use mongodb::Database;
+use pharos_interact::ContractRegistry;
+use salvo::oapi::extract::QueryParam;
+use salvo::prelude::*;
+use service::OutboxService;
+use std::sync::Arc;
+
+/// 查询各合约部署最近一次链上票据对账报告
+#[salvo::oapi::endpoint(
+    tags("管理"),
+    status_codes(200, 401, 403, 404),
+    responses(
+        (status_code = 200, description = "Latest reconcile report of every deployment.", body = Vec<ReconcileReportDto>),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+        (status_code = 404, description = "No reconcile run has finished yet."),
+    )
+)]
+pub async fn reconcile_report(depot: &mut Depot) -> Res<Vec<ReconcileReportDto>> {
+    let state = depot.obtain::<Arc<ReconcileState>>().expect("Reconcile state not found").clone();
+
+    let reports = state.last_reports().await;
+    if reports.is_empty() {
+        return Err(res_not_found("No reconcile report available yet"));
+    }
+    Ok(res_json_ok(Some(reports)))
+}
+
+/// 立即触发一次链上票据对账
+#[salvo::oapi::endpoint(
+    tags("管理"),
+    status_codes(200, 401, 403),
+    responses(
+        (status_code = 200, description = "Reconcile run requested."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+    )
+)]
+pub async fn trigger_reconcile(depot: &mut Depot) -> Res<()> {
+    let state = depot.obtain::<Arc<ReconcileState>>().expect("Reconcile state not found").clone();
+    state.trigger();
+    Ok(res_json_ok(None))
+}
+
+/// 查询合约交易发件箱记录
+#[salvo::oapi::endpoint(
+    tags("管理"),
+    status_codes(200, 401, 403, 500),
+    parameters(
+        ("limit" = Option<i64>, Query, description = "Max entries to return, default 50")
+    ),
+    responses(
+        (status_code = 200, description = "Most recent outbox entries, newest first.", body = Vec<TxOutboxEntryDto>),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_outbox(limit: QueryParam<i64, false>, depot: &mut Depot) -> Res<Vec<TxOutboxEntryDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let limit = limit.into_inner().unwrap_or(50).clamp(1, 500);
+
+    match OutboxService::new(&mongodb).recent(limit).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(TxOutboxEntryDto::from).collect()))),
+        Err(e) => {
+            log::error!("Failed to list outbox entries: {}", e);
+            Err(res_json_err("Failed to list outbox entries"))
+        }
+    }
+}
+
+/// 查询各合约部署的链上连接状态
+#[salvo::oapi::endpoint(
+    tags("管理"),
+    status_codes(200, 401, 403),
+    responses(
+        (status_code = 200, description = "Connection, chain id, head block and signer balance of every deployment.", body = Vec<ChainStatusDto>),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+    )
+)]
+pub async fn chain_status(depot: &mut Depot) -> Res<Vec<ChainStatusDto>> {
+    let contracts = depot.obtain::<Arc<ContractRegistry>>().expect("Contract registry not found").clone();
+
+    let mut statuses = Vec::new();
+    for (name, contract) in contracts.iter() {
+        statuses.push(contract.status(name, contracts.is_default(name)).await);
+    }
+    Ok(res_json_ok(Some(statuses)))
+}
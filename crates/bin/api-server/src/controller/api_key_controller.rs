@@ -0,0 +1,182 @@
+use crate::utils::auth::{current_enterprise_id, current_user_address, is_platform_admin};
+use crate::utils::res::{Res, ResObj, res_bad_request, res_json_custom, res_json_err, res_json_ok, res_not_found};
+use common::domain::entity::{ApiKey, ApiKeyDto};
+use configs::CFG;
+use mongodb::{Database, bson::oid::ObjectId};
+use salvo::{
+    oapi::{ToSchema, extract::{JsonBody, QueryParam}},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use service::{ApiKeyError, ApiKeyService};
+use std::sync::Arc;
+
+// --- Request DTOs ---
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({ "name": "SAP ERP" })))]
+pub struct CreateApiKeyRequest {
+    /// 名称, 如使用该 Key 的 ERP 系统
+    pub name: String,
+    /// 企业ID, 仅平台管理员可指定, 默认为当前用户所属企业
+    pub enterprise_id: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({ "keyId": "rk_0f1e2d3c4b5a69788796a5b4c3d2e1f0" })))]
+pub struct RotateApiKeyRequest {
+    /// API Key ID
+    pub key_id: String,
+}
+
+// --- Response DTO ---
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeySecretResponse {
+    /// API Key 信息
+    pub key: ApiKeyDto,
+    /// 签名密钥, 仅在创建与轮换时返回一次
+    pub secret: String,
+}
+
+fn res_api_key_err(e: &ApiKeyError) -> Json<ResObj<()>> {
+    match e {
+        ApiKeyError::NotFound => res_not_found("API key not found"),
+        e => {
+            log::error!("API key operation failed: {}", e);
+            res_json_err("API key operation failed")
+        }
+    }
+}
+
+fn api_keys(depot: &Depot) -> ApiKeyService {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    ApiKeyService::new(&mongodb, &CFG.api_keys.master_secret)
+}
+
+// Enterprise whose keys are managed: platform admins may name any, everyone else gets their own
+async fn target_enterprise_id(depot: &Depot, mongodb: &Database, requested: Option<&str>) -> Result<ObjectId, Json<ResObj<()>>> {
+    match requested {
+        Some(id) if is_platform_admin(depot) => ObjectId::parse_str(id).map_err(|_| res_bad_request("Invalid enterprise ID format")),
+        Some(_) => Err(res_json_custom(403, "Only platform admins can manage API keys of other enterprises")),
+        None => current_enterprise_id(depot, mongodb).await,
+    }
+}
+
+// Load a key the caller may manage: one of their enterprise's keys, or any key for platform admins
+async fn owned_api_key(depot: &Depot, mongodb: &Database, key_id: &str) -> Result<ApiKey, Json<ResObj<()>>> {
+    let key = match api_keys(depot).find(key_id).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return Err(res_not_found("API key not found")),
+        Err(e) => return Err(res_api_key_err(&e)),
+    };
+    if !is_platform_admin(depot) && current_enterprise_id(depot, mongodb).await? != key.enterprise_id {
+        log::warn!("User {} denied managing API key {} of enterprise {}", current_user_address(depot)?, key_id, key.enterprise_id);
+        return Err(res_json_custom(403, "API key belongs to another enterprise"));
+    }
+    Ok(key)
+}
+
+/// 为当前用户所属企业创建 ERP API Key (企业管理员或平台管理员)
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    status_codes(200, 400, 401, 403, 500),
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status_code = 200, description = "API key created; the secret is only returned now.", body = ApiKeySecretResponse),
+        (status_code = 400, description = "Name is required or the enterprise ID is invalid."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not an admin of the enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn create_api_key(req: JsonBody<CreateApiKeyRequest>, depot: &mut Depot) -> Res<ApiKeySecretResponse> {
+    let actor = current_user_address(depot)?;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let req = req.into_inner();
+    let enterprise_id = target_enterprise_id(depot, &mongodb, req.enterprise_id.as_deref()).await?;
+    let name = req.name;
+    if name.trim().is_empty() {
+        return Err(res_bad_request("name is required"));
+    }
+
+    match api_keys(depot).create(enterprise_id, name.trim(), &actor).await {
+        Ok((key, secret)) => Ok(res_json_ok(Some(ApiKeySecretResponse { key: ApiKeyDto::from(&key), secret }))),
+        Err(e) => Err(res_api_key_err(&e)),
+    }
+}
+
+/// 查询当前用户所属企业的 API Key (不含密钥)
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    status_codes(200, 400, 401, 403, 500),
+    parameters(
+        ("enterprise_id" = Option<String>, Query, description = "Enterprise ID, platform admins only")
+    ),
+    responses(
+        (status_code = 200, description = "API keys of the enterprise, newest first.", body = Vec<ApiKeyDto>),
+        (status_code = 400, description = "Invalid enterprise ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not an admin of the enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_api_keys(enterprise_id: QueryParam<String, false>, depot: &mut Depot) -> Res<Vec<ApiKeyDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let enterprise_id = target_enterprise_id(depot, &mongodb, enterprise_id.as_deref()).await?;
+
+    match api_keys(depot).list(enterprise_id).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(ApiKeyDto::from).collect()))),
+        Err(e) => Err(res_api_key_err(&e)),
+    }
+}
+
+/// 轮换 API Key 的签名密钥, 旧密钥立即失效
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    status_codes(200, 401, 403, 404, 500),
+    request_body = RotateApiKeyRequest,
+    responses(
+        (status_code = 200, description = "Secret rotated; the new secret is only returned now.", body = ApiKeySecretResponse),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "API key belongs to another enterprise."),
+        (status_code = 404, description = "No active API key with this id."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn rotate_api_key(req: JsonBody<RotateApiKeyRequest>, depot: &mut Depot) -> Res<ApiKeySecretResponse> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let key = owned_api_key(depot, &mongodb, &req.key_id).await?;
+
+    match api_keys(depot).rotate(key.enterprise_id, &key.key_id).await {
+        Ok((key, secret)) => Ok(res_json_ok(Some(ApiKeySecretResponse { key: ApiKeyDto::from(&key), secret }))),
+        Err(e) => Err(res_api_key_err(&e)),
+    }
+}
+
+/// 吊销 API Key
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    status_codes(200, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Query, description = "API key id")
+    ),
+    responses(
+        (status_code = 200, description = "API key revoked."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "API key belongs to another enterprise."),
+        (status_code = 404, description = "No active API key with this id."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn revoke_api_key(id: QueryParam<String>, depot: &mut Depot) -> Res<()> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let key = owned_api_key(depot, &mongodb, &id.into_inner()).await?;
+
+    match api_keys(depot).revoke(key.enterprise_id, &key.key_id).await {
+        Ok(()) => Ok(res_json_ok(None)),
+        Err(e) => Err(res_api_key_err(&e)),
+    }
+}
@@ -0,0 +1,585 @@
+use crate::utils::auth::{current_enterprise_id, current_user_address, is_platform_admin, require_enterprise};
+use crate::utils::chains::{enqueue_chain_job, obtain_contract};
+use crate::utils::res::{Res, ResObj, res_bad_request, res_contract_err, res_json_custom, res_json_err, res_json_ok, res_lifecycle_err, res_not_found};
+use common::domain::dto::chain_job_dto::{ChainJobDto, JobEffect};
+use common::domain::dto::contract_call_dto::ContractCall;
+use common::domain::entity::invoice::InvoiceDto;
+use common::domain::entity::invoice_batch::InvoiceBatchDto;
+use common::domain::entity::settlement_nft::SettlementNftDto;
+use common::domain::entity::status_transition::StatusTransitionDto;
+use common::domain::entity::{InvoiceBatch, InvoiceBatchStatus, InvoiceStatus, TransitionEntity};
+use common::utils::decimal::apy_to_basis_points;
+use mongodb::{
+    Database,
+    bson::{DateTime, Decimal128, oid::ObjectId},
+};
+use salvo::oapi::{ToSchema, extract::JsonBody, extract::PathParam, extract::QueryParam};
+use salvo::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use service::repository::{InvoiceBatchRepository, InvoiceRepository};
+use service::{LifecycleService, SettlementService};
+use pharos_interact::{ContractQuerier, ContractRegistry, ContractWriter, RawTransactionSender};
+use std::str::FromStr;
+use std::sync::Arc;
+
+// --- Request DTOs ---
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({
+    "debtorId": "6610c1f2a3b4c5d6e7f80912",
+    "acceptedCurrency": "USDC",
+    "interestRateApy": "8.5",
+    "defaultInterestRateApy": "15",
+    "maturityDate": 1704067200
+})))]
+pub struct CreateBatchRequest {
+    /// 债务人企业ID (Database ObjectId)
+    pub debtor_id: String,
+    /// 接受的还款币种
+    pub accepted_currency: String,
+    /// 年化利率 (decimal string)
+    pub interest_rate_apy: String,
+    /// 违约年化利率 (decimal string)
+    pub default_interest_rate_apy: String,
+    /// 到期日期 (Unix timestamp, seconds)
+    pub maturity_date: i64,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({ "batchId": "6610c1f2a3b4c5d6e7f80912", "invoiceId": "6610c1f2a3b4c5d6e7f80913" })))]
+pub struct BatchInvoiceRequest {
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// 票据ID (Database ObjectId)
+    pub invoice_id: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({
+    "batchId": "6610c1f2a3b4c5d6e7f80912",
+    "stableTokenAddress": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+    "minTerm": 2592000,
+    "maxTerm": 7776000
+})))]
+pub struct CreateChainBatchRequest {
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// 稳定币合约地址
+    pub stable_token_address: String,
+    /// 最短期限 (秒)
+    pub min_term: u64,
+    /// 最长期限 (秒)
+    pub max_term: u64,
+    /// 合约部署名称, 为空使用默认部署
+    #[serde(default)]
+    pub deployment: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({ "batchId": "6610c1f2a3b4c5d6e7f80912" })))]
+pub struct BatchIdRequest {
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+}
+
+// --- Response DTOs ---
+#[derive(Serialize, ToSchema, Debug)]
+pub struct InvoiceBatchDetailDto {
+    pub batch: InvoiceBatchDto,
+    pub invoices: Vec<InvoiceDto>,
+}
+
+// --- Handlers ---
+
+/// 创建票据批次 (债权人为当前用户绑定的企业)
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 500),
+    request_body = CreateBatchRequest,
+    responses(
+        (status_code = 200, description = "Batch created in Packaging status.", body = InvoiceBatchDto),
+        (status_code = 400, description = "Invalid request data."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not bound to an enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn create_batch(req: JsonBody<CreateBatchRequest>, depot: &mut Depot) -> Res<InvoiceBatchDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let repo = InvoiceBatchRepository::new(&mongodb);
+
+    let creditor_id = current_enterprise_id(depot, &mongodb).await?;
+
+    let req = req.into_inner();
+    let debtor_id = match ObjectId::parse_str(&req.debtor_id) {
+        Ok(oid) => oid,
+        Err(_) => return Err(res_bad_request("Invalid debtorId format")),
+    };
+    if debtor_id == creditor_id {
+        return Err(res_bad_request("Debtor must differ from creditor"));
+    }
+    let interest_rate_apy = match Decimal128::from_str(&req.interest_rate_apy) {
+        Ok(rate) => rate,
+        Err(_) => return Err(res_bad_request("Invalid interestRateApy format")),
+    };
+    let default_interest_rate_apy = match Decimal128::from_str(&req.default_interest_rate_apy) {
+        Ok(rate) => rate,
+        Err(_) => return Err(res_bad_request("Invalid defaultInterestRateApy format")),
+    };
+    let maturity_date = DateTime::from_millis(req.maturity_date * 1000);
+
+    match repo
+        .create(creditor_id, debtor_id, req.accepted_currency, interest_rate_apy, default_interest_rate_apy, maturity_date)
+        .await
+    {
+        Ok(batch) => Ok(res_json_ok(Some(InvoiceBatchDto::from(&batch)))),
+        Err(e) => {
+            log::error!("Failed to create invoice batch: {}", e);
+            Err(res_json_err("Failed to create invoice batch"))
+        }
+    }
+}
+
+/// 向批次中添加票据
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 404, 500),
+    request_body = BatchInvoiceRequest,
+    responses(
+        (status_code = 200, description = "Invoice added, batch total recomputed.", body = InvoiceBatchDto),
+        (status_code = 400, description = "Invalid ID format, batch not packaging, or invoice not eligible."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not the creditor enterprise of the batch."),
+        (status_code = 404, description = "Batch or invoice not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn add_invoice(req: JsonBody<BatchInvoiceRequest>, depot: &mut Depot) -> Res<InvoiceBatchDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let invoice_repo = InvoiceRepository::new(&mongodb);
+    let lifecycle = LifecycleService::new(&mongodb);
+
+    let actor = current_user_address(depot)?;
+    let (batch_id, invoice_id) = parse_batch_invoice_ids(&req)?;
+    let batch = find_packaging_batch(&batch_repo, batch_id).await?;
+    require_enterprise(depot, &mongodb, &[batch.creditor_id], "Only the creditor enterprise can package invoices into its batch").await?;
+
+    let invoice = match invoice_repo.find_by_id(invoice_id).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => return Err(res_not_found("Invoice not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice {}: {}", invoice_id, e);
+            return Err(res_json_err("Failed to get invoice"));
+        }
+    };
+    // A batch packages receivables between exactly one creditor and one debtor
+    if invoice.creditor_id != batch.creditor_id || invoice.debtor_id != batch.debtor_id {
+        return Err(res_bad_request("Invoice creditor/debtor does not match the batch"));
+    }
+    if invoice.currency != batch.accepted_currency {
+        log::warn!(
+            "Invoice {} currency {} differs from batch {} accepted currency {}",
+            invoice.invoice_number,
+            invoice.currency,
+            batch_id,
+            batch.accepted_currency
+        );
+    }
+
+    if let Err(e) = lifecycle.package_invoice(batch_id, invoice_id, &actor).await {
+        log::error!("Failed to add invoice {} to batch {}: {}", invoice_id, batch_id, e);
+        return Err(res_lifecycle_err(&e));
+    }
+
+    refresh_total_amount(&batch_repo, &invoice_repo, batch_id).await
+}
+
+/// 从批次中移除票据
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 404, 500),
+    request_body = BatchInvoiceRequest,
+    responses(
+        (status_code = 200, description = "Invoice removed, batch total recomputed.", body = InvoiceBatchDto),
+        (status_code = 400, description = "Invalid ID format, batch not packaging, or invoice not in batch."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not the creditor enterprise of the batch."),
+        (status_code = 404, description = "Batch or invoice not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn remove_invoice(req: JsonBody<BatchInvoiceRequest>, depot: &mut Depot) -> Res<InvoiceBatchDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let invoice_repo = InvoiceRepository::new(&mongodb);
+    let lifecycle = LifecycleService::new(&mongodb);
+
+    let actor = current_user_address(depot)?;
+    let (batch_id, invoice_id) = parse_batch_invoice_ids(&req)?;
+    let batch = find_packaging_batch(&batch_repo, batch_id).await?;
+    require_enterprise(depot, &mongodb, &[batch.creditor_id], "Only the creditor enterprise can remove invoices from its batch").await?;
+
+    if let Err(e) = lifecycle.unpackage_invoice(batch_id, invoice_id, &actor).await {
+        log::error!("Failed to remove invoice {} from batch {}: {}", invoice_id, batch_id, e);
+        return Err(res_lifecycle_err(&e));
+    }
+
+    refresh_total_amount(&batch_repo, &invoice_repo, batch_id).await
+}
+
+/// 查询批次 (平台管理员可见全部，企业用户仅见本企业作为债权人或债务人的批次)
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 401, 403, 500),
+    responses(
+        (status_code = 200, description = "List of invoice batches visible to the user.", body = Vec<InvoiceBatchDto>),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not bound to an enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_batches(depot: &mut Depot) -> Res<Vec<InvoiceBatchDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let repo = InvoiceBatchRepository::new(&mongodb);
+
+    let batches = if is_platform_admin(depot) {
+        repo.find_all().await
+    } else {
+        let enterprise_id = current_enterprise_id(depot, &mongodb).await?;
+        repo.find_by_enterprise(enterprise_id).await
+    };
+
+    match batches {
+        Ok(list) => {
+            let data: Vec<InvoiceBatchDto> = list.iter().map(InvoiceBatchDto::from).collect();
+            Ok(res_json_ok(Some(data)))
+        }
+        Err(e) => {
+            log::error!("Failed to list invoice batches: {}", e);
+            Err(res_json_err("Failed to list invoice batches"))
+        }
+    }
+}
+
+/// 根据批次ID查询详情 (包含批次内票据，仅限该批次的债权人、债务人与平台管理员)
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Query, description = "Batch MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Batch found.", body = InvoiceBatchDetailDto),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is neither the creditor nor the debtor of the batch."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_batch(id: QueryParam<String>, depot: &mut Depot) -> Res<InvoiceBatchDetailDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let invoice_repo = InvoiceRepository::new(&mongodb);
+
+    let oid = match ObjectId::parse_str(&id.into_inner()) {
+        Ok(oid) => oid,
+        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+    };
+
+    let batch = match batch_repo.find_by_id(oid).await {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", oid, e);
+            return Err(res_json_err("Failed to get invoice batch"));
+        }
+    };
+    require_enterprise(depot, &mongodb, &[batch.creditor_id, batch.debtor_id], "Only the creditor or debtor can view the batch").await?;
+
+    match invoice_repo.find_by_batch(oid).await {
+        Ok(invoices) => Ok(res_json_ok(Some(InvoiceBatchDetailDto {
+            batch: InvoiceBatchDto::from(&batch),
+            invoices: invoices.iter().map(InvoiceDto::from).collect(),
+        }))),
+        Err(e) => {
+            log::error!("Failed to list invoices of batch {}: {}", oid, e);
+            Err(res_json_err("Failed to list invoices of batch"))
+        }
+    }
+}
+
+/// 在链上创建代币批次 (createTokenBatch)，返回异步任务
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 404, 500, 503),
+    request_body = CreateChainBatchRequest,
+    responses(
+        (status_code = 200, description = "createTokenBatch queued; the tx hash is recorded on the batch once confirmed.", body = ChainJobDto),
+        (status_code = 400, description = "Invalid request, unknown deployment, batch not packaging or has no invoices."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not the creditor enterprise of the batch."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 500, description = "Failed to queue the job."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn create_chain_batch(req: JsonBody<CreateChainBatchRequest>, depot: &mut Depot) -> Res<ChainJobDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let invoice_repo = InvoiceRepository::new(&mongodb);
+    let req = req.into_inner();
+    let (deployment, _) = obtain_contract(depot, req.deployment.as_deref())?;
+
+    let actor = current_user_address(depot)?;
+    let batch_id = ObjectId::parse_str(&req.batch_id).map_err(|_| res_bad_request("Invalid batchId format"))?;
+    if req.min_term == 0 || req.min_term > req.max_term {
+        return Err(res_bad_request("minTerm must be positive and not exceed maxTerm"));
+    }
+    let batch = find_packaging_batch(&batch_repo, batch_id).await?;
+    require_enterprise(depot, &mongodb, &[batch.creditor_id], "Only the creditor enterprise can issue its batch on chain").await?;
+    if batch.chain_batch_id.is_some() {
+        return Err(res_bad_request("Token batch already created on chain"));
+    }
+
+    let invoices = match invoice_repo.find_by_batch(batch_id).await {
+        Ok(invoices) => invoices,
+        Err(e) => {
+            log::error!("Failed to list invoices of batch {}: {}", batch_id, e);
+            return Err(res_json_err("Failed to list invoices of batch"));
+        }
+    };
+    if invoices.is_empty() {
+        return Err(res_bad_request("Batch has no invoices"));
+    }
+    if let Some(invoice) = invoices.iter().find(|invoice| invoice.status != InvoiceStatus::Packaged) {
+        return Err(res_bad_request(&format!("Invoice {} is {:?}, expected Packaged", invoice.invoice_number, invoice.status)));
+    }
+    // Invoices without a deployment live on the default one
+    let registry = depot.obtain::<Arc<ContractRegistry>>().expect("Contract registry not found").clone();
+    if let Some(invoice) = invoices.iter().find(|invoice| registry.resolve_name(invoice.deployment.as_deref()) != deployment) {
+        return Err(res_bad_request(&format!("Invoice {} is not on deployment '{}'", invoice.invoice_number, deployment)));
+    }
+    let invoice_numbers: Vec<String> = invoices.iter().map(|invoice| invoice.invoice_number.clone()).collect();
+
+    let interest_rate = match apy_to_basis_points(&batch.interest_rate_apy) {
+        Some(rate) => rate,
+        None => return Err(res_bad_request("Batch interest rate cannot be expressed in basis points")),
+    };
+
+    // The DB ObjectId doubles as the on-chain batch id so both sides can be joined later
+    let chain_batch_id = batch_id.to_hex();
+    let call = ContractCall::CreateTokenBatch {
+        batch_id: chain_batch_id.clone(),
+        invoice_numbers,
+        stable_token_address: req.stable_token_address.clone(),
+        min_term: req.min_term.to_string(),
+        max_term: req.max_term.to_string(),
+        interest_rate: interest_rate.to_string(),
+    };
+    let effect = JobEffect::RecordChainBatch {
+        batch_id: batch_id.to_hex(),
+        chain_batch_id,
+        stable_token_address: req.stable_token_address,
+    };
+    enqueue_chain_job(depot, Some(&deployment), call, effect, format!("batch:{}", batch_id), &actor).await
+}
+
+/// 确认链上代币批次发行 (confirmTokenBatchIssue)，返回异步任务，确认后批次进入 Issued
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 404, 500, 503),
+    request_body = BatchIdRequest,
+    responses(
+        (status_code = 200, description = "confirmTokenBatchIssue queued; the batch moves to Issued once confirmed.", body = ChainJobDto),
+        (status_code = 400, description = "Batch not created on chain yet or not packaging."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not the creditor enterprise of the batch."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 500, description = "Failed to queue the job."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn confirm_chain_batch(req: JsonBody<BatchIdRequest>, depot: &mut Depot) -> Res<ChainJobDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+
+    let actor = current_user_address(depot)?;
+    let batch_id = ObjectId::parse_str(&req.batch_id).map_err(|_| res_bad_request("Invalid batchId format"))?;
+    let batch = find_packaging_batch(&batch_repo, batch_id).await?;
+    require_enterprise(depot, &mongodb, &[batch.creditor_id], "Only the creditor enterprise can confirm its batch").await?;
+    let (deployment, _) = obtain_contract(depot, batch.deployment.as_deref())?;
+    let chain_batch_id = match batch.chain_batch_id {
+        Some(id) => id,
+        None => return Err(res_bad_request("Token batch has not been created on chain yet")),
+    };
+
+    let call = ContractCall::ConfirmTokenBatchIssue { batch_id: chain_batch_id };
+    // Shares are balances of the invoice contract; there is no separate RBT token to point at
+    let effect = JobEffect::ConfirmBatchIssue {
+        batch_id: batch_id.to_hex(),
+        rbt_token_address: None,
+    };
+    enqueue_chain_job(depot, Some(&deployment), call, effect, format!("batch:{}", batch_id), &actor).await
+}
+
+/// 查询批次状态变更记录 (仅限该批次的债权人、债务人与平台管理员)
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Query, description = "Batch MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Status transitions of the batch, oldest first.", body = Vec<StatusTransitionDto>),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is neither the creditor nor the debtor of the batch."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn batch_history(id: QueryParam<String>, depot: &mut Depot) -> Res<Vec<StatusTransitionDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let lifecycle = LifecycleService::new(&mongodb);
+
+    let oid = match ObjectId::parse_str(&id.into_inner()) {
+        Ok(oid) => oid,
+        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+    };
+
+    let batch = match batch_repo.find_by_id(oid).await {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", oid, e);
+            return Err(res_json_err("Failed to get invoice batch"));
+        }
+    };
+    require_enterprise(depot, &mongodb, &[batch.creditor_id, batch.debtor_id], "Only the creditor or debtor can view the batch history").await?;
+
+    match lifecycle.history(TransitionEntity::InvoiceBatch, oid).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(StatusTransitionDto::from).collect()))),
+        Err(e) => {
+            log::error!("Failed to get history of batch {}: {}", oid, e);
+            Err(res_json_err("Failed to get batch history"))
+        }
+    }
+}
+
+/// 查询批次结算凭证 (仅限该批次的债权人与债务人)
+#[salvo::oapi::endpoint(
+    tags("批次"),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Batch MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Settlement record of the batch.", body = SettlementNftDto),
+        (status_code = 400, description = "Invalid ID format or batch not settled."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is neither creditor nor debtor of the batch."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn batch_settlement(id: PathParam<String>, depot: &mut Depot) -> Res<SettlementNftDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let settlement_service = SettlementService::new(&mongodb);
+
+    let batch_id = ObjectId::parse_str(id.into_inner()).map_err(|_| res_bad_request("Invalid ObjectId format"))?;
+    let enterprise_id = current_enterprise_id(depot, &mongodb).await?;
+
+    let batch = match batch_repo.find_by_id(batch_id).await {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", batch_id, e);
+            return Err(res_json_err("Failed to get invoice batch"));
+        }
+    };
+    if batch.creditor_id != enterprise_id && batch.debtor_id != enterprise_id {
+        return Err(res_json_custom(403, "Only the creditor or debtor can view the settlement"));
+    }
+    if batch.status != InvoiceBatchStatus::Settled {
+        return Err(res_bad_request(&format!("Batch is {:?}, not Settled", batch.status)));
+    }
+
+    // Issuing is idempotent, so this also repairs a record missed at settlement time
+    match settlement_service.issue(&batch).await {
+        Ok(settlement) => Ok(res_json_ok(Some(SettlementNftDto::from(&settlement)))),
+        Err(e) => {
+            log::error!("Failed to get settlement of batch {}: {}", batch_id, e);
+            Err(res_json_err("Failed to get settlement record"))
+        }
+    }
+}
+
+// --- Helper Functions ---
+
+fn parse_batch_invoice_ids(req: &BatchInvoiceRequest) -> Result<(ObjectId, ObjectId), Json<ResObj<()>>> {
+    let batch_id = ObjectId::parse_str(&req.batch_id).map_err(|_| res_bad_request("Invalid batchId format"))?;
+    let invoice_id = ObjectId::parse_str(&req.invoice_id).map_err(|_| res_bad_request("Invalid invoiceId format"))?;
+    Ok((batch_id, invoice_id))
+}
+
+// Members can only change while the batch is still being packaged
+async fn find_packaging_batch(repo: &InvoiceBatchRepository, batch_id: ObjectId) -> Result<InvoiceBatch, Json<ResObj<()>>> {
+    match repo.find_by_id(batch_id).await {
+        Ok(Some(batch)) if batch.status == InvoiceBatchStatus::Packaging => Ok(batch),
+        Ok(Some(batch)) => Err(res_bad_request(&format!("Batch is {:?}, invoices can only change while Packaging", batch.status))),
+        Ok(None) => Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", batch_id, e);
+            Err(res_json_err("Failed to get invoice batch"))
+        }
+    }
+}
+
+async fn find_batch_dto(repo: &InvoiceBatchRepository, batch_id: ObjectId) -> Res<InvoiceBatchDto> {
+    match repo.find_by_id(batch_id).await {
+        Ok(Some(batch)) => Ok(res_json_ok(Some(InvoiceBatchDto::from(&batch)))),
+        Ok(None) => Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", batch_id, e);
+            Err(res_json_err("Failed to get invoice batch"))
+        }
+    }
+}
+
+// Recompute `total_amount` as the sum of the member invoice amounts
+async fn refresh_total_amount(
+    batch_repo: &InvoiceBatchRepository,
+    invoice_repo: &InvoiceRepository,
+    batch_id: ObjectId,
+) -> Res<InvoiceBatchDto> {
+    let invoices = match invoice_repo.find_by_batch(batch_id).await {
+        Ok(invoices) => invoices,
+        Err(e) => {
+            log::error!("Failed to list invoices of batch {}: {}", batch_id, e);
+            return Err(res_json_err("Failed to list invoices of batch"));
+        }
+    };
+    let total: u128 = invoices.iter().map(|invoice| invoice.amount as u128).sum();
+    let total_amount = match Decimal128::from_str(&total.to_string()) {
+        Ok(amount) => amount,
+        Err(e) => {
+            log::error!("Total amount {} of batch {} does not fit Decimal128: {}", total, batch_id, e);
+            return Err(res_json_err("Batch total amount out of range"));
+        }
+    };
+
+    if let Err(e) = batch_repo.update_total_amount(batch_id, total_amount).await {
+        log::error!("Failed to update total amount of batch {}: {}", batch_id, e);
+        return Err(res_json_err("Failed to update batch total amount"));
+    }
+
+    find_batch_dto(batch_repo, batch_id).await
+}
@@ -0,0 +1,161 @@
+use crate::utils::auth::current_user_address;
+use crate::utils::chains::{enqueue_chain_job, obtain_contract};
+use crate::utils::res::{Res, res_bad_request, res_json_err, res_json_ok, res_contract_err};
+use common::domain::dto::chain_job_dto::{ChainJobDto, JobEffect};
+use common::domain::dto::contract_call_dto::ContractCall;
+use common::domain::dto::token_batch_dto::TokenBatchDto;
+use ethers::types::Address;
+use pharos_interact::ContractQuerier;
+use salvo::oapi::{ToSchema, extract::JsonBody, extract::QueryParam};
+use salvo::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Request body for invalidating an invoice on chain
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({ "invoiceNumber": "INV-12345" })))]
+pub struct InvalidateInvoiceRequest {
+    /// 票据编号
+    pub invoice_number: String,
+    /// 合约部署名称, 为空使用默认部署
+    #[serde(default)]
+    pub deployment: Option<String>,
+}
+
+/// 查询链上代币批次 (getTokenBatch)
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    status_codes(200, 400, 500, 503),
+    parameters(
+        ("batch_id" = String, Query, description = "On-chain token batch id"),
+        ("deployment" = Option<String>, Query, description = "Contract deployment name, default deployment when empty")
+    ),
+    responses(
+        (status_code = 200, description = "Token batch data from the contract.", body = TokenBatchDto),
+        (status_code = 400, description = "Missing batch id or unknown deployment."),
+        (status_code = 500, description = "Blockchain query failed."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn get_token_batch(batch_id: QueryParam<String>, deployment: QueryParam<String, false>, depot: &mut Depot) -> Res<TokenBatchDto> {
+    let batch_id = batch_id.into_inner();
+    if batch_id.trim().is_empty() {
+        return Err(res_bad_request("batch_id is required"));
+    }
+    let (_, contract) = obtain_contract(depot, deployment.as_deref())?;
+
+    match contract.get_token_batch(batch_id.clone()).await {
+        Ok(batch) => Ok(res_json_ok(Some(batch))),
+        Err(e) => {
+            log::error!("getTokenBatch for {} failed: {}", batch_id, e);
+            Err(res_contract_err(&e))
+        }
+    }
+}
+
+/// 查询用户参与的链上批次 (getUserBatches)
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    status_codes(200, 400, 500, 503),
+    parameters(
+        ("address" = String, Query, description = "User wallet address"),
+        ("deployment" = Option<String>, Query, description = "Contract deployment name, default deployment when empty")
+    ),
+    responses(
+        (status_code = 200, description = "On-chain batch ids of the user.", body = Vec<String>),
+        (status_code = 400, description = "Invalid wallet address or unknown deployment."),
+        (status_code = 500, description = "Blockchain query failed."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn get_user_batches(address: QueryParam<String>, deployment: QueryParam<String, false>, depot: &mut Depot) -> Res<Vec<String>> {
+    let address = address.into_inner();
+    if address.parse::<Address>().is_err() {
+        return Err(res_bad_request("Invalid wallet address"));
+    }
+    let (_, contract) = obtain_contract(depot, deployment.as_deref())?;
+
+    match contract.get_user_batches(address.clone()).await {
+        Ok(batch_ids) => Ok(res_json_ok(Some(batch_ids))),
+        Err(e) => {
+            log::error!("getUserBatches for {} failed: {}", address, e);
+            Err(res_contract_err(&e))
+        }
+    }
+}
+
+/// 链上作废票据 (invalidateInvoice)，返回异步任务
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    status_codes(200, 400, 401, 403, 500, 503),
+    request_body = InvalidateInvoiceRequest,
+    responses(
+        (status_code = 200, description = "invalidateInvoice queued.", body = ChainJobDto),
+        (status_code = 400, description = "Missing invoice number or unknown deployment."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+        (status_code = 500, description = "Failed to queue the job."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn invalidate_invoice(req: JsonBody<InvalidateInvoiceRequest>, depot: &mut Depot) -> Res<ChainJobDto> {
+    let actor = current_user_address(depot)?;
+    if req.invoice_number.trim().is_empty() {
+        return Err(res_bad_request("invoiceNumber is required"));
+    }
+    let (deployment, _) = obtain_contract(depot, req.deployment.as_deref())?;
+
+    log::info!("User {} invalidating invoice {} on chain '{}'", actor, req.invoice_number, deployment);
+    let call = ContractCall::InvalidateInvoice { invoice_number: req.invoice_number.clone() };
+    enqueue_chain_job(depot, Some(&deployment), call, JobEffect::None, format!("invoice:{}", req.invoice_number), &actor).await
+}
+
+/// 暂停合约 (pause)，返回异步任务
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    status_codes(200, 400, 401, 403, 500, 503),
+    parameters(
+        ("deployment" = Option<String>, Query, description = "Contract deployment name, default deployment when empty")
+    ),
+    responses(
+        (status_code = 200, description = "pause queued.", body = ChainJobDto),
+        (status_code = 400, description = "Unknown deployment."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+        (status_code = 500, description = "Failed to queue the job."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn pause_contract(deployment: QueryParam<String, false>, depot: &mut Depot) -> Res<ChainJobDto> {
+    let actor = current_user_address(depot)?;
+    let (deployment, contract) = obtain_contract(depot, deployment.as_deref())?;
+
+    log::info!("User {} pausing contract {} on '{}'", actor, contract.contract_address(), deployment);
+    enqueue_chain_job(depot, Some(&deployment), ContractCall::Pause, JobEffect::None, format!("contract:{}", contract.contract_address()), &actor).await
+}
+
+/// 恢复合约 (unpause)，返回异步任务
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    status_codes(200, 400, 401, 403, 500, 503),
+    parameters(
+        ("deployment" = Option<String>, Query, description = "Contract deployment name, default deployment when empty")
+    ),
+    responses(
+        (status_code = 200, description = "unpause queued.", body = ChainJobDto),
+        (status_code = 400, description = "Unknown deployment."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+        (status_code = 500, description = "Failed to queue the job."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn unpause_contract(deployment: QueryParam<String, false>, depot: &mut Depot) -> Res<ChainJobDto> {
+    let actor = current_user_address(depot)?;
+    let (deployment, contract) = obtain_contract(depot, deployment.as_deref())?;
+
+    log::info!("User {} unpausing contract {} on '{}'", actor, contract.contract_address(), deployment);
+    enqueue_chain_job(depot, Some(&deployment), ContractCall::Unpause, JobEffect::None, format!("contract:{}", contract.contract_address()), &actor).await
+}
@@ -1,8 +1,13 @@
-use salvo::{Depot, FlowCtrl, Request, Response, handler, prelude::StatusCode};
+use salvo::{Depot, FlowCtrl, Handler, Request, Response, async_trait, handler, prelude::StatusCode};
+use common::domain::entity::UserRole;
 use crate::utils::res::res_json_custom;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation, Algorithm};
 use configs::CFG; // Assuming your JWT secret is in CFG
 use crate::controller::Claims; // Import the Claims struct
+use service::{ApiKeyService, SessionStore, SignedRequest};
+use mongodb::Database;
+use redis::Client as RedisClient;
+use std::sync::Arc;
 
 #[handler]
 pub async fn auth_token(req: &mut Request, res: &mut Response, ctrl: &mut FlowCtrl, depot: &mut Depot) {
@@ -24,10 +29,28 @@ pub async fn auth_token(req: &mut Request, res: &mut Response, ctrl: &mut FlowCt
 
             match decode::<Claims>(&token, &decoding_key, &validation) {
                 Ok(token_data) => {
+                    // Reject tokens of sessions ended by logout or revoked by the user
+                    let sessions = depot.obtain::<Arc<SessionStore>>().expect("Session store not found").clone();
+                    match sessions.is_revoked(&token_data.claims.sid).await {
+                        Ok(false) => {}
+                        Ok(true) => {
+                            ctrl.skip_rest();
+                            res.render(res_json_custom::<()>(401, "Token revoked"));
+                            return;
+                        }
+                        Err(e) => {
+                            log::error!("Failed to check token revocation: {}", e);
+                            ctrl.skip_rest();
+                            res.render(res_json_custom::<()>(500, "Failed to verify token"));
+                            return;
+                        }
+                    }
                     // Token is valid, extract the user_address (subject)
                     let user_address = token_data.claims.sub;
-                    // Inject the user_address into the depot
+                    // Inject the user_address and session into the depot
                     depot.insert("user_address", user_address);
+                    depot.insert("session_id", token_data.claims.sid);
+                    depot.insert("user_role", token_data.claims.role);
                     // Continue to the next handler
                     // ctrl.call_next(req, depot, res).await; // call_next is implicitly called if not skipped
                 }
@@ -49,6 +72,91 @@ pub async fn auth_token(req: &mut Request, res: &mut Response, ctrl: &mut FlowCt
     }
 }
 
+/// Authenticate ERP requests signed with an enterprise API key (HMAC-SHA256).
+/// Headers: `X-Api-Key`, `X-Timestamp` (Unix seconds), `X-Nonce` (single use) and `X-Signature`,
+/// the hex HMAC of `service::api_key::canonical_request` over method, path with query, timestamp,
+/// nonce and body. Puts the key's enterprise into the depot as "enterprise_id".
+#[handler]
+pub async fn api_key_auth(req: &mut Request, res: &mut Response, ctrl: &mut FlowCtrl, depot: &mut Depot) {
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let (Some(key_id), Some(timestamp), Some(nonce), Some(signature)) = (header("X-Api-Key"), header("X-Timestamp"), header("X-Nonce"), header("X-Signature")) else {
+        ctrl.skip_rest();
+        res.render(res_json_custom::<()>(401, "API key headers missing"));
+        return;
+    };
+    let Ok(timestamp) = timestamp.parse::<i64>() else {
+        ctrl.skip_rest();
+        res.render(res_json_custom::<()>(401, "Invalid X-Timestamp"));
+        return;
+    };
+    let method = req.method().to_string();
+    let path = req.uri().path_and_query().map(|p| p.as_str().to_string()).unwrap_or_else(|| req.uri().path().to_string());
+    // The body is cached by the request, so handlers can still extract it afterwards
+    let body = match req.payload().await {
+        Ok(body) => body.clone(),
+        Err(e) => {
+            log::warn!("Failed to read body of API key request: {}", e);
+            ctrl.skip_rest();
+            res.render(res_json_custom::<()>(400, "Invalid request body"));
+            return;
+        }
+    };
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let redis_client = depot.obtain::<Arc<RedisClient>>().expect("Redis client not found").clone();
+    let request = SignedRequest { key_id: &key_id, timestamp, nonce: &nonce, signature: &signature, method: &method, path: &path, body: &body };
+    let keys = ApiKeyService::new(&mongodb, &CFG.api_keys.master_secret);
+    match keys.authenticate(&redis_client, &request, chrono::Utc::now().timestamp(), CFG.api_keys.max_skew_secs).await {
+        Ok(key) => {
+            depot.insert("enterprise_id", key.enterprise_id);
+            depot.insert("api_key_id", key.key_id);
+        }
+        Err(e) if e.is_rejection() => {
+            log::warn!("API key request {} {} with key {} rejected: {}", method, path, key_id, e);
+            ctrl.skip_rest();
+            res.render(res_json_custom::<()>(401, &e.to_string()));
+        }
+        Err(e) => {
+            log::error!("Failed to verify API key request: {}", e);
+            ctrl.skip_rest();
+            res.render(res_json_custom::<()>(500, "Failed to verify API key"));
+        }
+    }
+}
+
+/// Router hoop admitting only users whose role is one of `roles`; runs after `auth_token`.
+pub struct RequireRole {
+    roles: Vec<UserRole>,
+}
+
+impl RequireRole {
+    pub fn any_of(roles: &[UserRole]) -> Self {
+        Self { roles: roles.to_vec() }
+    }
+
+    pub fn platform_admin() -> Self {
+        Self::any_of(&[UserRole::PlatformAdmin])
+    }
+}
+
+#[async_trait]
+impl Handler for RequireRole {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        match depot.get::<UserRole>("user_role") {
+            Ok(role) if self.roles.contains(role) => {}
+            Ok(role) => {
+                log::warn!("{:?} denied access to {} {}", role, req.method(), req.uri().path());
+                ctrl.skip_rest();
+                res.render(res_json_custom::<()>(403, "Insufficient permissions"));
+            }
+            Err(_) => {
+                ctrl.skip_rest();
+                res.render(res_json_custom::<()>(401, "User not authenticated"));
+            }
+        }
+    }
+}
+
 #[handler]
 pub async fn catcher_err(req: &mut Request, res: &mut Response, ctrl: &mut FlowCtrl) {
     // 记录请求基本信息
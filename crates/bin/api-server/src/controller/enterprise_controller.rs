@@ -141,7 +141,7 @@ pub async fn list_enterprises(depot: &mut Depot) -> Res<Vec<EnterpriseDto>> {
 /// 更新企业信息
 #[salvo::oapi::endpoint(
     tags("企业"),
-    status_codes(200, 400, 404, 500),
+    status_codes(200, 400, 401, 403, 404, 500),
     parameters(
         ("id" = String, Path, description = "Enterprise MongoDB ObjectId")
     ),
@@ -149,6 +149,8 @@ pub async fn list_enterprises(depot: &mut Depot) -> Res<Vec<EnterpriseDto>> {
     responses(
         (status_code = 200, description = "Enterprise updated successfully."),
         (status_code = 400, description = "Invalid ID format or request data."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
         (status_code = 404, description = "Enterprise not found."),
         (status_code = 500, description = "Internal server error."),
     )
@@ -188,13 +190,15 @@ pub async fn update_enterprise(id: PathParam<String>, req: JsonBody<UpdateEnterp
 /// 删除企业
 #[salvo::oapi::endpoint(
     tags("企业"),
-    status_codes(200, 400, 404, 500),
+    status_codes(200, 400, 401, 403, 404, 500),
     parameters(
         ("id" = String, Query, description = "Enterprise MongoDB ObjectId")
     ),
     responses(
         (status_code = 200, description = "Enterprise deleted successfully."),
         (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
         (status_code = 404, description = "Enterprise not found."),
         (status_code = 500, description = "Internal server error."),
     )
@@ -0,0 +1,156 @@
+use crate::utils::res::{Res, ResObj, res_bad_request, res_json_custom, res_json_err, res_json_ok, res_not_found};
+use common::domain::dto::invoice_dto::InvoiceDataDto;
+use common::domain::entity::invoice::InvoiceDto;
+use mongodb::{Database, bson::oid::ObjectId};
+use salvo::{
+    oapi::extract::{JsonBody, QueryParam},
+    prelude::*,
+};
+use service::EnterpriseRepository;
+use service::repository::InvoiceRepository;
+use service::db::is_duplicate_key;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Enterprise of the API key the request was signed with (set by `api_key_auth`)
+fn key_enterprise_id(depot: &Depot) -> Result<ObjectId, Json<ResObj<()>>> {
+    match depot.get::<ObjectId>("enterprise_id") {
+        Ok(enterprise_id) => Ok(*enterprise_id),
+        Err(_) => Err(res_json_custom(401, "API key not authenticated")),
+    }
+}
+
+/// ERP 推送票据 (API Key 签名认证)，收款方 (payee) 须为 API Key 所属企业
+#[salvo::oapi::endpoint(
+    tags("ERP"),
+    status_codes(200, 400, 401, 403, 500),
+    request_body = Vec<InvoiceDataDto>,
+    responses(
+        (status_code = 200, description = "Invoices stored.", body = Vec<InvoiceDto>),
+        (status_code = 400, description = "Empty invoice list, invalid amount, missing or duplicate invoice number; nothing is stored then."),
+        (status_code = 401, description = "Missing, stale, replayed or invalid API key signature."),
+        (status_code = 403, description = "An invoice's payee is not the API key's enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn create_invoices(req: JsonBody<Vec<InvoiceDataDto>>, depot: &mut Depot) -> Res<Vec<InvoiceDto>> {
+    let enterprise_id = key_enterprise_id(depot)?;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let repo = InvoiceRepository::new(&mongodb);
+
+    let invoices = req.into_inner();
+    if invoices.is_empty() {
+        return Err(res_bad_request("At least one invoice is required"));
+    }
+    if invoices.iter().any(|invoice| invoice.invoice_number.trim().is_empty()) {
+        return Err(res_bad_request("invoiceNumber is required"));
+    }
+    let mut numbers = HashSet::with_capacity(invoices.len());
+    if let Some(repeated) = invoices.iter().find(|invoice| !numbers.insert(invoice.invoice_number.as_str())) {
+        return Err(res_bad_request(&format!("Invoice {} appears more than once in the request", repeated.invoice_number)));
+    }
+
+    let wallet = match EnterpriseRepository::new(&mongodb).find_by_id(enterprise_id).await {
+        Ok(Some(enterprise)) => enterprise.wallet_address,
+        Ok(None) => return Err(res_json_custom(403, "Enterprise of the API key not found")),
+        Err(e) => {
+            log::error!("Failed to get enterprise {}: {}", enterprise_id, e);
+            return Err(res_json_err("Failed to get enterprise"));
+        }
+    };
+    for invoice in &invoices {
+        if !invoice.payee.eq_ignore_ascii_case(&wallet) {
+            log::warn!("API key of enterprise {} denied creating invoice {} for payee {}", enterprise_id, invoice.invoice_number, invoice.payee);
+            return Err(res_json_custom(403, "Only the creditor enterprise can create its invoices"));
+        }
+        match repo.find_by_invoice_number(&invoice.invoice_number).await {
+            Ok(None) => {}
+            Ok(Some(_)) => return Err(res_bad_request(&format!("Invoice {} already exists", invoice.invoice_number))),
+            Err(e) => {
+                log::error!("Failed to look up invoice {}: {}", invoice.invoice_number, e);
+                return Err(res_json_err("Failed to create invoices"));
+            }
+        }
+    }
+
+    // Build every invoice first so a bad one fails the request before anything is written
+    let mut pending = Vec::with_capacity(invoices.len());
+    for invoice in &invoices {
+        match repo.build_from_blockchain(invoice, None).await {
+            Ok(built) => pending.push(built),
+            // Custom errors are the DTO's own: an amount that does not fit
+            Err(e) if !matches!(*e.kind, mongodb::error::ErrorKind::Custom(_)) => {
+                log::error!("Failed to prepare invoice {} pushed by ERP of enterprise {}: {}", invoice.invoice_number, enterprise_id, e);
+                return Err(res_json_err("Failed to create invoices"));
+            }
+            Err(e) => {
+                log::error!("Invalid invoice {} pushed by ERP of enterprise {}: {}", invoice.invoice_number, enterprise_id, e);
+                return Err(res_bad_request(&format!("Invalid invoice {}", invoice.invoice_number)));
+            }
+        }
+    }
+    let created: Vec<InvoiceDto> = match repo.insert_all(pending).await {
+        Ok(stored) => stored.iter().map(InvoiceDto::from).collect(),
+        // Lost a race with another request creating the same invoice number
+        Err(e) if is_duplicate_key(&e) => return Err(res_bad_request("Invoice already exists")),
+        Err(e) => {
+            log::error!("Failed to create invoices pushed by ERP of enterprise {}: {}", enterprise_id, e);
+            return Err(res_json_err("Failed to create invoices"));
+        }
+    };
+    log::info!("ERP of enterprise {} pushed {} invoices", enterprise_id, created.len());
+    Ok(res_json_ok(Some(created)))
+}
+
+/// ERP 查询本企业作为债权人的票据
+#[salvo::oapi::endpoint(
+    tags("ERP"),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "Invoices of the API key's enterprise.", body = Vec<InvoiceDto>),
+        (status_code = 401, description = "Missing, stale, replayed or invalid API key signature."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_invoices(depot: &mut Depot) -> Res<Vec<InvoiceDto>> {
+    let enterprise_id = key_enterprise_id(depot)?;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+
+    match InvoiceRepository::new(&mongodb).find_by_creditor(enterprise_id).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(InvoiceDto::from).collect()))),
+        Err(e) => {
+            log::error!("Failed to list invoices of enterprise {}: {}", enterprise_id, e);
+            Err(res_json_err("Failed to list invoices"))
+        }
+    }
+}
+
+/// ERP 查询本企业的单张票据 (债权人或债务人)
+#[salvo::oapi::endpoint(
+    tags("ERP"),
+    status_codes(200, 401, 404, 500),
+    parameters(
+        ("invoice_number" = String, Query, description = "Invoice number to query")
+    ),
+    responses(
+        (status_code = 200, description = "Invoice found.", body = InvoiceDto),
+        (status_code = 401, description = "Missing, stale, replayed or invalid API key signature."),
+        (status_code = 404, description = "Invoice not found or not an invoice of the API key's enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn invoice_detail(invoice_number: QueryParam<String>, depot: &mut Depot) -> Res<InvoiceDto> {
+    let enterprise_id = key_enterprise_id(depot)?;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let invoice_number = invoice_number.into_inner();
+
+    match InvoiceRepository::new(&mongodb).find_by_invoice_number(&invoice_number).await {
+        // Other enterprises' invoices are reported as missing rather than forbidden
+        Ok(Some(invoice)) if invoice.creditor_id == enterprise_id || invoice.debtor_id == enterprise_id => Ok(res_json_ok(Some(InvoiceDto::from(&invoice)))),
+        Ok(_) => Err(res_not_found("Invoice not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice {}: {}", invoice_number, e);
+            Err(res_json_err("Failed to get invoice"))
+        }
+    }
+}
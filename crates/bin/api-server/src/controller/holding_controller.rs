@@ -0,0 +1,171 @@
+use crate::utils::auth::current_user_address;
+use crate::utils::chains::obtain_contract;
+use crate::utils::res::{Res, ResObj, res_bad_request, res_contract_err, res_json_custom, res_json_err, res_json_ok, res_lifecycle_err, res_not_found};
+use common::domain::entity::rbt_holding::RbtHoldingDto;
+use common::domain::entity::SharePurchaseDto;
+use common::utils::decimal::is_positive_integer;
+use mongodb::{
+    Database,
+    bson::{DateTime, Decimal128, oid::ObjectId},
+};
+use pharos_interact::ContractQuerier;
+use salvo::oapi::{ToSchema, extract::JsonBody};
+use salvo::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+use service::repository::{InvoiceBatchRepository, RbtHoldingRepository, UserRepository};
+use service::{PurchaseError, PurchaseService};
+use std::str::FromStr;
+use std::sync::Arc;
+
+// --- Request DTOs ---
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({
+    "batchId": "6610c1f2a3b4c5d6e7f80912",
+    "transactionHash": "0x1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b"
+})))]
+pub struct PurchaseSharesRequest {
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// 投资者钱包发送的 purchaseShares 交易哈希
+    pub transaction_hash: String,
+}
+
+// --- Handlers ---
+
+/// 投资者登记购买份额交易 (投资者钱包自行调用 purchaseShares 并支付稳定币, 链上校验后计入持仓)
+#[salvo::oapi::endpoint(
+    tags("持仓"),
+    status_codes(200, 400, 401, 403, 404, 409, 500, 503),
+    request_body = PurchaseSharesRequest,
+    responses(
+        (status_code = 200, description = "Purchase verified and credited to the holding.", body = SharePurchaseDto),
+        (status_code = 400, description = "Invalid input, batch not open for purchase, transaction not mined/failed or not a purchaseShares call of the batch."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Transaction was not sent from the user's wallet."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 409, description = "Transaction already registered and credited."),
+        (status_code = 500, description = "Internal server error."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn purchase_shares(req: JsonBody<PurchaseSharesRequest>, depot: &mut Depot) -> Res<SharePurchaseDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+
+    let user_address = current_user_address(depot)?;
+    let user_id = current_user_id(&mongodb, &user_address).await?;
+
+    let req = req.into_inner();
+    let batch_id = ObjectId::parse_str(&req.batch_id).map_err(|_| res_bad_request("Invalid batchId format"))?;
+    let tx_hash = req.transaction_hash.trim().to_lowercase();
+
+    let batch = match batch_repo.find_by_id(batch_id).await {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", batch_id, e);
+            return Err(res_json_err("Failed to get invoice batch"));
+        }
+    };
+    // Whether the batch is open for purchase is checked when recording, so a purchase whose
+    // credit failed can still be completed after the batch moved on
+    let chain_batch_id = match batch.chain_batch_id {
+        Some(id) => id,
+        None => return Err(res_bad_request("Batch has no on-chain token batch")),
+    };
+    // The purchase is looked up on the chain the batch was issued on
+    let (_, contract) = obtain_contract(depot, batch.deployment.as_deref())?;
+
+    let receipt = match contract.get_purchase_receipt(tx_hash.clone()).await {
+        Ok(Some(receipt)) => receipt,
+        Ok(None) => return Err(res_bad_request("Transaction is not mined yet")),
+        Err(e) => {
+            log::error!("Failed to fetch purchase transaction {}: {}", tx_hash, e);
+            return Err(res_contract_err(&e));
+        }
+    };
+    if !receipt.success {
+        return Err(res_bad_request("Purchase transaction reverted"));
+    }
+    // Shares are held by the sender on chain, so only the investor's own purchase is credited to them
+    if !receipt.buyer.eq_ignore_ascii_case(&user_address) {
+        return Err(res_json_custom(403, "Purchase was not sent from the user's wallet"));
+    }
+    if receipt.batch_id != chain_batch_id {
+        return Err(res_bad_request("Transaction purchases shares of another batch"));
+    }
+    let amount = parse_share_amount(&receipt.amount)?;
+
+    let purchase_timestamp = DateTime::from_millis(receipt.block_timestamp as i64 * 1000);
+    match PurchaseService::new(&mongodb).record(batch_id, user_id, &receipt.buyer, amount, &tx_hash, purchase_timestamp, &user_address).await {
+        Ok(purchase) => {
+            log::info!("Purchase of {} shares of batch {} by {} recorded (tx {})", amount, batch_id, user_address, tx_hash);
+            Ok(res_json_ok(Some(SharePurchaseDto::from(&purchase))))
+        }
+        Err(e) => {
+            log::error!("Failed to record purchase {} for batch {}: {}", tx_hash, batch_id, e);
+            Err(res_purchase_err(&e))
+        }
+    }
+}
+
+/// 查询我的持仓
+#[salvo::oapi::endpoint(
+    tags("持仓"),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "Holdings of the authenticated user.", body = Vec<RbtHoldingDto>),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn my_holdings(depot: &mut Depot) -> Res<Vec<RbtHoldingDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let holding_repo = RbtHoldingRepository::new(&mongodb);
+
+    let user_address = current_user_address(depot)?;
+    let user_id = current_user_id(&mongodb, &user_address).await?;
+
+    match holding_repo.find_by_user(user_id).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(RbtHoldingDto::from).collect()))),
+        Err(e) => {
+            log::error!("Failed to list holdings of {}: {}", user_address, e);
+            Err(res_json_err("Failed to list holdings"))
+        }
+    }
+}
+
+// --- Helper Functions ---
+
+async fn current_user_id(mongodb: &Database, user_address: &str) -> Result<ObjectId, Json<ResObj<()>>> {
+    match UserRepository::new(mongodb).find_by_wallet_address(user_address).await {
+        Ok(Some(user)) => user.id.ok_or_else(|| res_json_err("User has no id")),
+        Ok(None) => Err(res_json_custom(401, "User not found")),
+        Err(e) => {
+            log::error!("Failed to load user {}: {}", user_address, e);
+            Err(res_json_err("Failed to load user"))
+        }
+    }
+}
+
+fn res_purchase_err(e: &PurchaseError) -> Json<ResObj<()>> {
+    match e {
+        PurchaseError::BatchNotFound(_) => res_not_found("Batch not found"),
+        PurchaseError::NotPurchasable(_) => res_bad_request(&e.to_string()),
+        PurchaseError::DuplicateTransaction(_) => res_json_custom(409, &e.to_string()),
+        PurchaseError::AmountOutOfRange(_) => res_json_err(&e.to_string()),
+        PurchaseError::Lifecycle(e) => res_lifecycle_err(e),
+        PurchaseError::Database(_) => res_json_err("Database error"),
+    }
+}
+
+// Share amounts are whole token units, as expected by purchaseShares(uint256)
+fn parse_share_amount(amount: &str) -> Result<Decimal128, Json<ResObj<()>>> {
+    let decimal = Decimal128::from_str(amount).map_err(|_| res_bad_request("Invalid amount format"))?;
+    if !is_positive_integer(&decimal) {
+        return Err(res_bad_request("Amount must be a positive integer"));
+    }
+    Ok(decimal)
+}
@@ -1,17 +1,16 @@
-use crate::utils::res::{Res, res_bad_request, res_json_err, res_json_ok, res_not_found};
+use crate::utils::res::{Res, ResObj, res_bad_request, res_json_custom, res_json_err, res_json_ok, res_not_found, res_contract_err, res_lifecycle_err};
+use common::domain::dto::chain_job_dto::{ChainJobDto, JobEffect};
+use common::domain::dto::contract_call_dto::ContractCall;
 use common::domain::dto::invoice_dto::InvoiceDataDto;
 use common::domain::dto::query_invoice_dto::QueryParamsDto;
 use common::domain::entity::enterprise::EnterpriseDto;
 use common::domain::entity::invoice::InvoiceDto;
 use common::domain::entity::{Invoice, InvoiceStatus};
-use ethers::middleware::SignerMiddleware;
-use ethers::providers::{Http, Provider};
-use ethers::signers::LocalWallet;
 use mongodb::{
     Database,
     bson::{DateTime, Decimal128, oid::ObjectId},
 };
-use pharos_interact::{ContractQuerier, InvoiceContract};
+use pharos_interact::{ContractQuerier, ContractWriter};
 use salvo::{
     oapi::{ToSchema, extract::JsonBody, extract::PathParam, extract::QueryParam},
     prelude::*,
@@ -19,20 +18,36 @@ use salvo::{
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use service::repository::InvoiceRepository;
-use service::repository::invoice_repository::UpdateInvoiceData;
+use service::LifecycleService;
+use common::domain::entity::TransitionEntity;
+use common::domain::entity::status_transition::StatusTransitionDto;
+use crate::utils::auth::{current_enterprise_id, current_user_address, is_platform_admin, require_enterprise};
+use crate::utils::chains::{enqueue_chain_job, obtain_contract};
+use service::EnterpriseRepository;
 use std::convert::From;
 use std::str::FromStr;
 use std::sync::Arc;
 
+// --- Request DTOs ---
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({ "invoiceId": "6610c1f2a3b4c5d6e7f80913" })))]
+pub struct VerifyInvoiceRequest {
+    /// 票据ID (Database ObjectId)
+    pub invoice_id: String,
+}
+
 // --- Handlers ---
 /// 创建一个票据 (Standard endpoint for creating invoice directly in DB)
 #[salvo::oapi::endpoint(
     tags("票据"),
-    status_codes(200, 400, 500),
+    status_codes(200, 400, 401, 403, 500),
     request_body = InvoiceDataDto,
     responses(
         (status_code = 200, description = "Invoice created successfully.", body = InvoiceDto),
         (status_code = 400, description = "Invalid request data."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not the creditor (payee) enterprise of the invoice."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
@@ -50,8 +65,9 @@ pub async fn create_invoice(req: JsonBody<InvoiceDataDto>, depot: &mut Depot) ->
     };
 
     let data = req.into_inner();
+    require_own_payee(depot, &mongodb, std::iter::once(data.payee.as_str())).await?;
     log::warn!("create_invoice:{:?}", data.clone());
-    match repo.create_from_blockchain(&data).await {
+    match repo.create_from_blockchain(&data, None).await {
         Ok(invoice) => {
             // Convert the created Invoice entity to InvoiceDto for the response
             let data = InvoiceDto::from(&invoice);
@@ -64,6 +80,42 @@ pub async fn create_invoice(req: JsonBody<InvoiceDataDto>, depot: &mut Depot) ->
     }
 }
 
+/// 在链上批量创建票据 (batchCreateInvoices)，返回异步任务，确认后票据写入数据库
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    status_codes(200, 400, 401, 403, 500, 503),
+    request_body = Vec<InvoiceDataDto>,
+    parameters(
+        ("deployment" = Option<String>, Query, description = "Contract deployment name, default deployment when empty")
+    ),
+    responses(
+        (status_code = 200, description = "batchCreateInvoices queued; the invoices are stored once confirmed.", body = ChainJobDto),
+        (status_code = 400, description = "Empty invoice list, missing invoice number or unknown deployment."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not the creditor (payee) enterprise of every invoice."),
+        (status_code = 500, description = "Failed to queue the job."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn chain_create_invoices(req: JsonBody<Vec<InvoiceDataDto>>, deployment: QueryParam<String, false>, depot: &mut Depot) -> Res<ChainJobDto> {
+    let actor = current_user_address(depot)?;
+    let (deployment, _) = obtain_contract(depot, deployment.as_deref())?;
+
+    let invoices = req.into_inner();
+    if invoices.is_empty() {
+        return Err(res_bad_request("At least one invoice is required"));
+    }
+    if invoices.iter().any(|invoice| invoice.invoice_number.trim().is_empty()) {
+        return Err(res_bad_request("invoiceNumber is required"));
+    }
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    require_own_payee(depot, &mongodb, invoices.iter().map(|invoice| invoice.payee.as_str())).await?;
+
+    let reference = format!("invoices:{}", invoices.iter().map(|invoice| invoice.invoice_number.as_str()).collect::<Vec<_>>().join(","));
+    let call = ContractCall::BatchCreateInvoices { invoices };
+    enqueue_chain_job(depot, Some(&deployment), call, JobEffect::ImportInvoices, reference, &actor).await
+}
+
 /// 查询所有票据
 #[salvo::oapi::endpoint(
     tags("票据"),
@@ -96,13 +148,15 @@ pub async fn list_invoices(depot: &mut Depot) -> Res<Vec<InvoiceDto>> {
 /// 删除票据
 #[salvo::oapi::endpoint(
     tags("票据"),
-    status_codes(200, 400, 404, 500),
+    status_codes(200, 400, 401, 403, 404, 500),
     parameters(
         ("id" = String, Query, description = "Invoice MongoDB ObjectId")
     ),
     responses(
         (status_code = 200, description = "Invoice deleted successfully."),
         (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
         (status_code = 404, description = "Invoice not found."),
         (status_code = 500, description = "Internal server error."),
     )
@@ -131,6 +185,85 @@ pub async fn delete_invoice(invoice_number: QueryParam<String>, depot: &mut Depo
     }
 }
 
+/// 审核票据 (Pending -> Verified)
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    status_codes(200, 400, 401, 403, 404, 409, 500),
+    request_body = VerifyInvoiceRequest,
+    responses(
+        (status_code = 200, description = "Invoice verified.", body = InvoiceDto),
+        (status_code = 400, description = "Invalid ID format or illegal transition."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Requires the PlatformAdmin role."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "Invoice status changed concurrently."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn verify_invoice(req: JsonBody<VerifyInvoiceRequest>, depot: &mut Depot) -> Res<InvoiceDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let lifecycle = LifecycleService::new(&mongodb);
+
+    let actor = current_user_address(depot)?;
+    let oid = match ObjectId::parse_str(&req.invoice_id) {
+        Ok(oid) => oid,
+        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+    };
+
+    match lifecycle.transition_invoice(oid, InvoiceStatus::Verified, &actor).await {
+        Ok(invoice) => Ok(res_json_ok(Some(InvoiceDto::from(&invoice)))),
+        Err(e) => {
+            log::error!("Failed to verify invoice {}: {}", oid, e);
+            Err(res_lifecycle_err(&e))
+        }
+    }
+}
+
+/// 查询票据状态变更记录 (仅限该票据的债权人、债务人与平台管理员)
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Query, description = "Invoice MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Status transitions of the invoice, oldest first.", body = Vec<StatusTransitionDto>),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is neither the creditor nor the debtor of the invoice."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn invoice_history(id: QueryParam<String>, depot: &mut Depot) -> Res<Vec<StatusTransitionDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let invoice_repo = InvoiceRepository::new(&mongodb);
+    let lifecycle = LifecycleService::new(&mongodb);
+
+    let oid = match ObjectId::parse_str(&id.into_inner()) {
+        Ok(oid) => oid,
+        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+    };
+
+    let invoice = match invoice_repo.find_by_id(oid).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => return Err(res_not_found("Invoice not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice {}: {}", oid, e);
+            return Err(res_json_err("Failed to get invoice"));
+        }
+    };
+    require_enterprise(depot, &mongodb, &[invoice.creditor_id, invoice.debtor_id], "Only the creditor or debtor can view the invoice history").await?;
+
+    match lifecycle.history(TransitionEntity::Invoice, oid).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(StatusTransitionDto::from).collect()))),
+        Err(e) => {
+            log::error!("Failed to get history of invoice {}: {}", oid, e);
+            Err(res_json_err("Failed to get invoice history"))
+        }
+    }
+}
+
 /// 查询与我相关的票据（仅查询数据库）
 #[salvo::oapi::endpoint(
     tags("票据"),
@@ -213,15 +346,15 @@ pub async fn query_invoice_data(invoice_number: QueryParam<String>, depot: &mut
 
 // Helper function to query blockchain and save to DB
 async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot, repo: &InvoiceRepository) -> Res<Vec<InvoiceDto>> {
-    // Try to get contract connection
-    let contract_opt = depot.obtain::<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>();
-
-    if contract_opt.is_err() {
-        log::warn!("Blockchain contract connection not available.");
-        // Return Not Found as we couldn't check the canonical source
-        return Err(res_not_found("Invoice not found and blockchain connection unavailable"));
-    }
-    let contract = contract_opt.unwrap();
+    // Try to get the default deployment's contract connection
+    let (deployment, contract) = match obtain_contract(depot, None) {
+        Ok(found) => found,
+        Err(_) => {
+            log::warn!("Blockchain contract connection not available.");
+            // Return Not Found as we couldn't check the canonical source
+            return Err(res_not_found("Invoice not found and blockchain connection unavailable"));
+        }
+    };
 
     // Prepare blockchain query parameters
     let mut params = QueryParamsDto {
@@ -270,7 +403,7 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
                         Ok(None) => {
                             // 票据不存在，可以安全地尝试创建
                             log::debug!("Invoice {} not found in DB, attempting to create from blockchain data.", invoice_data_dto.invoice_number);
-                            match repo.create_from_blockchain(&invoice_data_dto).await {
+                            match repo.create_from_blockchain(&invoice_data_dto, Some(&deployment)).await {
                                 Ok(saved_invoice) => {
                                     log::info!("Successfully saved new invoice {} from blockchain to DB.", saved_invoice.invoice_number);
                                     saved_invoice_dtos.push(InvoiceDto::from(&saved_invoice));
@@ -300,7 +433,30 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
         }
         Err(e) => {
             log::error!("Failed to query blockchain for invoice {}: {}", invoice_number, e);
-            Err(res_json_err(&format!("Blockchain query failed: {}", e)))
+            Err(res_contract_err(&e))
+        }
+    }
+}
+
+// Invoices are created by their creditor: every payee must be the wallet of the user's enterprise
+async fn require_own_payee<'a>(depot: &Depot, mongodb: &Database, payees: impl Iterator<Item = &'a str>) -> Result<(), Json<ResObj<()>>> {
+    if is_platform_admin(depot) {
+        return Ok(());
+    }
+    let enterprise_id = current_enterprise_id(depot, mongodb).await?;
+    let wallet = match EnterpriseRepository::new(mongodb).find_by_id(enterprise_id).await {
+        Ok(Some(enterprise)) => enterprise.wallet_address,
+        Ok(None) => return Err(res_json_custom(403, "User is not bound to an enterprise")),
+        Err(e) => {
+            log::error!("Failed to get enterprise {}: {}", enterprise_id, e);
+            return Err(res_json_err("Failed to get enterprise"));
+        }
+    };
+    for payee in payees {
+        if !payee.eq_ignore_ascii_case(&wallet) {
+            log::warn!("Enterprise {} denied creating an invoice for payee {}", enterprise_id, payee);
+            return Err(res_json_custom(403, "Only the creditor enterprise can create its invoices"));
         }
     }
+    Ok(())
 }
@@ -0,0 +1,105 @@
+use crate::utils::auth::current_user_address;
+use crate::utils::res::{Res, ResObj, res_json_custom, res_json_err, res_json_ok, res_not_found};
+use common::domain::dto::chain_job_dto::{ChainJob, ChainJobDto, ChainJobStatus};
+use futures::stream;
+use redis::Client as RedisClient;
+use salvo::oapi::extract::PathParam;
+use salvo::prelude::*;
+use salvo::sse::{self, SseEvent};
+use service::JobQueue;
+use std::sync::Arc;
+use std::time::Duration;
+
+const EVENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 查询链上写入任务状态
+#[salvo::oapi::endpoint(
+    tags("任务"),
+    status_codes(200, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Chain job id")
+    ),
+    responses(
+        (status_code = 200, description = "Current status of the job, with the receipt once mined.", body = ChainJobDto),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Job was requested by another user."),
+        (status_code = 404, description = "Job not found or expired."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_job(id: PathParam<String>, depot: &mut Depot) -> Res<ChainJobDto> {
+    let job = find_own_job(depot, &id.into_inner()).await?;
+    Ok(res_json_ok(Some(ChainJobDto::from(&job))))
+}
+
+/// 订阅链上写入任务状态 (SSE)，每次状态变化推送一个事件，任务结束后关闭
+#[salvo::oapi::endpoint(
+    tags("任务"),
+    status_codes(200, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Chain job id")
+    ),
+    responses(
+        (status_code = 200, description = "text/event-stream of ChainJobDto events named queued/sent/confirmed/reverted/failed."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Job was requested by another user."),
+        (status_code = 404, description = "Job not found or expired."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn job_events(id: PathParam<String>, depot: &mut Depot, res: &mut Response) {
+    let id = id.into_inner();
+    if let Err(e) = find_own_job(depot, &id).await {
+        res.render(e);
+        return;
+    }
+    let queue = JobQueue::new(depot.obtain::<Arc<RedisClient>>().expect("Redis client not found").clone());
+
+    // Poll the job and emit an event whenever its status changes, ending after a final status
+    let events = stream::unfold((queue, id, None::<ChainJobStatus>, false), |(queue, id, last, done)| async move {
+        if done {
+            return None;
+        }
+        loop {
+            let job = match queue.get(&id).await {
+                Ok(Some(job)) => job,
+                Ok(None) => {
+                    let event = SseEvent::default().name("error").text("Job not found or expired");
+                    return Some((Ok(event), (queue, id, last, true)));
+                }
+                Err(e) => {
+                    log::error!("Failed to read chain job {}: {}", id, e);
+                    let event = SseEvent::default().name("error").text("Failed to read job");
+                    return Some((Ok(event), (queue, id, last, true)));
+                }
+            };
+            if last.as_ref() != Some(&job.status) {
+                let event = SseEvent::default()
+                    .name(format!("{:?}", job.status).to_lowercase())
+                    .json(ChainJobDto::from(&job));
+                let done = job.status.is_final();
+                return Some((event, (queue, id, Some(job.status), done)));
+            }
+            tokio::time::sleep(EVENT_POLL_INTERVAL).await;
+        }
+    });
+    sse::stream(res, events);
+}
+
+// --- Helper Functions ---
+
+// Jobs are only visible to the user who queued them
+async fn find_own_job(depot: &Depot, id: &str) -> Result<ChainJob, Json<ResObj<()>>> {
+    let user_address = current_user_address(depot)?;
+    let redis_client = depot.obtain::<Arc<RedisClient>>().expect("Redis client not found").clone();
+
+    match JobQueue::new(redis_client).get(id).await {
+        Ok(Some(job)) if job.requested_by.eq_ignore_ascii_case(&user_address) => Ok(job),
+        Ok(Some(_)) => Err(res_json_custom(403, "Job was requested by another user")),
+        Ok(None) => Err(res_not_found("Job not found")),
+        Err(e) => {
+            log::error!("Failed to read chain job {}: {}", id, e);
+            Err(res_json_err("Failed to read job"))
+        }
+    }
+}
@@ -3,7 +3,16 @@ pub mod swagger_controller;
 pub mod user_controller;
 pub mod enterprise_controller;
 pub mod invoice_controller;
+pub mod batch_controller;
+pub mod holding_controller;
+pub mod chain_controller;
+pub mod repayment_controller;
+pub mod admin_controller;
+pub mod jobs_controller;
+pub mod api_key_controller;
+pub mod erp_controller;
 
+use common::domain::entity::UserRole;
 use serde::{Deserialize, Serialize};
 
 /// Defines the structure of the JWT claims (payload).
@@ -13,7 +22,12 @@ pub struct Claims {
     pub sub: String, 
     /// Expiration time (Unix timestamp)
     pub exp: usize,  
-    // You can add other custom claims here if needed, e.g.:
-    // pub role: String,
+    /// Issued at (Unix timestamp)
+    pub iat: usize,
+    /// Login session the token belongs to; revoked with the session
+    pub sid: String,
+    /// Platform role of the user when the token was issued
+    #[serde(default)]
+    pub role: UserRole,
 }
 
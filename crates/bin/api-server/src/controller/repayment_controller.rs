@@ -0,0 +1,196 @@
+use crate::utils::auth::{current_enterprise_id, current_user_address, require_enterprise};
+use crate::utils::chains::obtain_contract;
+use crate::utils::res::{Res, ResObj, res_bad_request, res_json_custom, res_json_err, res_json_ok, res_not_found, res_contract_err, res_lifecycle_err};
+use common::domain::dto::token_transfer_dto::TransferReceiptDto;
+use common::domain::entity::invoice_batch::InvoiceBatchDto;
+use common::domain::entity::repayment::RepaymentDto;
+use common::domain::entity::InvoiceBatch;
+use common::utils::decimal::{is_positive_integer, sum_decimals};
+use mongodb::{
+    Database,
+    bson::{DateTime, Decimal128, oid::ObjectId},
+};
+use pharos_interact::ContractQuerier;
+use salvo::oapi::{ToSchema, extract::JsonBody, extract::QueryParam};
+use salvo::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+use service::repository::{EnterpriseRepository, InvoiceBatchRepository};
+use service::{RepaymentError, RepaymentService};
+use std::str::FromStr;
+use std::sync::Arc;
+
+// --- Request DTOs ---
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({
+    "batchId": "6610c1f2a3b4c5d6e7f80912",
+    "transactionHash": "0x1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b"
+})))]
+pub struct RegisterRepaymentRequest {
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// 稳定币还款交易哈希
+    pub transaction_hash: String,
+}
+
+// --- Handlers ---
+
+/// 债务人登记还款交易 (链上校验稳定币转账后入账)
+#[salvo::oapi::endpoint(
+    tags("还款"),
+    status_codes(200, 400, 401, 403, 404, 409, 500, 503),
+    request_body = RegisterRepaymentRequest,
+    responses(
+        (status_code = 200, description = "Repayment verified and recorded; returns the updated batch.", body = InvoiceBatchDto),
+        (status_code = 400, description = "Invalid input, transaction not mined/failed, or no matching stablecoin transfer."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not the debtor of the batch."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 409, description = "Transaction already registered and applied, or batch status changed concurrently."),
+        (status_code = 500, description = "Internal server error."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn register_repayment(req: JsonBody<RegisterRepaymentRequest>, depot: &mut Depot) -> Res<InvoiceBatchDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let repayment_service = RepaymentService::new(&mongodb);
+
+    let user_address = current_user_address(depot)?;
+    let enterprise_id = current_enterprise_id(depot, &mongodb).await?;
+
+    let req = req.into_inner();
+    let batch_id = ObjectId::parse_str(&req.batch_id).map_err(|_| res_bad_request("Invalid batchId format"))?;
+    let tx_hash = req.transaction_hash.trim().to_lowercase();
+
+    let batch = match batch_repo.find_by_id(batch_id).await {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", batch_id, e);
+            return Err(res_json_err("Failed to get invoice batch"));
+        }
+    };
+    if batch.debtor_id != enterprise_id {
+        return Err(res_json_custom(403, "Only the debtor enterprise can register repayments"));
+    }
+    // Whether the batch accepts repayments is checked when recording, so a repayment whose
+    // batch update failed can still be completed after the batch moved on
+    // The repayment transfer is looked up on the chain the batch was issued on
+    let (_, contract) = obtain_contract(depot, batch.deployment.as_deref())?;
+
+    let debtor_wallet = match EnterpriseRepository::new(&mongodb).find_by_id(enterprise_id).await {
+        Ok(Some(enterprise)) => enterprise.wallet_address,
+        Ok(None) => return Err(res_not_found("Debtor enterprise not found")),
+        Err(e) => {
+            log::error!("Failed to get enterprise {}: {}", enterprise_id, e);
+            return Err(res_json_err("Failed to get debtor enterprise"));
+        }
+    };
+
+    let receipt = match contract.get_transfer_receipt(tx_hash.clone()).await {
+        Ok(Some(receipt)) => receipt,
+        Ok(None) => return Err(res_bad_request("Transaction is not mined yet")),
+        Err(e) => {
+            log::error!("Failed to fetch repayment transaction {}: {}", tx_hash, e);
+            return Err(res_contract_err(&e));
+        }
+    };
+    if !receipt.success {
+        return Err(res_bad_request("Repayment transaction reverted"));
+    }
+    let senders = [debtor_wallet.as_str(), user_address.as_str()];
+    let amount = repaid_amount(&batch, &receipt, &contract.contract_address(), &senders)?;
+
+    let repayment_timestamp = DateTime::from_millis(receipt.block_timestamp as i64 * 1000);
+    match repayment_service.record(batch_id, enterprise_id, amount, &tx_hash, repayment_timestamp, &user_address).await {
+        Ok(batch) => {
+            log::info!("Repayment of {} for batch {} recorded (tx {})", amount, batch_id, tx_hash);
+            Ok(res_json_ok(Some(InvoiceBatchDto::from(&batch))))
+        }
+        Err(e) => {
+            log::error!("Failed to record repayment {} for batch {}: {}", tx_hash, batch_id, e);
+            Err(res_repayment_err(&e))
+        }
+    }
+}
+
+/// 查询批次还款记录 (仅限该批次的债权人、债务人与平台管理员)
+#[salvo::oapi::endpoint(
+    tags("还款"),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("batch_id" = String, Query, description = "Batch MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Repayments of the batch, oldest first.", body = Vec<RepaymentDto>),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is neither the creditor nor the debtor of the batch."),
+        (status_code = 404, description = "Batch not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_repayments(batch_id: QueryParam<String>, depot: &mut Depot) -> Res<Vec<RepaymentDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let batch_repo = InvoiceBatchRepository::new(&mongodb);
+    let repayment_service = RepaymentService::new(&mongodb);
+
+    let batch_id = ObjectId::parse_str(batch_id.into_inner()).map_err(|_| res_bad_request("Invalid ObjectId format"))?;
+
+    let batch = match batch_repo.find_by_id(batch_id).await {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return Err(res_not_found("Batch not found")),
+        Err(e) => {
+            log::error!("Failed to get invoice batch {}: {}", batch_id, e);
+            return Err(res_json_err("Failed to get invoice batch"));
+        }
+    };
+    require_enterprise(depot, &mongodb, &[batch.creditor_id, batch.debtor_id], "Only the creditor or debtor can view the repayments").await?;
+
+    match repayment_service.list_by_batch(batch_id).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(RepaymentDto::from).collect()))),
+        Err(e) => {
+            log::error!("Failed to list repayments of batch {}: {}", batch_id, e);
+            Err(res_json_err("Failed to list repayments"))
+        }
+    }
+}
+
+// --- Helper Functions ---
+
+// Sum the batch stablecoin transfers from the debtor to the contract in this transaction
+fn repaid_amount(batch: &InvoiceBatch, receipt: &TransferReceiptDto, contract_address: &str, senders: &[&str]) -> Result<Decimal128, Json<ResObj<()>>> {
+    let stable_token = match &batch.stable_token_address {
+        Some(token) => token,
+        None => return Err(res_bad_request("Batch has no stablecoin address")),
+    };
+
+    let mut amounts = Vec::new();
+    for transfer in &receipt.transfers {
+        if transfer.token.eq_ignore_ascii_case(stable_token)
+            && transfer.to.eq_ignore_ascii_case(contract_address)
+            && senders.iter().any(|sender| transfer.from.eq_ignore_ascii_case(sender))
+        {
+            amounts.push(Decimal128::from_str(&transfer.amount).map_err(|_| res_json_err("Invalid transfer amount"))?);
+        }
+    }
+
+    match sum_decimals(&amounts) {
+        Some(amount) if is_positive_integer(&amount) => Ok(amount),
+        Some(_) => Err(res_bad_request("Transaction contains no stablecoin transfer from the debtor to the contract")),
+        None => Err(res_json_err("Failed to sum transfer amounts")),
+    }
+}
+
+fn res_repayment_err(e: &RepaymentError) -> Json<ResObj<()>> {
+    match e {
+        RepaymentError::BatchNotFound(_) => res_not_found("Batch not found"),
+        RepaymentError::NotRepayable(_) => res_bad_request(&e.to_string()),
+        RepaymentError::DuplicateTransaction(_) => res_json_custom(409, &e.to_string()),
+        RepaymentError::AmountOutOfRange(_) => res_json_err(&e.to_string()),
+        RepaymentError::Lifecycle(e) => res_lifecycle_err(e),
+        RepaymentError::Database(_) => res_json_err("Database error"),
+    }
+}
@@ -1,42 +1,32 @@
 use ethers::types::{Address, Signature};
-use moka::future::Cache;
 use rand::RngCore;
-use salvo::oapi::{ToSchema, extract::JsonBody};
+use salvo::oapi::{ToSchema, extract::{JsonBody, QueryParam}};
 use salvo::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use std::time::Duration;
 use uuid::Uuid;
 
 use crate::utils::res::{Res, ResObj, res_json_custom, res_json_err, res_json_ok};
-use chrono::Utc;
+use chrono::{SecondsFormat, Utc};
 use log::{error, info, warn};
 use salvo::http::header;
 use serde_json::json;
 
 use service::repository::UserRepository;
+use service::{ChallengeStore, SessionError, SessionStore, SiweError, SiweExpectation, SiweMessage, WalletCodeCache};
+use pharos_interact::is_eip6492;
+use crate::utils::auth::current_user_address;
+use crate::utils::chains::obtain_contract;
+use common::domain::dto::session_dto::SessionDto;
 use mongodb::Database;
 use thiserror::Error;
 use crate::controller::Claims;
 use jsonwebtoken::{encode, Header, EncodingKey, Algorithm};
 use configs::CFG;
 use service::repository::EnterpriseRepository;
-use common::domain::entity::Enterprise;
+use common::domain::entity::{Enterprise, User, UserRole};
 use mongodb::bson::oid::ObjectId;
 
-// --- Nonce Cache ---
-lazy_static::lazy_static! {
-    // Cache stores nonce (String) keyed by a unique request ID (String)
-    static ref NONCE_CACHE: Cache<String, String> = Cache::builder()
-        // Time to live: Nonces expire after 5 minutes
-        .time_to_live(Duration::from_secs(5 * 60))
-        // Time to idle: Nonces expire if not accessed for 5 minutes
-        .time_to_idle(Duration::from_secs(5 * 60))
-        // Maximum capacity of the cache
-        .max_capacity(10_000)
-        .build();
-}
-
 // --- Error Handling ---
 #[derive(Debug, Error, Serialize, ToSchema)]
 pub enum AuthError {
@@ -59,77 +49,117 @@ pub struct ChallengeRequest {
 }
 
 #[derive(Serialize, ToSchema, Debug)]
-#[salvo(schema(example = json!({ "nonce": "...", "requestId": "..."})))]
+#[salvo(schema(example = json!({ "message": "localhost:3000 wants you to sign in with your Ethereum account:...", "nonce": "...", "requestId": "...", "expirationTime": "2025-05-01T08:05:00Z"})))]
 pub struct ChallengeResponse {
+    pub message: String, // EIP-4361 message the wallet signs with personal_sign
     pub nonce: String,
     #[serde(rename = "requestId")]
     pub request_id: String, // Unique ID to link challenge and login
+    #[serde(rename = "expirationTime")]
+    pub expiration_time: String,
 }
 
 #[derive(Deserialize, ToSchema, Debug)]
-#[salvo(schema(example = json!({ "requestId": "...", "signature": "0x..."})))]
+#[salvo(schema(example = json!({ "requestId": "...", "message": "...", "signature": "0x..."})))]
 pub struct LoginRequest {
     #[serde(rename = "requestId")]
     pub request_id: String, // ID received from /challenge
-    pub signature: String, // Signature generated by the wallet
+    pub message: Option<String>, // Signed SIWE message, defaults to the one issued by /challenge
+    pub signature: String, // Wallet signature: 65-byte ECDSA, EIP-1271 contract wallet or EIP-6492 wrapped
 }
 
 #[derive(Serialize, ToSchema, Debug)]
-#[salvo(schema(example = json!({ "token": "eyJ...", "walletAddress": "0x..."})))]
+#[salvo(schema(example = json!({ "token": "eyJ...", "refreshToken": "...", "expiresIn": 900, "walletAddress": "0x..."})))]
 pub struct LoginResponse {
-    pub token: String, // The generated JWT
+    pub token: String, // The generated short-lived access JWT
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String, // Single-use token for /user/refresh, rotated on every refresh
+    #[serde(rename = "expiresIn")]
+    pub expires_in: u64, // Access token lifetime in seconds
     #[serde(rename = "walletAddress")]
     pub wallet_address: String, // Return wallet address as confirmation
 }
 
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "refreshToken": "..."})))]
+pub struct RefreshRequest {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
+
 #[derive(Deserialize, ToSchema, Debug)]
 #[salvo(schema(example = json!({ "enterpriseAddress": "0x..."})))]
 pub struct BindEnterpriseRequest {
     #[serde(rename = "enterpriseAddress")]
     pub enterprise_address: String,
+    /// 被绑定用户的钱包地址, 为空时绑定当前用户
+    #[serde(rename = "userAddress")]
+    pub user_address: Option<String>,
 }
 
 // --- Handlers ---
 
-/// 登录步骤1 生成一个挑战
+/// 登录步骤1 生成 EIP-4361 (SIWE) 登录消息
 #[salvo::oapi::endpoint(
     tags("用户"),
-    status_codes(200, 400),
+    status_codes(200, 400, 500),
     request_body = ChallengeRequest,
     responses(
-        (status_code = 200, description = "Challenge generated successfully."),
+        (status_code = 200, description = "SIWE message generated successfully.", body = ChallengeResponse),
         (status_code = 400, description = "Invalid request."),
+        (status_code = 500, description = "Failed to store the challenge."),
     )
 )]
 pub async fn challenge(req: JsonBody<ChallengeRequest>, depot: &mut Depot) -> Res<ChallengeResponse> {
     let address_str = &req.address;
 
-    // Basic validation (more thorough validation might be needed)
-    if !address_str.starts_with("0x") || address_str.len() != 42 {
-        warn!("Invalid address format received: {}", address_str);
-        return Err(res_json_custom(400, "InvalidAddress"));
-    }
+    let address = match address_str.parse::<Address>() {
+        Ok(address) if address_str.starts_with("0x") && address_str.len() == 42 => address,
+        _ => {
+            warn!("Invalid address format received: {}", address_str);
+            return Err(res_json_custom(400, "InvalidAddress"));
+        }
+    };
 
     let nonce = generate_nonce();
     let request_id = Uuid::new_v4().to_string();
+    let siwe = &CFG.siwe;
+    let message = SiweMessage::new(
+        &siwe.domain,
+        address,
+        Some(&siwe.statement),
+        &siwe.uri,
+        siwe.chain_id,
+        &nonce,
+        Utc::now(),
+        chrono::Duration::seconds(siwe.ttl_secs as i64),
+    )
+    .with_request_id(&request_id);
+    let expiration_time = message.expiration_time.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)).unwrap_or_default();
+    let message = message.to_string();
+
+    // Store the issued message associated with the request ID
+    let challenges = depot.obtain::<Arc<ChallengeStore>>().expect("Challenge store not found").clone();
+    if let Err(e) = challenges.put(&request_id, &message).await {
+        error!("Failed to store login challenge {}: {}", request_id, e);
+        return Err(res_json_custom(500, "ChallengeStorageError"));
+    }
+    info!("Generated SIWE message for request ID: {}", request_id);
 
-    // Store nonce associated with the request ID
-    NONCE_CACHE.insert(request_id.clone(), nonce.clone()).await;
-    info!("Generated nonce for request ID: {}", request_id);
-
-    Ok(res_json_ok(Some(ChallengeResponse { nonce, request_id })))
+    Ok(res_json_ok(Some(ChallengeResponse { message, nonce, request_id, expiration_time })))
 }
 
 /// 登录步骤2 验证挑战并登录 (generates JWT)
 #[salvo::oapi::endpoint(
     tags("用户"),
-    status_codes(200, 400, 401, 500),
+    status_codes(200, 400, 401, 500, 503),
     request_body = LoginRequest,
     responses(
         (status_code = 200, description = "Login successful, JWT returned.", body = LoginResponse),
-        (status_code = 400, description = "Nonce not found or expired / Invalid signature format."),
-        (status_code = 401, description = "Invalid signature (verification failed)."),
+        (status_code = 400, description = "Nonce not found or expired / Malformed SIWE message / Invalid signature format."),
+        (status_code = 401, description = "SIWE message does not match domain, chain, nonce or validity window / Invalid signature."),
         (status_code = 500, description = "Internal server error during login processing."),
+        (status_code = 503, description = "Chain unavailable to verify a contract wallet signature."),
     )
 )]
 pub async fn login(req: JsonBody<LoginRequest>, depot: &mut Depot, request: &mut Request) -> Res<LoginResponse> {
@@ -143,47 +173,95 @@ pub async fn login(req: JsonBody<LoginRequest>, depot: &mut Depot, request: &mut
     let request_id = &req.request_id;
     let signature_str = &req.signature;
 
-    let nonce = match NONCE_CACHE.get(request_id).await {
-        Some(n) => {
-            // Invalidate the nonce after retrieval to prevent reuse
-            NONCE_CACHE.invalidate(request_id).await;
-            n
+    // Taken atomically, so the challenge cannot be reused on any instance
+    let challenges = depot.obtain::<Arc<ChallengeStore>>().expect("Challenge store not found").clone();
+    let issued = match challenges.take(request_id).await {
+        Ok(Some(n)) => n,
+        Err(e) => {
+            error!("Failed to load login challenge {}: {}", request_id, e);
+            return Err(res_json_custom(500, "LoginProcessingError"));
         }
-        None => {
+        Ok(None) => {
             warn!("Nonce not found or expired for request ID: {}", request_id);
             return Err(res_json_custom(400, "NonceNotFoundOrExpired"));
         }
     };
+    let issued: SiweMessage = match issued.parse() {
+        Ok(message) => message,
+        Err(e) => {
+            error!("Failed to parse issued SIWE message for request ID {}: {}", request_id, e);
+            return Err(res_json_custom(500, "LoginProcessingError"));
+        }
+    };
 
-    // 2. Prepare the message that was signed (should match exactly what frontend signed)
-    let message_to_verify = nonce; 
+    // 2. Parse the message that was signed; wallets sign the issued text unchanged
+    let message: SiweMessage = match req.message.as_deref() {
+        Some(text) => match text.parse() {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Invalid SIWE message for request ID {}: {}", request_id, e);
+                return Err(res_json_custom(400, "InvalidSiweMessage"));
+            }
+        },
+        None => issued.clone(),
+    };
 
-    // 3. Parse the signature
-    let signature: Signature = match signature_str.parse() {
-        Ok(sig) => sig,
-        Err(e) => {
-            warn!("Invalid signature format provided: {}", e);
+    // 3. Validate domain, URI, chain, nonce and validity window against the issued challenge
+    let siwe = &CFG.siwe;
+    let expected = SiweExpectation { domain: &siwe.domain, uri: &siwe.uri, chain_id: siwe.chain_id, nonce: &issued.nonce };
+    if let Err(e) = message.validate(&expected, Utc::now()) {
+        warn!("SIWE message rejected for request ID {}: {}", request_id, e);
+        return Err(res_json_custom(401, &e.to_string()));
+    }
+    if message.address != issued.address {
+        warn!("SIWE message for request ID {} names {:?}, challenge was issued to {:?}", request_id, message.address, issued.address);
+        return Err(res_json_custom(401, "InvalidSignature"));
+    }
+
+    // 4. Parse the signature; contract wallets may send more than the 65 bytes of an EOA signature
+    let signature = match hex::decode(signature_str.trim_start_matches("0x")) {
+        Ok(bytes) if !bytes.is_empty() => bytes,
+        _ => {
+            warn!("Invalid signature format provided for request ID {}", request_id);
             // Return 400 for bad format
             return Err(res_json_custom(400, "InvalidSignatureFormat"));
         }
     };
 
-    // 4. Recover the address that signed the message
-    let recovered_address = match signature.recover(message_to_verify) {
-        Ok(addr) => addr,
+    // 5. Verify the message was signed by the address it names: EOAs locally, contract wallets
+    // (EIP-1271, or EIP-6492 before deployment) against the default deployment's chain
+    let verified = match Signature::try_from(signature.as_slice()) {
+        Ok(parsed) if message.verify_signature(&parsed).is_ok() => Ok(()),
+        _ => match obtain_contract(depot, None) {
+            Ok((_, contract)) => {
+                let codes = depot.obtain::<Arc<WalletCodeCache>>().expect("Wallet code cache not found").clone();
+                message.verify_wallet_signature(&signature, contract.as_ref(), &codes).await
+            }
+            // Without a chain only a counterfactual wallet could still be valid; anything else is a bad signature
+            Err(_) if !is_eip6492(&signature) => Err(SiweError::InvalidSignature("signature does not recover to the address".to_string())),
+            Err(e) => return Err(e),
+        },
+    };
+    match verified {
+        Ok(()) => {}
+        Err(e @ (SiweError::Chain(_) | SiweError::ChainMismatch { .. })) => {
+            error!("Contract wallet signature of {:?} could not be checked: {}", message.address, e);
+            return Err(res_json_custom(503, "Blockchain connection unavailable"));
+        }
         Err(e) => {
-            warn!("Failed to recover address from signature: {}", e);
+            warn!("SIWE signature verification failed: {}", e);
             // Return 401 as signature verification failed
             return Err(res_json_custom(401, "InvalidSignature"));
         }
-    };
+    }
+    let recovered_address = message.address;
 
     // Format recovered address consistently (lowercase hex)
     let recovered_address_str = format!("0x{:x}", recovered_address).to_lowercase();
     info!("Successfully recovered address: {}", recovered_address_str);
 
-    // 5. Process user login (find or create user based on recovered address)
-    let _user = match user_repo.process_login(&recovered_address_str).await {
+    // 6. Process user login (find or create user based on recovered address)
+    let user = match user_repo.process_login(&recovered_address_str).await {
         Ok(db_user) => {
             info!("Processed login for user: {}", recovered_address_str);
             db_user // Keep the user object if needed later, otherwise ignore
@@ -195,22 +273,26 @@ pub async fn login(req: JsonBody<LoginRequest>, depot: &mut Depot, request: &mut
         }
     };
 
-    // 6. Generate JWT
-    let now = Utc::now();
-    // Set expiration (e.g., 1 day from now)
-    let expiration_time = now + chrono::Duration::days(1);
-    let exp_timestamp = expiration_time.timestamp() as usize;
-
-    let claims = Claims {
-        sub: recovered_address_str.clone(), // Use recovered address as subject
-        exp: exp_timestamp,
+    let role = match platform_role(&user_repo, &user).await {
+        Ok(role) => role,
+        Err(e) => {
+            error!("Failed to grant platform role to {}: {}", recovered_address_str, e);
+            return Err(res_json_custom(500, "LoginProcessingError"));
+        }
     };
 
-    // Retrieve the secret key from configuration
-    let secret = &CFG.jwt.secret;
-    let encoding_key = EncodingKey::from_secret(secret.as_ref());
-    
-    let token = match encode(&Header::new(Algorithm::HS256), &claims, &encoding_key) {
+    // 7. Start a session and issue its tokens
+    let sessions = depot.obtain::<Arc<SessionStore>>().expect("Session store not found").clone();
+    let user_agent = request.headers().get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+    let ip = request.remote_addr().to_string();
+    let (session, refresh_token) = match sessions.create(&recovered_address_str, user_agent, Some(&ip)).await {
+        Ok(created) => created,
+        Err(e) => {
+            error!("Failed to create session for {}: {}", recovered_address_str, e);
+            return Err(res_json_custom(500, "LoginProcessingError"));
+        }
+    };
+    let token = match access_token(&recovered_address_str, &session.id, role, sessions.access_ttl()) {
         Ok(t) => t,
         Err(e) => {
             error!("Failed to generate JWT: {}", e);
@@ -218,60 +300,192 @@ pub async fn login(req: JsonBody<LoginRequest>, depot: &mut Depot, request: &mut
         }
     };
 
-    // 7. Return successful response with JWT and wallet address
+    // 8. Return successful response with JWT and wallet address
     Ok(res_json_ok(Some(LoginResponse {
         token,
+        refresh_token,
+        expires_in: sessions.access_ttl().as_secs(),
         wallet_address: recovered_address_str,
     })))    
 }
 
-/// 绑定用户到企业 (Requires authentication)
+/// 用刷新令牌换取新的访问令牌 (刷新令牌同时轮换)
 #[salvo::oapi::endpoint(
     tags("用户"),
-    status_codes(200, 400, 401, 404, 500),
-    request_body = BindEnterpriseRequest,
+    status_codes(200, 401, 500),
+    request_body = RefreshRequest,
     responses(
-        (status_code = 200, description = "Successfully bound user to enterprise."),
-        (status_code = 400, description = "Invalid enterprise address format."),
+        (status_code = 200, description = "New access and refresh tokens.", body = LoginResponse),
+        (status_code = 401, description = "Refresh token invalid, expired or already used."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn refresh(req: JsonBody<RefreshRequest>, depot: &mut Depot) -> Res<LoginResponse> {
+    let sessions = depot.obtain::<Arc<SessionStore>>().expect("Session store not found").clone();
+
+    let (session, refresh_token) = match sessions.rotate(&req.refresh_token).await {
+        Ok(rotated) => rotated,
+        Err(e @ (SessionError::InvalidToken | SessionError::Reused(_))) => {
+            warn!("Token refresh rejected: {}", e);
+            return Err(res_json_custom(401, "InvalidRefreshToken"));
+        }
+        Err(e) => {
+            error!("Failed to refresh session: {}", e);
+            return Err(res_json_custom(500, "TokenRefreshError"));
+        }
+    };
+    // Reload the role so role changes apply from the next refresh
+    let mongodb = depot.obtain::<Arc<Database>>().expect("MongoDB Database connection not found in Depot").clone();
+    let role = match UserRepository::new(&mongodb).find_by_wallet_address(&session.user_address).await {
+        Ok(Some(user)) => user.role,
+        Ok(None) => {
+            warn!("Session {} belongs to unknown user {}", session.id, session.user_address);
+            return Err(res_json_custom(401, "InvalidRefreshToken"));
+        }
+        Err(e) => {
+            error!("Failed to load user {}: {}", session.user_address, e);
+            return Err(res_json_custom(500, "TokenRefreshError"));
+        }
+    };
+    let token = match access_token(&session.user_address, &session.id, role, sessions.access_ttl()) {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Failed to generate JWT: {}", e);
+            return Err(res_json_custom(500, "TokenGenerationError"));
+        }
+    };
+
+    Ok(res_json_ok(Some(LoginResponse {
+        token,
+        refresh_token,
+        expires_in: sessions.access_ttl().as_secs(),
+        wallet_address: session.user_address,
+    })))
+}
+
+/// 退出登录, 吊销当前会话的访问令牌与刷新令牌
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "Logged out."),
         (status_code = 401, description = "User not authenticated."),
-        (status_code = 404, description = "Enterprise not found with the provided address."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
-pub async fn bind_enterprise(req: JsonBody<BindEnterpriseRequest>, depot: &mut Depot) -> Res<()> { // Returns Res<()> for success/failure
-    // 1. Get authenticated user address from depot (inserted by auth_token middleware)
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => {
-            address_ref.as_str()
-        },
+pub async fn logout(depot: &mut Depot) -> Res<()> {
+    let (user_address, session_id) = match (depot.get::<String>("user_address"), depot.get::<String>("session_id")) {
+        (Ok(address), Ok(session)) => (address.clone(), session.clone()),
+        _ => return Err(res_json_custom(401, "User not authenticated")),
+    };
+    let sessions = depot.obtain::<Arc<SessionStore>>().expect("Session store not found").clone();
+
+    match sessions.revoke(&user_address, &session_id).await {
+        Ok(_) => Ok(res_json_ok(None)),
+        Err(e) => {
+            error!("Failed to revoke session {}: {}", session_id, e);
+            Err(res_json_custom(500, "LogoutError"))
+        }
+    }
+}
+
+/// 查询当前用户的登录会话
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "Active sessions, most recently used first.", body = Vec<SessionDto>),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_sessions(depot: &mut Depot) -> Res<Vec<SessionDto>> {
+    let (user_address, session_id) = match (depot.get::<String>("user_address"), depot.get::<String>("session_id")) {
+        (Ok(address), Ok(session)) => (address.clone(), session.clone()),
+        _ => return Err(res_json_custom(401, "User not authenticated")),
+    };
+    let sessions = depot.obtain::<Arc<SessionStore>>().expect("Session store not found").clone();
+
+    match sessions.list(&user_address).await {
+        Ok(list) => Ok(res_json_ok(Some(list.iter().map(|session| session.to_dto(session.id == session_id)).collect()))),
         Err(e) => {
-            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
+            error!("Failed to list sessions of {}: {}", user_address, e);
+            Err(res_json_err("Failed to list sessions"))
         }
+    }
+}
+
+/// 结束当前用户的指定登录会话
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    status_codes(200, 401, 404, 500),
+    parameters(
+        ("id" = String, Query, description = "Session id")
+    ),
+    responses(
+        (status_code = 200, description = "Session revoked."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 404, description = "Session not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn delete_session(id: QueryParam<String>, depot: &mut Depot) -> Res<()> {
+    let user_address = match depot.get::<String>("user_address") {
+        Ok(address) => address.clone(),
+        Err(_) => return Err(res_json_custom(401, "User not authenticated")),
     };
+    let sessions = depot.obtain::<Arc<SessionStore>>().expect("Session store not found").clone();
+
+    match sessions.revoke(&user_address, &id.into_inner()).await {
+        Ok(true) => Ok(res_json_ok(None)),
+        Ok(false) => Err(res_json_custom(404, "SessionNotFound")),
+        Err(e) => {
+            error!("Failed to revoke session of {}: {}", user_address, e);
+            Err(res_json_err("Failed to revoke session"))
+        }
+    }
+}
+
+/// 绑定用户到企业 (仅企业自身钱包、该企业的 EnterpriseAdmin 或平台管理员可操作)
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    status_codes(200, 400, 401, 403, 404, 409, 500),
+    request_body = BindEnterpriseRequest,
+    responses(
+        (status_code = 200, description = "Successfully bound user to enterprise."),
+        (status_code = 400, description = "Invalid enterprise or user address format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Caller is neither the enterprise wallet, an EnterpriseAdmin of it nor a PlatformAdmin."),
+        (status_code = 404, description = "Enterprise or user to bind not found."),
+        (status_code = 409, description = "User is bound to another enterprise; only a PlatformAdmin can move it."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn bind_enterprise(req: JsonBody<BindEnterpriseRequest>, depot: &mut Depot) -> Res<()> {
+    // 1. Get authenticated user address from depot (inserted by auth_token middleware)
+    let actor_address = current_user_address(depot)?;
 
     // 2. Get dependencies
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let user_repo = UserRepository::new(&mongodb);
     let enterprise_repo = EnterpriseRepository::new(&mongodb);
 
-    // 3. Validate enterprise address format
+    // 3. Validate address formats
+    let is_address = |address: &str| address.starts_with("0x") && address.len() == 42;
     let enterprise_address = &req.enterprise_address;
-    if !enterprise_address.starts_with("0x") || enterprise_address.len() != 42 {
-        warn!("Invalid enterprise address format provided for binding: {}", enterprise_address);
-        return Err(res_json_err( "InvalidEnterpriseAddressFormat"));
+    let user_address = req.user_address.as_deref().unwrap_or(&actor_address);
+    if !is_address(enterprise_address) || !is_address(user_address) {
+        warn!("Invalid address format provided for binding: {} / {}", enterprise_address, user_address);
+        return Err(res_json_custom(400, "InvalidAddressFormat"));
     }
 
     // 4. Find the enterprise by its wallet address
-    let enterprise_oid = match enterprise_repo.find_by_wallet_address(enterprise_address).await {
-        Ok(Some(enterprise)) => {
-            if let Some(id) = enterprise.id {
-                id // Return the ObjectId
-            } else {
-                 error!("Enterprise found by address {} but has no ObjectId", enterprise_address);
-                 return Err(res_json_custom(500, "EnterpriseMissingId"));
-            }
-        },
+    let enterprise = match enterprise_repo.find_by_wallet_address(enterprise_address).await {
+        Ok(Some(enterprise)) if enterprise.id.is_some() => enterprise,
+        Ok(Some(_)) => {
+            error!("Enterprise found by address {} but has no ObjectId", enterprise_address);
+            return Err(res_json_custom(500, "EnterpriseMissingId"));
+        }
         Ok(None) => {
             log::warn!("Enterprise not found with address: {}", enterprise_address);
             return Err(res_json_custom(404, "EnterpriseNotFound"));
@@ -281,28 +495,92 @@ pub async fn bind_enterprise(req: JsonBody<BindEnterpriseRequest>, depot: &mut D
             return Err(res_json_custom(500, "DatabaseError"));
         }
     };
+    let enterprise_oid = enterprise.id.unwrap();
 
-    // 5. Bind the user to the enterprise
-    match user_repo.bind_enterprise(&user_address, enterprise_oid).await {
-        Ok(true) => {
-            info!("Successfully bound user {} to enterprise {}", user_address, enterprise_oid);
-            Ok(res_json_ok(None)) // Return 200 OK with no body
-        }
-        Ok(false) => {
-             // This means the user address wasn't found, which shouldn't happen if they are authenticated
-             error!("Authenticated user {} not found in DB for binding update?", user_address);
-             Err(res_json_custom(500, "AuthenticatedUserNotFoundInDB"))
+    // 5. Only the enterprise itself or its administrators may bind users to it
+    let actor = match user_repo.find_by_wallet_address(&actor_address).await {
+        Ok(Some(actor)) => actor,
+        Ok(None) => return Err(res_json_custom(401, "User not found")),
+        Err(e) => {
+            error!("Failed to load user {}: {}", actor_address, e);
+            return Err(res_json_custom(500, "DatabaseError"));
         }
+    };
+    if !actor.can_bind_to(&enterprise) {
+        warn!("User {} denied binding {} to enterprise {}", actor_address, user_address, enterprise_oid);
+        return Err(res_json_custom(403, "Only the enterprise wallet or its administrators can bind users"));
+    }
+
+    // 6. Bind the user to the enterprise
+    let user = match user_repo.find_by_wallet_address(user_address).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(res_json_custom(404, "UserNotFound")),
         Err(e) => {
-            error!("Database error binding user {} to enterprise {}: {}", user_address, enterprise_oid, e);
-            Err(res_json_custom(500, "DatabaseError"))
+            error!("Failed to load user {}: {}", user_address, e);
+            return Err(res_json_custom(500, "DatabaseError"));
+        }
+    };
+    let mut role = user.role;
+    if user.enterprise_id.is_some_and(|current| current != enterprise_oid) {
+        // Moving a user out of another enterprise is left to platform admins
+        if actor.role != UserRole::PlatformAdmin {
+            warn!("User {} denied moving {} from enterprise {:?} to {}", actor_address, user_address, user.enterprise_id, enterprise_oid);
+            return Err(res_json_custom(409, "User is already bound to another enterprise"));
+        }
+        // Administration of the previous enterprise does not carry over
+        if role == UserRole::EnterpriseAdmin {
+            if let Err(e) = user_repo.set_role(user.id.unwrap(), UserRole::Investor).await {
+                error!("Failed to reset role of {}: {}", user_address, e);
+                return Err(res_json_custom(500, "DatabaseError"));
+            }
+            role = UserRole::Investor;
+        }
+    }
+    if let Err(e) = user_repo.bind_enterprise(user_address, enterprise_oid).await {
+        error!("Database error binding user {} to enterprise {}: {}", user_address, enterprise_oid, e);
+        return Err(res_json_custom(500, "DatabaseError"));
+    }
+    // The enterprise's own wallet administers it
+    if user.wallet_address.eq_ignore_ascii_case(&enterprise.wallet_address) && role == UserRole::Investor {
+        if let Err(e) = user_repo.set_role(user.id.unwrap(), UserRole::EnterpriseAdmin).await {
+            error!("Failed to grant EnterpriseAdmin to {}: {}", user_address, e);
+            return Err(res_json_custom(500, "DatabaseError"));
         }
     }
+    info!("User {} bound {} to enterprise {}", actor_address, user_address, enterprise_oid);
+    Ok(res_json_ok(None))
 }
 
 // --- Helper Functions ---
+// Role of a logging-in user; addresses configured as platform admins are promoted on login
+async fn platform_role(user_repo: &UserRepository, user: &User) -> Result<UserRole, mongodb::error::Error> {
+    let configured = CFG.jwt.platform_admins.iter().any(|admin| admin.eq_ignore_ascii_case(&user.wallet_address));
+    if !configured || user.role == UserRole::PlatformAdmin {
+        return Ok(user.role);
+    }
+    if let Some(id) = user.id {
+        user_repo.set_role(id, UserRole::PlatformAdmin).await?;
+        info!("Granted PlatformAdmin to configured admin {}", user.wallet_address);
+    }
+    Ok(UserRole::PlatformAdmin)
+}
+
+fn access_token(user_address: &str, session_id: &str, role: UserRole, ttl: std::time::Duration) -> Result<String, jsonwebtoken::errors::Error> {
+    let now = Utc::now().timestamp() as usize;
+    let claims = Claims {
+        sub: user_address.to_string(), // Use wallet address as subject
+        exp: now + ttl.as_secs() as usize,
+        iat: now,
+        sid: session_id.to_string(),
+        role,
+    };
+    let encoding_key = EncodingKey::from_secret(CFG.jwt.secret.as_ref());
+    encode(&Header::new(Algorithm::HS256), &claims, &encoding_key)
+}
+
 fn generate_nonce() -> String {
-    let mut bytes = [0u8; 32];
+    // EIP-4361 nonces are alphanumeric, at least 8 characters
+    let mut bytes = [0u8; 16];
     rand::thread_rng().fill_bytes(&mut bytes);
-    format!("pharos-auth-{}", hex::encode(bytes))
+    hex::encode(bytes)
 }
@@ -2,15 +2,21 @@
 mod router;
 mod controller;
 mod utils;
+mod worker;
 
 use common::config::logger;
 use configs::CFG;
-use log::{info, error};
+use log::{info, error, warn};
 use salvo::prelude::*;
-use service::{db::init_mongodb, init_redis_client};
+use service::{create_indexes, db::init_mongodb, init_redis_client, ChallengeStore, OutboxService, SessionStore};
 use std::sync::Arc;
-use pharos_interact::initialize_contract_from_env;
+use std::time::Duration;
+use pharos_interact::GasPolicy;
+use utils::chains::init_contract_registry;
 use anyhow::Context;
+use worker::chain_job_worker::spawn_chain_job_worker;
+use worker::reconcile_worker::{ReconcileState, spawn_reconcile_worker};
+use worker::reorg_worker::spawn_reorg_worker;
 
 #[tokio::main]
 async fn main() {
@@ -31,6 +37,9 @@ async fn main() {
             panic!("MongoDB connection failed!"); 
         }
     };
+    if let Err(e) = create_indexes(&mongodb).await {
+        error!("Failed to create MongoDB indexes: {}", e);
+    }
 
     // Initialize Redis Client (sync)
     let redis_client = match init_redis_client(&redis_config) {
@@ -42,21 +51,71 @@ async fn main() {
         }
     };
 
-    // Initialize blockchain contract connection (async)
-    let contract = match initialize_contract_from_env().await {
-        Ok(contract) => {
-            info!("Blockchain contract connection initialized successfully");
-            Some(Arc::new(contract))
-        },
+    // Register contract deployments; each connects and fails over in the background
+    let gas_policy = match GasPolicy::new(&CFG.gas.mode, CFG.gas.buffer_percent, CFG.gas.max_fee_gwei, CFG.gas.priority_fee_gwei) {
+        Ok(policy) => policy,
         Err(e) => {
-            error!("Failed to initialize blockchain contract connection: {}", e);
-            // Don't panic, just continue without contract capability
-            None
+            error!("Invalid gas configuration: {}", e);
+            panic!("Invalid gas configuration!");
         }
     };
-    
+    let contracts = Arc::new(init_contract_registry(&CFG.chains, &gas_policy).await);
+    info!("Contract deployments available: {:?}, default '{}', gas policy {:?}", contracts.names(), contracts.default_name(), gas_policy);
+
+    // Finish contract writes left in the outbox by a previous run
+    for (name, contract) in contracts.iter() {
+        let mongodb = mongodb.clone();
+        let contract = contract.clone();
+        let name = name.clone();
+        let is_default = contracts.is_default(&name);
+        tokio::spawn(async move {
+            contract.wait_connected().await;
+            if let Err(e) = OutboxService::new(&mongodb).resume(contract.as_ref(), &name, is_default).await {
+                error!("Failed to resume transaction outbox of deployment '{}': {}", name, e);
+            }
+        });
+    }
+
+    // Execute queued chain-write jobs
+    if contracts.is_empty() {
+        error!("Chain job worker not started: no blockchain contract connection available");
+    } else {
+        spawn_chain_job_worker(mongodb.clone(), redis_client.clone(), contracts.clone());
+    }
+
+    // Start the chain-to-database invoice reconciliation worker
+    let reconcile_state = Arc::new(ReconcileState::default());
+    match (contracts.is_empty(), CFG.reconcile.enabled) {
+        (false, true) => spawn_reconcile_worker(mongodb.clone(), contracts.clone(), reconcile_state.clone(), CFG.reconcile.clone()),
+        (true, true) => error!("Invoice reconcile worker not started: no blockchain contract connection available"),
+        (_, false) => info!("Invoice reconcile worker disabled by configuration"),
+    }
+
+    // Re-check recently confirmed writes and roll back the ones a reorg dropped
+    match (contracts.is_empty(), CFG.reorg.enabled) {
+        (false, true) => spawn_reorg_worker(mongodb.clone(), contracts.clone(), CFG.reorg.clone()),
+        (true, true) => error!("Reorg re-check worker not started: no blockchain contract connection available"),
+        (_, false) => info!("Reorg re-check worker disabled by configuration"),
+    }
+
+    // Login challenges live in Redis so any replica can complete a login; memory is for single-node dev
+    let challenge_ttl = Duration::from_secs(CFG.siwe.ttl_secs);
+    let challenges = Arc::new(match CFG.siwe.challenge_store.as_str() {
+        "memory" => {
+            warn!("Login challenges are stored in process memory; logins only work against this instance");
+            ChallengeStore::memory(challenge_ttl)
+        }
+        _ => ChallengeStore::redis(redis_client.clone(), challenge_ttl),
+    });
+
+    let sessions = Arc::new(SessionStore::new(
+        redis_client.clone(),
+        Duration::from_secs(CFG.jwt.access_ttl_secs),
+        Duration::from_secs(CFG.jwt.refresh_ttl_secs),
+    ));
+
     info!("Starting Pharos API server");
-    let service = router::init_service(mongodb, redis_client, contract);
+    let service = router::init_service(mongodb, redis_client, contracts, reconcile_state, challenges, sessions);
 
     // Setup server address
     let address = format!("{}:{}", server_config.ip, server_config.port);
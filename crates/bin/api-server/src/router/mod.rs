@@ -21,23 +21,28 @@ use salvo::{
     serve_static::StaticDir,
     session::CookieStore,
 };
-use service::{db::init_mongodb, init_redis_client}; // Updated import
+use service::{db::init_mongodb, init_redis_client, ChallengeStore, SessionStore, WalletCodeCache}; // Updated import
 use std::{env, sync::Arc};
-use pharos_interact::{InvoiceContract, ContractQuerier, ContractWriter}; // Import for contract interaction
-use ethers::middleware::SignerMiddleware;
-use ethers::providers::{Http, Provider};
-use ethers::signers::LocalWallet;
+use pharos_interact::ContractRegistry; // Import for contract interaction
+use crate::worker::reconcile_worker::ReconcileState;
 
 
 pub mod middware;
 pub mod router;
 
+// Contract code rarely changes; a new deployment is picked up after this long
+const WALLET_CODE_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
 // --- Injection Middleware Struct ---
 #[derive(Clone)] // Clone is needed for the handler
 struct InjectConnections {
     mongodb: Arc<Database>, // Changed from db_conn: Arc<DatabaseConnection>
     redis_client: Arc<RedisClient>,
-    contract: Option<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>, // Contract connection
+    contracts: Arc<ContractRegistry>, // Contract connections by deployment name, live or in-memory fake
+    reconcile_state: Arc<ReconcileState>, // Latest invoice reconcile report
+    challenges: Arc<ChallengeStore>, // Issued login challenges
+    sessions: Arc<SessionStore>, // Login sessions and revoked access tokens
+    wallet_codes: Arc<WalletCodeCache>, // Which login addresses are contract wallets
 }
 
 #[async_trait]
@@ -45,11 +50,11 @@ impl Handler for InjectConnections {
     async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
         depot.inject(self.mongodb.clone()); // Updated
         depot.inject(self.redis_client.clone());
-        
-        // Inject contract connection if available
-        if let Some(contract) = &self.contract {
-            depot.inject(contract.clone());
-        }
+        depot.inject(self.reconcile_state.clone());
+        depot.inject(self.contracts.clone());
+        depot.inject(self.challenges.clone());
+        depot.inject(self.sessions.clone());
+        depot.inject(self.wallet_codes.clone());
         
         // Indicate that the next handler should be called
         ctrl.call_next(req, depot, res).await;
@@ -69,7 +74,14 @@ pub fn init_router() -> Router {
     let api_router = Router::with_path(&CFG.server.api_prefix) // Use configured prefix
         .push(router::init_user_router()) // Existing user/auth routes
         .push(router::init_enterprise_router()) // Add enterprise routes
-        .push(router::init_invoice_router()); // Add invoice routes
+        .push(router::init_invoice_router()) // Add invoice routes
+        .push(router::init_batch_router()) // Add invoice batch routes
+        .push(router::init_holding_router()) // Add RBT holding routes
+        .push(router::init_repayment_router()) // Add repayment routes
+        .push(router::init_chain_router()) // Add direct contract routes
+        .push(router::init_admin_router()) // Add admin routes
+        .push(router::init_jobs_router()) // Add chain job status routes
+        .push(router::init_erp_router()); // Add ERP integration routes (API key auth)
 
     let router = router.push(api_router);
 
@@ -99,7 +111,10 @@ pub fn init_router() -> Router {
 pub fn init_service(
     mongodb: Arc<Database>, 
     redis_client: Arc<RedisClient>,
-    contract: Option<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>
+    contracts: Arc<ContractRegistry>,
+    reconcile_state: Arc<ReconcileState>,
+    challenges: Arc<ChallengeStore>,
+    sessions: Arc<SessionStore>,
 ) -> Service {
     let router = init_router();
 
@@ -107,7 +122,11 @@ pub fn init_service(
     let injector = InjectConnections {
         mongodb, // Updated field name
         redis_client,
-        contract,
+        contracts,
+        reconcile_state,
+        challenges,
+        sessions,
+        wallet_codes: Arc::new(WalletCodeCache::new(WALLET_CODE_TTL)),
     };
 
     let cors = Cors::new()
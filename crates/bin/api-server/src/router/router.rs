@@ -1,6 +1,12 @@
 use salvo::Router;
 
-use crate::controller::{common_controller, enterprise_controller, invoice_controller, user_controller};
+use crate::controller::common_controller::RequireRole;
+use common::domain::entity::UserRole;
+
+use crate::controller::{
+    admin_controller, api_key_controller, batch_controller, chain_controller, common_controller, enterprise_controller, erp_controller, holding_controller,
+    invoice_controller, jobs_controller, repayment_controller, user_controller,
+};
 
 pub fn init_user_router() -> Router {
     let router = Router::with_path("/user");
@@ -9,12 +15,15 @@ pub fn init_user_router() -> Router {
         // Web3 认证相关路由
         .push(Router::with_path("/challenge").post(user_controller::challenge))
         .push(Router::with_path("/login").post(user_controller::login))
-        // 绑定企业路由 (需要认证)
-        .hoop(common_controller::auth_token)
+        .push(Router::with_path("/refresh").post(user_controller::refresh))
+        // 需要认证的路由
         .push(
-            Router::with_path("/bind-enterprise")
+            Router::new()
                 .hoop(common_controller::auth_token)
-                .post(user_controller::bind_enterprise),
+                .push(Router::with_path("/bind-enterprise").post(user_controller::bind_enterprise))
+                .push(Router::with_path("/logout").post(user_controller::logout))
+                .push(Router::with_path("/sessions").get(user_controller::list_sessions))
+                .push(Router::with_path("/session/del").delete(user_controller::delete_session)),
         )
 }
 
@@ -23,12 +32,29 @@ pub fn init_enterprise_router() -> Router {
     Router::with_path("/enterprise")
         .push(Router::with_path("/list").get(enterprise_controller::list_enterprises))
         .push(Router::with_path("/detail").get(enterprise_controller::get_enterprise_by_id))
-        .push(Router::with_path("/del").delete(enterprise_controller::delete_enterprise))
         .push(
             Router::with_path("/create")
                 .hoop(common_controller::auth_token)
                 .post(enterprise_controller::create_enterprise),
         )
+        // 企业管理员: 管理本企业 ERP API Key (平台管理员可管理任意企业)
+        .push(
+            Router::with_path("/api-key")
+                .hoop(common_controller::auth_token)
+                .hoop(RequireRole::any_of(&[UserRole::EnterpriseAdmin, UserRole::PlatformAdmin]))
+                .push(Router::with_path("/create").post(api_key_controller::create_api_key))
+                .push(Router::with_path("/list").get(api_key_controller::list_api_keys))
+                .push(Router::with_path("/rotate").post(api_key_controller::rotate_api_key))
+                .push(Router::with_path("/del").delete(api_key_controller::revoke_api_key)),
+        )
+        // 平台管理员: 审核 (更新状态) 与删除企业
+        .push(
+            Router::new()
+                .hoop(common_controller::auth_token)
+                .hoop(RequireRole::platform_admin())
+                .push(Router::with_path("/update/{id}").put(enterprise_controller::update_enterprise))
+                .push(Router::with_path("/del").delete(enterprise_controller::delete_enterprise)),
+        )
 }
 
 pub fn init_invoice_router() -> Router {
@@ -36,6 +62,89 @@ pub fn init_invoice_router() -> Router {
     Router::with_path("/invoice")
         .push(Router::with_path("/list").get(invoice_controller::list_invoices))
         .push(Router::with_path("/detail").get(invoice_controller::query_invoice_data))
-        .push(Router::with_path("/del").delete(invoice_controller::delete_invoice))
+        .push(Router::with_path("/history").hoop(common_controller::auth_token).get(invoice_controller::invoice_history))
         .push(Router::with_path("/create").hoop(common_controller::auth_token).post(invoice_controller::create_invoice))
+        .push(Router::with_path("/chain-create").hoop(common_controller::auth_token).post(invoice_controller::chain_create_invoices))
+        // 平台管理员: 审核与删除票据
+        .push(
+            Router::new()
+                .hoop(common_controller::auth_token)
+                .hoop(RequireRole::platform_admin())
+                .push(Router::with_path("/verify").post(invoice_controller::verify_invoice))
+                .push(Router::with_path("/del").delete(invoice_controller::delete_invoice)),
+        )
+}
+
+pub fn init_batch_router() -> Router {
+    // Base path for invoice batch (packaging) routes (all require authentication)
+    Router::with_path("/batch")
+        .hoop(common_controller::auth_token)
+        .push(Router::with_path("/list").get(batch_controller::list_batches))
+        .push(Router::with_path("/detail").get(batch_controller::get_batch))
+        .push(Router::with_path("/history").get(batch_controller::batch_history))
+        .push(Router::with_path("/create").post(batch_controller::create_batch))
+        .push(Router::with_path("/add-invoice").post(batch_controller::add_invoice))
+        .push(Router::with_path("/remove-invoice").post(batch_controller::remove_invoice))
+        .push(Router::with_path("/chain-create").post(batch_controller::create_chain_batch))
+        .push(Router::with_path("/chain-confirm").post(batch_controller::confirm_chain_batch))
+        .push(Router::with_path("/{id}/settlement").get(batch_controller::batch_settlement))
+}
+
+pub fn init_holding_router() -> Router {
+    // Base path for RBT share purchase and holding routes (all require authentication)
+    Router::with_path("/holding")
+        .hoop(common_controller::auth_token)
+        .push(Router::with_path("/purchase").post(holding_controller::purchase_shares))
+        .push(Router::with_path("/my").get(holding_controller::my_holdings))
+}
+
+pub fn init_repayment_router() -> Router {
+    // Base path for batch repayment routes (all require authentication)
+    Router::with_path("/repayment")
+        .hoop(common_controller::auth_token)
+        .push(Router::with_path("/list").get(repayment_controller::list_repayments))
+        .push(Router::with_path("/register").post(repayment_controller::register_repayment))
+}
+
+pub fn init_chain_router() -> Router {
+    // Base path for direct contract reads and admin operations
+    Router::with_path("/chain")
+        .push(Router::with_path("/token-batch").get(chain_controller::get_token_batch))
+        .push(Router::with_path("/user-batches").get(chain_controller::get_user_batches))
+        .push(
+            Router::new()
+                .hoop(common_controller::auth_token)
+                .hoop(RequireRole::platform_admin())
+                .push(Router::with_path("/invalidate-invoice").post(chain_controller::invalidate_invoice))
+                .push(Router::with_path("/pause").post(chain_controller::pause_contract))
+                .push(Router::with_path("/unpause").post(chain_controller::unpause_contract)),
+        )
+}
+
+pub fn init_admin_router() -> Router {
+    // Base path for platform administration routes
+    Router::with_path("/admin")
+        .hoop(common_controller::auth_token)
+        .hoop(RequireRole::platform_admin())
+        .push(Router::with_path("/reconcile/report").get(admin_controller::reconcile_report))
+        .push(Router::with_path("/reconcile/run").post(admin_controller::trigger_reconcile))
+        .push(Router::with_path("/outbox").get(admin_controller::list_outbox))
+        .push(Router::with_path("/chain-status").get(admin_controller::chain_status))
+}
+
+pub fn init_jobs_router() -> Router {
+    // Base path for asynchronous chain-write job status routes
+    Router::with_path("/jobs")
+        .hoop(common_controller::auth_token)
+        .push(Router::with_path("/{id}").get(jobs_controller::get_job))
+        .push(Router::with_path("/{id}/events").get(jobs_controller::job_events))
+}
+
+pub fn init_erp_router() -> Router {
+    // Base path for ERP integrations, authenticated by HMAC-signed API key requests
+    Router::with_path("/erp")
+        .hoop(common_controller::api_key_auth)
+        .push(Router::with_path("/invoice/create").post(erp_controller::create_invoices))
+        .push(Router::with_path("/invoice/list").get(erp_controller::list_invoices))
+        .push(Router::with_path("/invoice/detail").get(erp_controller::invoice_detail))
 }
@@ -0,0 +1,51 @@
+use crate::utils::res::{ResObj, res_json_custom, res_json_err};
+use common::domain::entity::UserRole;
+use mongodb::{Database, bson::oid::ObjectId};
+use salvo::prelude::*;
+use service::repository::UserRepository;
+
+pub fn current_user_address(depot: &Depot) -> Result<String, Json<ResObj<()>>> {
+    match depot.get::<String>("user_address") {
+        Ok(address_ref) => Ok(address_ref.clone()),
+        Err(e) => {
+            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
+            Err(res_json_custom(401, "User not authenticated"))
+        }
+    }
+}
+
+// Resolve the enterprise bound to the authenticated user (set by `auth_token`)
+pub async fn current_enterprise_id(depot: &Depot, mongodb: &Database) -> Result<ObjectId, Json<ResObj<()>>> {
+    let user_address = current_user_address(depot)?;
+
+    match UserRepository::new(mongodb).find_by_wallet_address(&user_address).await {
+        Ok(Some(user)) => match user.enterprise_id {
+            Some(enterprise_id) => Ok(enterprise_id),
+            None => Err(res_json_custom(403, "User is not bound to an enterprise")),
+        },
+        Ok(None) => Err(res_json_custom(401, "User not found")),
+        Err(e) => {
+            log::error!("Failed to load user {}: {}", user_address, e);
+            Err(res_json_err("Failed to load user"))
+        }
+    }
+}
+
+// Platform admins may act on behalf of any enterprise
+pub fn is_platform_admin(depot: &Depot) -> bool {
+    depot.get::<UserRole>("user_role").is_ok_and(|role| *role == UserRole::PlatformAdmin)
+}
+
+/// Allow the request only for users bound to one of `enterprises` (or platform admins); 403 with `denied` otherwise
+pub async fn require_enterprise(depot: &Depot, mongodb: &Database, enterprises: &[ObjectId], denied: &str) -> Result<(), Json<ResObj<()>>> {
+    if is_platform_admin(depot) {
+        return Ok(());
+    }
+    let enterprise_id = current_enterprise_id(depot, mongodb).await?;
+    if enterprises.contains(&enterprise_id) {
+        Ok(())
+    } else {
+        log::warn!("Enterprise {} denied: {}", enterprise_id, denied);
+        Err(res_json_custom(403, denied))
+    }
+}
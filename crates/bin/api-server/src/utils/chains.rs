@@ -0,0 +1,127 @@
+use crate::utils::res::{Res, ResObj, res_bad_request, res_contract_err, res_json_custom, res_json_err, res_json_ok};
+use common::domain::dto::chain_job_dto::{ChainJobDto, JobEffect};
+use common::domain::dto::contract_call_dto::ContractCall;
+use configs::cfgs::{Chains, Deployment};
+use redis::Client as RedisClient;
+use salvo::prelude::*;
+use service::JobQueue;
+use log::{error, info};
+use ethers::signers::LocalWallet;
+use pharos_interact::{
+    ChainContract, ConnectFuture, Connector, ContractQuerier, ContractRegistry, FakeInvoiceContract, GasPolicy, ManagedContract, RawTransactionSender, RemoteSigner,
+    SigningBackend,
+    decrypt_keystore, initialize_contract,
+};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Register every configured deployment. Each one connects in the background and fails over
+/// across its RPC URLs, so an unreachable node does not keep the server from starting; a
+/// deployment whose configuration is incomplete is left out and logged.
+pub async fn init_contract_registry(chains: &Chains, gas_policy: &GasPolicy) -> ContractRegistry {
+    let mut registry = ContractRegistry::new(&chains.default);
+    let interval = Duration::from_secs(chains.health_check_secs.max(1));
+    for (name, deployment) in chains.deployments() {
+        match managed(&deployment, gas_policy) {
+            Ok(contract) => {
+                info!("Contract deployment '{}' registered at {}", name, contract.contract_address());
+                contract.spawn_supervisor(interval);
+                registry.insert(&name, contract);
+            }
+            Err(e) => error!("Failed to set up contract deployment '{}': {}", name, e),
+        }
+    }
+    registry
+}
+
+fn managed(deployment: &Deployment, gas_policy: &GasPolicy) -> anyhow::Result<Arc<ManagedContract>> {
+    if deployment.fake {
+        return Ok(Arc::new(ManagedContract::fixed(Arc::new(FakeInvoiceContract::default()))));
+    }
+    dotenv::dotenv().ok();
+    // Empty values fall back to the single-deployment environment variables
+    let rpc_url = or_env(&deployment.rpc_url, "PHAROS_RPC_URL")?;
+    let contract_address = or_env(&deployment.contract_address, "INVOICE_CONTRACT_ADDRESS")?;
+    let signer = signing_backend(deployment)?;
+    info!("Deployment signer {:?}", signer.key_address());
+
+    let mut rpc_urls = vec![rpc_url];
+    rpc_urls.extend(deployment.rpc_urls.iter().filter(|url| !url.is_empty()).cloned());
+    let address = contract_address.clone();
+    let gas_policy = gas_policy.clone();
+    let confirmations = deployment.confirmations;
+    let connector: Connector = Arc::new(move |rpc_url: String| -> ConnectFuture {
+        let (address, signer, gas_policy) = (address.clone(), signer.clone(), gas_policy.clone());
+        Box::pin(async move {
+            let contract = initialize_contract(&rpc_url, &address, signer).await?;
+            Ok(Arc::new(contract.with_gas_policy(gas_policy).with_confirmations(confirmations)) as Arc<dyn ChainContract>)
+        })
+    });
+    Ok(Arc::new(ManagedContract::new(&contract_address, rpc_urls, connector)))
+}
+
+// Remote signer first, then an encrypted keystore, then a raw key from the environment
+fn signing_backend(deployment: &Deployment) -> anyhow::Result<Arc<dyn SigningBackend>> {
+    if !deployment.remote_signer_url.is_empty() {
+        let signer = RemoteSigner::new(&deployment.remote_signer_url, &deployment.remote_signer_key)?;
+        return Ok(Arc::new(signer));
+    }
+    if !deployment.keystore_path.is_empty() {
+        let password = read_env(env_name(&deployment.keystore_password_env, "SIGNER_KEYSTORE_PASSWORD"))?;
+        let wallet = decrypt_keystore(&deployment.keystore_path, &password)
+            .map_err(|e| anyhow::anyhow!("Failed to decrypt keystore {}: {}", deployment.keystore_path, e))?;
+        return Ok(Arc::new(wallet));
+    }
+    let private_key = read_env(env_name(&deployment.signer_key_env, "SIGNER_PRIVATE_KEY"))?;
+    let wallet = private_key.parse::<LocalWallet>().map_err(|e| anyhow::anyhow!("Failed to parse private key: {}", e))?;
+    Ok(Arc::new(wallet))
+}
+
+fn env_name<'a>(configured: &'a str, default: &'a str) -> &'a str {
+    if configured.is_empty() { default } else { configured }
+}
+
+fn or_env(value: &str, key: &str) -> anyhow::Result<String> {
+    if !value.is_empty() {
+        return Ok(value.to_string());
+    }
+    read_env(key)
+}
+
+fn read_env(key: &str) -> anyhow::Result<String> {
+    env::var(key).map_err(|_| anyhow::anyhow!("Failed to read {} from environment", key))
+}
+
+// Contract of a deployment together with its resolved name; `None` picks the default deployment
+pub fn obtain_contract(depot: &Depot, deployment: Option<&str>) -> Result<(String, Arc<dyn ChainContract>), Json<ResObj<()>>> {
+    let registry = depot.obtain::<Arc<ContractRegistry>>().expect("Contract registry not found");
+    let name = registry.resolve_name(deployment).to_string();
+    match registry.get(Some(&name)) {
+        Some(contract) => Ok((name, contract)),
+        None if !registry.is_default(&name) => Err(res_bad_request(&format!("Unknown deployment '{}'", name))),
+        None => {
+            log::warn!("Blockchain contract connection not available.");
+            Err(res_json_custom(503, "Blockchain connection unavailable"))
+        }
+    }
+}
+
+// Queue a contract write on a deployment (`None` for the default); the chain job worker sends it and applies `effect` once confirmed.
+// The call is simulated first so a revert is reported now instead of as a failed job.
+pub async fn enqueue_chain_job(depot: &Depot, deployment: Option<&str>, call: ContractCall, effect: JobEffect, reference: String, actor: &str) -> Res<ChainJobDto> {
+    let redis_client = depot.obtain::<Arc<RedisClient>>().expect("Redis client not found").clone();
+    let (deployment, contract) = obtain_contract(depot, deployment)?;
+    let method = call.method_name();
+    if let Err(e) = contract.simulate(&call).await {
+        log::warn!("{} for {} rejected before queueing: {}", method, actor, e);
+        return Err(res_contract_err(&e));
+    }
+    match JobQueue::new(redis_client).enqueue(&deployment, call, effect, Some(reference), actor).await {
+        Ok(job) => Ok(res_json_ok(Some(ChainJobDto::from(&job)))),
+        Err(e) => {
+            log::error!("Failed to queue {} for {}: {}", method, actor, e);
+            Err(res_json_err(&format!("Failed to queue {}", method)))
+        }
+    }
+}
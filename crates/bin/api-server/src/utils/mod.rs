@@ -1,5 +1,7 @@
+pub mod auth;
 pub mod captcha;
 pub mod md5;
 pub mod mysql;
 pub mod res;
 
+pub mod chains;
@@ -1,5 +1,7 @@
+use pharos_interact::ContractError;
 use salvo::{oapi::ToSchema, prelude::Json};
 use serde::Serialize;
+use service::LifecycleError;
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ResObj<T: ToSchema + 'static> {
@@ -84,3 +86,28 @@ pub fn match_ok_common_result_no_error<T: ToSchema>(res: Result<T, ()>) -> Res<T
         Err(_) => Err(res_json_custom(400, "服务器发生错误")),
     }
 }
+
+/// Map a lifecycle engine error onto the response codes used by the API.
+pub fn res_lifecycle_err(e: &LifecycleError) -> Json<ResObj<()>> {
+    match e {
+        LifecycleError::InvoiceNotFound(_) => res_not_found("Invoice not found"),
+        LifecycleError::BatchNotFound(_) => res_not_found("Batch not found"),
+        LifecycleError::IllegalInvoiceTransition { .. } | LifecycleError::IllegalBatchTransition { .. } | LifecycleError::Inconsistent(_) => {
+            res_bad_request(&e.to_string())
+        }
+        LifecycleError::ConcurrentModification(_) => res_json_custom(409, &e.to_string()),
+        LifecycleError::Database(_) => res_json_err("Database error"),
+    }
+}
+
+/// Map a contract error onto the response codes used by the API, so a rejected call
+/// (e.g. "Invoice already exists") is told apart from an unreachable node.
+pub fn res_contract_err(e: &ContractError) -> Json<ResObj<()>> {
+    match e {
+        ContractError::Revert(reason) => res_bad_request(&format!("Contract rejected the call: {}", reason)),
+        ContractError::InvalidInput(_) => res_bad_request(&e.to_string()),
+        ContractError::Rpc(_) => res_json_custom(503, "Blockchain node unavailable"),
+        ContractError::Dropped(_) => res_json_custom(504, &e.to_string()),
+        ContractError::Reverted { .. } | ContractError::Signer(_) => res_json_err(&e.to_string()),
+    }
+}
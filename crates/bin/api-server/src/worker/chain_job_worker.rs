@@ -0,0 +1,41 @@
+use log::{error, info, warn};
+use mongodb::Database;
+use pharos_interact::ContractRegistry;
+use redis::Client as RedisClient;
+use service::{ChainJobError, ChainJobRunner, JobQueue};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Spawn the worker that executes queued chain-write jobs.
+/// Jobs are broadcast one at a time so transactions from the shared signer keep their nonce order;
+/// their receipts are awaited concurrently by the runner.
+pub fn spawn_chain_job_worker(
+    mongodb: Arc<Database>,
+    redis_client: Arc<RedisClient>,
+    contracts: Arc<ContractRegistry>,
+) {
+    tokio::spawn(async move {
+        let queue = JobQueue::new(redis_client);
+        match queue.requeue_processing().await {
+            Ok(0) => {}
+            Ok(moved) => info!("Requeued {} chain jobs interrupted by a previous run", moved),
+            Err(e) => error!("Failed to requeue interrupted chain jobs: {}", e),
+        }
+
+        let runner = Arc::new(ChainJobRunner::new(&mongodb, queue));
+        info!("Chain job worker started");
+        loop {
+            match runner.run_next(&contracts).await {
+                Ok(_) => {}
+                Err(e @ ChainJobError::Unavailable(_)) => {
+                    warn!("Chain job postponed: {}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                Err(e) => {
+                    error!("Chain job worker error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
+}
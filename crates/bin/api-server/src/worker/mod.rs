@@ -0,0 +1,3 @@
+pub mod chain_job_worker;
+pub mod reconcile_worker;
+pub mod reorg_worker;
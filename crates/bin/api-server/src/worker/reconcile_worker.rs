@@ -0,0 +1,72 @@
+use common::domain::dto::reconcile_dto::ReconcileReportDto;
+use configs::cfgs::Reconcile as ReconcileConfig;
+use log::{error, info, warn};
+use mongodb::Database;
+use pharos_interact::ContractRegistry;
+use service::ReconcileService;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, RwLock};
+
+/// Shared between the background worker and the admin endpoints.
+#[derive(Default)]
+pub struct ReconcileState {
+    last_reports: RwLock<BTreeMap<String, ReconcileReportDto>>,
+    trigger: Notify,
+}
+
+impl ReconcileState {
+    /// Latest report of every deployment, ordered by deployment name.
+    pub async fn last_reports(&self) -> Vec<ReconcileReportDto> {
+        self.last_reports.read().await.values().cloned().collect()
+    }
+
+    /// Wake the worker for an immediate run instead of waiting for the next interval.
+    pub fn trigger(&self) {
+        self.trigger.notify_one();
+    }
+}
+
+/// Spawn the chain-to-database invoice reconciliation loop.
+pub fn spawn_reconcile_worker(
+    mongodb: Arc<Database>,
+    contracts: Arc<ContractRegistry>,
+    state: Arc<ReconcileState>,
+    config: ReconcileConfig,
+) {
+    tokio::spawn(async move {
+        let service = ReconcileService::new(&mongodb);
+        info!("Invoice reconcile worker started, interval {}s", config.interval_secs);
+        loop {
+            for (name, contract) in contracts.iter() {
+                if !contract.is_connected() {
+                    continue; // Picked up again once the supervisor reconnects
+                }
+                match service.run(contract.as_ref(), name, contracts.is_default(name), config.page_size).await {
+                    Ok(report) => {
+                        info!(
+                            "Invoice reconcile of '{}' finished: chain {}, db {}, imported {}, updated {}, drift {}",
+                            name,
+                            report.chain_count,
+                            report.db_count,
+                            report.imported,
+                            report.updated,
+                            report.drifts.len()
+                        );
+                        for drift in &report.drifts {
+                            warn!("Invoice drift on '{}' {:?}: {} {:?}", name, drift.kind, drift.invoice_number, drift.fields);
+                        }
+                        state.last_reports.write().await.insert(name.clone(), report);
+                    }
+                    Err(e) => error!("Invoice reconcile of '{}' failed: {}", name, e),
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(config.interval_secs)) => {}
+                _ = state.trigger.notified() => info!("Invoice reconcile triggered manually"),
+            }
+        }
+    });
+}
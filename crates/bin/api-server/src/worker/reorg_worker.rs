@@ -0,0 +1,28 @@
+use configs::cfgs::Reorg as ReorgConfig;
+use log::{error, info, warn};
+use mongodb::Database;
+use pharos_interact::ContractRegistry;
+use service::ReorgService;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Spawn the loop that re-checks recently confirmed writes of every deployment for reorgs.
+pub fn spawn_reorg_worker(mongodb: Arc<Database>, contracts: Arc<ContractRegistry>, config: ReorgConfig) {
+    tokio::spawn(async move {
+        let service = ReorgService::new(&mongodb);
+        info!("Reorg re-check worker started, interval {}s, depth {} blocks", config.interval_secs, config.depth);
+        loop {
+            for (name, contract) in contracts.iter() {
+                if !contract.is_connected() {
+                    continue; // Picked up again once the supervisor reconnects
+                }
+                match service.recheck(contract.as_ref(), name, contracts.is_default(name), config.depth).await {
+                    Ok(0) => {}
+                    Ok(rolled_back) => warn!("Rolled back {} reorged transactions on '{}'", rolled_back, name),
+                    Err(e) => error!("Reorg re-check of '{}' failed: {}", name, e),
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(config.interval_secs)).await;
+        }
+    });
+}
@@ -0,0 +1,131 @@
+use mongodb::bson::DateTime;
+use salvo::prelude::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use super::contract_call_dto::ContractCall;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum ChainJobStatus {
+    Queued,    // Accepted, waiting for the job worker
+    Sent,      // Transaction broadcast, waiting for the receipt
+    Confirmed, // Mined with status 1
+    Reverted,  // Mined with status 0
+    Failed,    // Could not be sent
+    EffectFailed, // Confirmed on chain, but the database update failed and needs manual repair
+}
+
+impl ChainJobStatus {
+    /// No further updates will follow
+    pub fn is_final(&self) -> bool {
+        matches!(self, ChainJobStatus::Confirmed | ChainJobStatus::Reverted | ChainJobStatus::Failed | ChainJobStatus::EffectFailed)
+    }
+}
+
+/// Database changes applied once the transaction of a job is confirmed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum JobEffect {
+    None,
+    /// Store the invoices of a `batchCreateInvoices` call
+    ImportInvoices,
+    #[serde(rename_all = "camelCase")]
+    RecordChainBatch { batch_id: String, chain_batch_id: String, stable_token_address: String },
+    #[serde(rename_all = "camelCase")]
+    ConfirmBatchIssue { batch_id: String, rbt_token_address: Option<String> },
+}
+
+/// Receipt summary of a mined job transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobReceiptDto {
+    pub transaction_hash: String,
+    pub block_number: Option<u64>,
+    pub gas_used: Option<String>, // Use String for U256 representation
+    pub success: bool,
+}
+
+/// A queued contract write, kept in Redis while it runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainJob {
+    pub id: String,
+    pub call: ContractCall,
+    pub effect: JobEffect,
+    pub reference: Option<String>,
+    pub requested_by: String,
+    pub status: ChainJobStatus,
+    pub outbox_id: Option<String>, // Outbox entry carrying the transaction
+    pub tx_hash: Option<String>,
+    pub receipt: Option<JobReceiptDto>,
+    pub error: Option<String>,
+    pub attempts: u32, // Times the worker picked the job up
+    #[serde(default)]
+    pub deployment: String, // Contract deployment the call goes to, empty for the default one
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl ChainJob {
+    pub fn new(id: String, deployment: String, call: ContractCall, effect: JobEffect, reference: Option<String>, requested_by: String) -> Self {
+        let now = DateTime::now();
+        Self {
+            id,
+            call,
+            effect,
+            reference,
+            requested_by,
+            status: ChainJobStatus::Queued,
+            outbox_id: None,
+            tx_hash: None,
+            receipt: None,
+            error: None,
+            attempts: 0,
+            deployment,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Data Transfer Object for reporting a ChainJob via API.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainJobDto {
+    /// 任务ID
+    pub id: String,
+    /// 合约方法
+    pub method: String,
+    /// 关联业务对象
+    pub reference: Option<String>,
+    /// 状态
+    pub status: ChainJobStatus,
+    /// 交易哈希
+    pub tx_hash: Option<String>,
+    /// 交易回执
+    pub receipt: Option<JobReceiptDto>,
+    /// 错误信息
+    pub error: Option<String>,
+    /// 合约部署名称
+    pub deployment: String,
+    /// 创建时间
+    pub created_at: DateTime,
+    /// 更新时间
+    pub updated_at: DateTime,
+}
+
+impl From<&ChainJob> for ChainJobDto {
+    fn from(data: &ChainJob) -> ChainJobDto {
+        ChainJobDto {
+            id: data.id.clone(),
+            method: data.call.method_name().to_string(),
+            reference: data.reference.clone(),
+            status: data.status.clone(),
+            tx_hash: data.tx_hash.clone(),
+            receipt: data.receipt.clone(),
+            error: data.error.clone(),
+            deployment: data.deployment.clone(),
+            created_at: data.created_at,
+            updated_at: data.updated_at,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use salvo::prelude::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// Connection health of one contract deployment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStatusDto {
+    pub deployment: String,
+    pub is_default: bool,
+    pub connected: bool,
+    pub contract_address: String,
+    pub rpc_host: Option<String>,       // Host of the RPC URL in use, without path or credentials
+    pub chain_id: Option<u64>,
+    pub head_block: Option<u64>,
+    pub signer_address: Option<String>,
+    pub signer_balance: Option<String>, // Native balance of the signer in wei
+    pub last_error: Option<String>,     // Last connection or health-check failure
+}
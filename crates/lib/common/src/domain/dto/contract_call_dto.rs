@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use super::invoice_dto::InvoiceDataDto;
+
+/// A contract write described as plain data, so it can be persisted before it is signed
+/// and replayed after a restart. Numeric arguments are decimal strings like in `InvoiceDataDto`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "camelCase")]
+pub enum ContractCall {
+    #[serde(rename_all = "camelCase")]
+    BatchCreateInvoices { invoices: Vec<InvoiceDataDto> },
+    #[serde(rename_all = "camelCase")]
+    CreateTokenBatch {
+        batch_id: String,
+        invoice_numbers: Vec<String>,
+        stable_token_address: String,
+        min_term: String,
+        max_term: String,
+        interest_rate: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ConfirmTokenBatchIssue { batch_id: String },
+    #[serde(rename_all = "camelCase")]
+    PurchaseShares { batch_id: String, amount: String },
+    #[serde(rename_all = "camelCase")]
+    InvalidateInvoice { invoice_number: String },
+    Pause,
+    Unpause,
+}
+
+impl ContractCall {
+    /// Solidity function name, used in logs and audit records
+    pub fn method_name(&self) -> &'static str {
+        match self {
+            ContractCall::BatchCreateInvoices { .. } => "batchCreateInvoices",
+            ContractCall::CreateTokenBatch { .. } => "createTokenBatch",
+            ContractCall::ConfirmTokenBatchIssue { .. } => "confirmTokenBatchIssue",
+            ContractCall::PurchaseShares { .. } => "purchaseShares",
+            ContractCall::InvalidateInvoice { .. } => "invalidateInvoice",
+            ContractCall::Pause => "pause",
+            ContractCall::Unpause => "unpause",
+        }
+    }
+}
@@ -1,2 +1,9 @@
+pub mod chain_job_dto;
+pub mod chain_status_dto;
+pub mod contract_call_dto;
 pub mod invoice_dto;
-pub mod query_invoice_dto;
\ No newline at end of file
+pub mod query_invoice_dto;
+pub mod reconcile_dto;
+pub mod session_dto;
+pub mod token_batch_dto;
+pub mod token_transfer_dto;
\ No newline at end of file
@@ -0,0 +1,34 @@
+use mongodb::bson::DateTime;
+use salvo::prelude::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// Kind of difference between the chain and the `invoices` collection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum DriftKind {
+    DbOnly,        // Stored in DB, not returned by queryInvoices
+    ChainOnly,     // On chain, missing from DB (imported by the run)
+    FieldMismatch, // Present on both sides with different values
+}
+
+/// One drifted invoice found by a reconciliation run.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceDriftDto {
+    pub invoice_number: String,
+    pub kind: DriftKind,
+    pub fields: Vec<String>, // Mismatched fields, empty for DbOnly/ChainOnly
+}
+
+/// Result of one chain-to-database reconciliation run.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileReportDto {
+    pub deployment: String, // Contract deployment that was reconciled
+    pub started_at: DateTime,
+    pub finished_at: DateTime,
+    pub chain_count: usize,
+    pub db_count: usize,
+    pub imported: usize, // Chain-only invoices inserted into DB
+    pub updated: usize,  // Invoices whose chain fields were refreshed
+    pub drifts: Vec<InvoiceDriftDto>,
+}
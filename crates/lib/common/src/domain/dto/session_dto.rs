@@ -0,0 +1,14 @@
+use salvo::prelude::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// An active login session of the current user.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDto {
+    pub id: String,
+    pub created_at: i64,   // Login time, Unix seconds
+    pub last_used_at: i64, // Last login or token refresh, Unix seconds
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub current: bool, // Session of the token making the request
+}
@@ -0,0 +1,35 @@
+use salvo::prelude::ToSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Data Transfer Object for an on-chain token batch (`getTokenBatch`).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[salvo(schema(example = json!({
+    "batchId": "6610c1f2a3b4c5d6e7f80912",
+    "payee": "0xabc1234567890abcdef1234567890abcdef123456",
+    "payer": "0xdef4567890abcdef1234567890abcdef12345678",
+    "stableToken": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+    "minTerm": "2592000", // 秒
+    "maxTerm": "7776000", // 秒
+    "interestRate": "850", // 基点
+    "totalAmount": "2000000000000000000",
+    "issueDate": "1678886400", // Unix timestamp
+    "isSigned": true,
+    "isIssued": true,
+    "invoiceNumbers": ["INV-12345", "INV-12346"]
+})))]
+pub struct TokenBatchDto {
+    pub batch_id: String,
+    pub payee: String,        // Use String for address representation
+    pub payer: String,        // Use String for address representation
+    pub stable_token: String, // Use String for address representation
+    pub min_term: String,     // Use String for U256 representation
+    pub max_term: String,     // Use String for U256 representation
+    pub interest_rate: String, // Use String for U256 representation
+    pub total_amount: String, // Use String for U256 representation
+    pub issue_date: String,   // Use String for U256 representation
+    pub is_signed: bool,
+    pub is_issued: bool,
+    pub invoice_numbers: Vec<String>,
+}
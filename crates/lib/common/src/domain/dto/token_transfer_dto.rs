@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// An ERC20 `Transfer` event decoded from a transaction receipt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenTransferDto {
+    pub token: String,  // Use String for address representation
+    pub from: String,   // Use String for address representation
+    pub to: String,     // Use String for address representation
+    pub amount: String, // Use String for U256 representation
+}
+
+/// A mined transaction together with the ERC20 transfers it emitted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferReceiptDto {
+    pub transaction_hash: String,
+    pub success: bool,
+    pub block_number: u64,
+    pub block_timestamp: u64, // Unix timestamp of the containing block
+    pub transfers: Vec<TokenTransferDto>,
+}
+
+/// A mined `purchaseShares` call sent by a wallet to the invoice contract.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharePurchaseReceiptDto {
+    pub transaction_hash: String,
+    pub success: bool,
+    pub block_number: u64,
+    pub block_timestamp: u64, // Unix timestamp of the containing block
+    pub buyer: String,        // Sender of the transaction, who paid and holds the shares
+    pub batch_id: String,     // On-chain token batch id
+    pub amount: String,       // Shares bought, use String for U256 representation
+}
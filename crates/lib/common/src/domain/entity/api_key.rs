@@ -0,0 +1,78 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub key_id: String,             // Public id sent in `X-Api-Key` (unique)
+    pub enterprise_id: ObjectId,    // Enterprise the key acts for
+    pub name: String,               // Label, e.g. the ERP system using it
+    pub salt: String,               // Derives the secret together with the server master secret
+    pub secret_hash: String,        // SHA-256 of the derived secret; the secret itself is never stored
+    pub created_by: String,         // Wallet of the user who created the key
+    pub created_at: DateTime,
+    pub rotated_at: Option<DateTime>,
+    pub last_used_at: Option<DateTime>,
+    pub revoked_at: Option<DateTime>,
+}
+
+// Helper methods
+impl ApiKey {
+    pub fn new(key_id: String, enterprise_id: ObjectId, name: String, salt: String, secret_hash: String, created_by: String) -> Self {
+        Self {
+            id: None,
+            key_id,
+            enterprise_id,
+            name,
+            salt,
+            secret_hash,
+            created_by,
+            created_at: DateTime::now(),
+            rotated_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+/// Data Transfer Object for sending ApiKey data out via API (without secret material).
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyDto {
+    /// API Key ID, 请求头 X-Api-Key 的值
+    pub key_id: String,
+    /// 企业ID (Database ObjectId)
+    pub enterprise_id: String,
+    /// 名称
+    pub name: String,
+    /// 创建人钱包地址
+    pub created_by: String,
+    /// 创建时间
+    pub created_at: DateTime,
+    /// 最近轮换时间
+    pub rotated_at: Option<DateTime>,
+    /// 最近使用时间
+    pub last_used_at: Option<DateTime>,
+    /// 吊销时间, 为空表示有效
+    pub revoked_at: Option<DateTime>,
+}
+
+impl From<&ApiKey> for ApiKeyDto {
+    fn from(data: &ApiKey) -> ApiKeyDto {
+        ApiKeyDto {
+            key_id: data.key_id.clone(),
+            enterprise_id: data.enterprise_id.to_string(),
+            name: data.name.clone(),
+            created_by: data.created_by.clone(),
+            created_at: data.created_at,
+            rotated_at: data.rotated_at,
+            last_used_at: data.last_used_at,
+            revoked_at: data.revoked_at,
+        }
+    }
+}
@@ -33,13 +33,14 @@ pub struct Invoice {
     pub token_batch: Option<String>,  // Token batch identifier (from DTO)
     pub is_cleared: Option<bool>,     // Blockchain clearance status
     pub is_valid: Option<bool>,       // Blockchain validity status
+    pub deployment: Option<String>,   // Contract deployment the invoice is registered on, None for the default one
     
     // --- Timestamps ---
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize,ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize,ToSchema)]
 pub enum InvoiceStatus {
     Pending,    // Created in DB, potentially not on chain yet
     Verified,   // Potentially corresponds to on-chain validity
@@ -79,6 +80,7 @@ impl Invoice {
             token_batch: None,
             is_cleared: None,
             is_valid: None,
+            deployment: None,
             created_at: now,
             updated_at: now,
         }
@@ -126,6 +128,8 @@ pub struct InvoiceDto {
     pub is_cleared: Option<bool>,
     /// 是否有效 (from Blockchain)
     pub is_valid: Option<bool>,
+    /// 合约部署名称 (为空表示默认部署)
+    pub deployment: Option<String>,
 }
 
 impl From<&Invoice> for InvoiceDto {
@@ -150,6 +154,7 @@ impl From<&Invoice> for InvoiceDto {
             token_batch: data.token_batch.clone(),
             is_cleared: data.is_cleared,
             is_valid: data.is_valid,
+            deployment: data.deployment.clone(),
         }
     }
 }
\ No newline at end of file
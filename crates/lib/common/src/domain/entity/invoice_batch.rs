@@ -1,6 +1,10 @@
 use mongodb::bson::{DateTime, oid::ObjectId, Decimal128};
+use salvo_oapi::ToSchema;
+use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
 
+use crate::utils::decimal::{to_big_decimal, to_decimal128};
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InvoiceBatch {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -9,17 +13,24 @@ pub struct InvoiceBatch {
     pub debtor_id: ObjectId,   // Reference to Enterprise
     pub rbt_token_address: Option<String>, // Blockchain address of the RBT token
     pub total_amount: Decimal128,
+    pub repaid_amount: Option<Decimal128>, // Sum of verified repayments (None until the first repayment)
     pub accepted_currency: String, // e.g., "USDC"
     pub interest_rate_apy: Decimal128,
     pub default_interest_rate_apy: Decimal128,
     pub issuance_date: DateTime,
     pub maturity_date: DateTime,
     pub status: InvoiceBatchStatus,
+    // --- Blockchain Specific Data (set when the token batch is created/issued on chain) ---
+    pub stable_token_address: Option<String>, // Stablecoin accepted by the on-chain batch
+    pub chain_batch_id: Option<String>,       // `_batchId` passed to createTokenBatch
+    pub create_tx_hash: Option<String>,       // createTokenBatch transaction hash
+    pub issue_tx_hash: Option<String>,        // confirmTokenBatchIssue transaction hash
+    pub deployment: Option<String>,           // Contract deployment of the on-chain batch, None for the default one
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum InvoiceBatchStatus {
     Packaging, // Invoices being added
     Issued,    // RBT minted, ready for market
@@ -47,14 +58,111 @@ impl InvoiceBatch {
             debtor_id,
             rbt_token_address: None,
             total_amount,
+            repaid_amount: None,
             accepted_currency,
             interest_rate_apy,
             default_interest_rate_apy,
             issuance_date: now,
             maturity_date,
             status: InvoiceBatchStatus::Packaging,
+            stable_token_address: None,
+            chain_batch_id: None,
+            create_tx_hash: None,
+            issue_tx_hash: None,
+            deployment: None,
             created_at: now,
             updated_at: now,
         }
     }
-} 
\ No newline at end of file
+
+    /// Amount still owed on the batch, never below zero
+    pub fn outstanding_amount(&self) -> Option<Decimal128> {
+        let total = to_big_decimal(&self.total_amount)?;
+        let repaid = match &self.repaid_amount {
+            Some(repaid) => to_big_decimal(repaid)?,
+            None => BigDecimal::from(0),
+        };
+        let outstanding = if repaid >= total { BigDecimal::from(0) } else { total - repaid };
+        to_decimal128(&outstanding)
+    }
+
+    /// Whether verified repayments cover the whole batch amount
+    pub fn is_fully_repaid(&self) -> bool {
+        match (to_big_decimal(&self.total_amount), self.repaid_amount.as_ref().and_then(to_big_decimal)) {
+            (Some(total), Some(repaid)) => repaid >= total,
+            _ => false,
+        }
+    }
+}
+
+/// Data Transfer Object for sending InvoiceBatch data out via API.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct InvoiceBatchDto {
+    /// 批次Id (Database ObjectId)
+    pub id: String,
+    /// 债权人企业ID (Database ObjectId)
+    pub creditor_id: String,
+    /// 债务人企业ID (Database ObjectId)
+    pub debtor_id: String,
+    /// RBT 代币地址 (from Blockchain)
+    pub rbt_token_address: Option<String>,
+    /// 批次总金额 (所有票据金额之和)
+    pub total_amount: String,
+    /// 已还款金额
+    pub repaid_amount: String,
+    /// 待还款金额
+    pub outstanding_amount: String,
+    /// 接受的还款币种
+    pub accepted_currency: String,
+    /// 年化利率
+    pub interest_rate_apy: String,
+    /// 违约年化利率
+    pub default_interest_rate_apy: String,
+    /// 发行日期
+    pub issuance_date: DateTime,
+    /// 到期日期
+    pub maturity_date: DateTime,
+    /// 状态
+    pub status: InvoiceBatchStatus,
+    /// 稳定币地址 (from Blockchain)
+    pub stable_token_address: Option<String>,
+    /// 链上批次ID (from Blockchain)
+    pub chain_batch_id: Option<String>,
+    /// 链上创建交易哈希
+    pub create_tx_hash: Option<String>,
+    /// 链上发行交易哈希
+    pub issue_tx_hash: Option<String>,
+    /// 合约部署名称 (为空表示默认部署)
+    pub deployment: Option<String>,
+    /// 创建时间
+    pub created_at: DateTime,
+    /// 更新时间
+    pub updated_at: DateTime,
+}
+
+impl From<&InvoiceBatch> for InvoiceBatchDto {
+    fn from(data: &InvoiceBatch) -> InvoiceBatchDto {
+        InvoiceBatchDto {
+            id: data.id.map(|id| id.to_string()).unwrap_or_default(),
+            creditor_id: data.creditor_id.to_string(),
+            debtor_id: data.debtor_id.to_string(),
+            rbt_token_address: data.rbt_token_address.clone(),
+            total_amount: data.total_amount.to_string(),
+            repaid_amount: data.repaid_amount.map(|v| v.to_string()).unwrap_or_else(|| "0".to_string()),
+            outstanding_amount: data.outstanding_amount().map(|v| v.to_string()).unwrap_or_default(),
+            accepted_currency: data.accepted_currency.clone(),
+            interest_rate_apy: data.interest_rate_apy.to_string(),
+            default_interest_rate_apy: data.default_interest_rate_apy.to_string(),
+            issuance_date: data.issuance_date,
+            maturity_date: data.maturity_date,
+            status: data.status.clone(),
+            stable_token_address: data.stable_token_address.clone(),
+            chain_batch_id: data.chain_batch_id.clone(),
+            create_tx_hash: data.create_tx_hash.clone(),
+            issue_tx_hash: data.issue_tx_hash.clone(),
+            deployment: data.deployment.clone(),
+            created_at: data.created_at,
+            updated_at: data.updated_at,
+        }
+    }
+}
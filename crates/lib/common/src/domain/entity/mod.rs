@@ -6,6 +6,10 @@ pub mod invoice_batch;
 pub mod rbt_holding;
 pub mod repayment;
 pub mod settlement_nft;
+pub mod status_transition;
+pub mod tx_outbox;
+pub mod api_key;
+pub mod share_purchase;
 // Optional: Re-export entities for easier access
 // pub use user::Entity as User;
 // pub use login_log::Entity as LoginLog; 
@@ -18,3 +22,7 @@ pub use invoice_batch::{InvoiceBatch, InvoiceBatchStatus};
 pub use rbt_holding::RbtHolding;
 pub use repayment::Repayment;
 pub use settlement_nft::SettlementNft;
+pub use status_transition::{StatusTransition, TransitionEntity};
+pub use tx_outbox::{TxOutboxEntry, TxOutboxStatus};
+pub use api_key::{ApiKey, ApiKeyDto};
+pub use share_purchase::{SharePurchase, SharePurchaseDto};
@@ -1,4 +1,5 @@
 use mongodb::bson::{DateTime, oid::ObjectId, Decimal128};
+use salvo_oapi::ToSchema;
 use serde::{Deserialize, Serialize};
 use super::{user, invoice_batch}; // Import related entities
 
@@ -24,4 +25,31 @@ impl RbtHolding {
             updated_at: now,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Data Transfer Object for sending RbtHolding data out via API.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RbtHoldingDto {
+    /// 持仓Id (Database ObjectId)
+    pub id: String,
+    /// 用户ID (Database ObjectId)
+    pub user_id: String,
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// 持有的 RBT 数量
+    pub amount: String,
+    /// 更新时间
+    pub updated_at: DateTime,
+}
+
+impl From<&RbtHolding> for RbtHoldingDto {
+    fn from(data: &RbtHolding) -> RbtHoldingDto {
+        RbtHoldingDto {
+            id: data.id.map(|id| id.to_string()).unwrap_or_default(),
+            user_id: data.user_id.to_string(),
+            batch_id: data.batch_id.to_string(),
+            amount: data.amount.to_string(),
+            updated_at: data.updated_at,
+        }
+    }
+}
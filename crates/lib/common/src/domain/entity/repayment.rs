@@ -1,4 +1,5 @@
 use mongodb::bson::{DateTime, oid::ObjectId, Decimal128};
+use salvo_oapi::ToSchema;
 use serde::{Deserialize, Serialize};
 use super::{enterprise, invoice_batch}; // Import related entities
 
@@ -37,4 +38,40 @@ impl Repayment {
             created_at: now,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Data Transfer Object for sending Repayment data out via API.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RepaymentDto {
+    /// 还款记录Id (Database ObjectId)
+    pub id: String,
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// 还款企业ID (Database ObjectId)
+    pub debtor_id: String,
+    /// 还款金额
+    pub amount: String,
+    /// 还款币种
+    pub currency: String,
+    /// 链上交易哈希
+    pub transaction_hash: String,
+    /// 链上还款时间
+    pub repayment_timestamp: DateTime,
+    /// 创建时间
+    pub created_at: DateTime,
+}
+
+impl From<&Repayment> for RepaymentDto {
+    fn from(data: &Repayment) -> RepaymentDto {
+        RepaymentDto {
+            id: data.id.map(|id| id.to_string()).unwrap_or_default(),
+            batch_id: data.batch_id.to_string(),
+            debtor_id: data.debtor_id.to_string(),
+            amount: data.amount.to_string(),
+            currency: data.currency.clone(),
+            transaction_hash: data.transaction_hash.clone(),
+            repayment_timestamp: data.repayment_timestamp,
+            created_at: data.created_at,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use mongodb::bson::{DateTime, oid::ObjectId};
+use salvo_oapi::ToSchema;
 use serde::{Deserialize, Serialize};
 use super::invoice_batch; // Import related entity
 
@@ -33,3 +34,36 @@ impl SettlementNft {
         }
     }
 }
+
+/// Data Transfer Object for sending SettlementNft data out via API.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct SettlementNftDto {
+    /// 结算凭证Id (Database ObjectId)
+    pub id: String,
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// NFT 合约地址
+    pub nft_contract_address: String,
+    /// NFT Token ID
+    pub token_id: String,
+    /// 债权人持有地址
+    pub creditor_owner_address: Option<String>,
+    /// 债务人持有地址
+    pub debtor_owner_address: Option<String>,
+    /// 发行时间
+    pub issuance_timestamp: DateTime,
+}
+
+impl From<&SettlementNft> for SettlementNftDto {
+    fn from(data: &SettlementNft) -> SettlementNftDto {
+        SettlementNftDto {
+            id: data.id.map(|id| id.to_string()).unwrap_or_default(),
+            batch_id: data.batch_id.to_string(),
+            nft_contract_address: data.nft_contract_address.clone(),
+            token_id: data.token_id.clone(),
+            creditor_owner_address: data.creditor_owner_address.clone(),
+            debtor_owner_address: data.debtor_owner_address.clone(),
+            issuance_timestamp: data.issuance_timestamp,
+        }
+    }
+}
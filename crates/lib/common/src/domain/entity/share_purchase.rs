@@ -0,0 +1,75 @@
+use mongodb::bson::{DateTime, oid::ObjectId, Decimal128};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SharePurchase {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub batch_id: ObjectId,       // Reference to InvoiceBatch
+    pub user_id: ObjectId,        // Reference to User (the investor)
+    pub buyer: String,            // Wallet that sent and paid for the purchase
+    pub amount: Decimal128,       // Shares bought
+    pub transaction_hash: String, // On-chain purchaseShares transaction hash (unique)
+    pub purchase_timestamp: DateTime, // Block time of the transaction
+    pub created_at: DateTime,     // When the record was created in the DB
+}
+
+// Helper methods
+impl SharePurchase {
+    pub fn new(
+        batch_id: ObjectId,
+        user_id: ObjectId,
+        buyer: String,
+        amount: Decimal128,
+        transaction_hash: String,
+        purchase_timestamp: DateTime,
+    ) -> Self {
+        Self {
+            id: None,
+            batch_id,
+            user_id,
+            buyer,
+            amount,
+            transaction_hash,
+            purchase_timestamp,
+            created_at: DateTime::now(),
+        }
+    }
+}
+
+/// Data Transfer Object for sending SharePurchase data out via API.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct SharePurchaseDto {
+    /// 购买记录Id (Database ObjectId)
+    pub id: String,
+    /// 批次ID (Database ObjectId)
+    pub batch_id: String,
+    /// 投资者用户ID (Database ObjectId)
+    pub user_id: String,
+    /// 购买钱包地址
+    pub buyer: String,
+    /// 购买份额
+    pub amount: String,
+    /// 链上交易哈希
+    pub transaction_hash: String,
+    /// 链上购买时间
+    pub purchase_timestamp: DateTime,
+    /// 创建时间
+    pub created_at: DateTime,
+}
+
+impl From<&SharePurchase> for SharePurchaseDto {
+    fn from(data: &SharePurchase) -> SharePurchaseDto {
+        SharePurchaseDto {
+            id: data.id.map(|id| id.to_string()).unwrap_or_default(),
+            batch_id: data.batch_id.to_string(),
+            user_id: data.user_id.to_string(),
+            buyer: data.buyer.clone(),
+            amount: data.amount.to_string(),
+            transaction_hash: data.transaction_hash.clone(),
+            purchase_timestamp: data.purchase_timestamp,
+            created_at: data.created_at,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// Audit record of a single lifecycle status change.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusTransition {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub entity_type: TransitionEntity,
+    pub entity_id: ObjectId,   // Reference to Invoice or InvoiceBatch
+    pub from_status: String,   // Status name before the change, e.g. "Packaging"
+    pub to_status: String,     // Status name after the change, e.g. "Issued"
+    pub actor: String,         // Wallet address of the user, or a system component name
+    pub created_at: DateTime,  // When the transition happened
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum TransitionEntity {
+    Invoice,
+    InvoiceBatch,
+}
+
+// Helper methods
+impl StatusTransition {
+    pub fn new(entity_type: TransitionEntity, entity_id: ObjectId, from_status: String, to_status: String, actor: String) -> Self {
+        Self {
+            id: None,
+            entity_type,
+            entity_id,
+            from_status,
+            to_status,
+            actor,
+            created_at: DateTime::now(),
+        }
+    }
+}
+
+/// Data Transfer Object for sending StatusTransition data out via API.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StatusTransitionDto {
+    /// 实体类型
+    pub entity_type: TransitionEntity,
+    /// 实体ID (Database ObjectId)
+    pub entity_id: String,
+    /// 变更前状态
+    pub from_status: String,
+    /// 变更后状态
+    pub to_status: String,
+    /// 操作者
+    pub actor: String,
+    /// 变更时间
+    pub created_at: DateTime,
+}
+
+impl From<&StatusTransition> for StatusTransitionDto {
+    fn from(data: &StatusTransition) -> StatusTransitionDto {
+        StatusTransitionDto {
+            entity_type: data.entity_type.clone(),
+            entity_id: data.entity_id.to_string(),
+            from_status: data.from_status.clone(),
+            to_status: data.to_status.clone(),
+            actor: data.actor.clone(),
+            created_at: data.created_at,
+        }
+    }
+}
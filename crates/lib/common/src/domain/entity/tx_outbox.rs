@@ -0,0 +1,132 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::dto::chain_job_dto::JobEffect;
+use crate::domain::dto::contract_call_dto::ContractCall;
+
+/// A contract write tracked from intent to receipt, so in-flight transactions survive restarts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxOutboxEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub call: ContractCall,           // Intended contract call
+    pub reference: Option<String>,    // Business object the call belongs to, e.g. "batch:<id>"
+    pub status: TxOutboxStatus,
+    pub sender: Option<String>,       // Signing account
+    pub tx_hash: Option<String>,      // Set once signed, before broadcast
+    pub nonce: Option<i64>,
+    pub raw_tx: Option<String>,       // Signed RLP, kept for re-broadcast
+    #[serde(default)]
+    pub replaced_tx_hashes: Vec<String>, // Earlier transactions with the same nonce, any of them may still be mined
+    pub block_number: Option<i64>,    // From the receipt
+    pub gas_used: Option<String>,     // From the receipt
+    pub error: Option<String>,        // Last error, if any
+    pub attempts: i32,                // Broadcast attempts
+    pub deployment: Option<String>,   // Contract deployment the call goes to, None for the default one
+    #[serde(default)]
+    pub applied_effect: Option<JobEffect>, // Database change made once confirmed, undone if a reorg drops the tx
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum TxOutboxStatus {
+    Pending,   // Recorded, not signed yet
+    Signed,    // Signed and persisted, broadcast not confirmed
+    Submitted, // Accepted by the node, waiting for a receipt
+    Confirmed, // Mined with status 1
+    Reverted,  // Mined with status 0
+    Failed,    // Could not be signed or broadcast
+    Reorged,   // Confirmed, then dropped by a reorg; its applied effect was rolled back
+}
+
+impl TxOutboxStatus {
+    /// Entries the resumer still has to drive to a receipt
+    pub fn is_open(&self) -> bool {
+        matches!(self, TxOutboxStatus::Pending | TxOutboxStatus::Signed | TxOutboxStatus::Submitted)
+    }
+}
+
+// Helper methods
+impl TxOutboxEntry {
+    pub fn new(call: ContractCall, reference: Option<String>, deployment: Option<String>) -> Self {
+        let now = DateTime::now();
+        Self {
+            id: None,
+            call,
+            reference,
+            status: TxOutboxStatus::Pending,
+            sender: None,
+            tx_hash: None,
+            nonce: None,
+            raw_tx: None,
+            replaced_tx_hashes: Vec::new(),
+            block_number: None,
+            gas_used: None,
+            error: None,
+            attempts: 0,
+            deployment,
+            applied_effect: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Data Transfer Object for sending TxOutboxEntry data out via API.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct TxOutboxEntryDto {
+    /// 记录Id (Database ObjectId)
+    pub id: String,
+    /// 合约方法
+    pub method: String,
+    /// 关联业务对象
+    pub reference: Option<String>,
+    /// 状态
+    pub status: TxOutboxStatus,
+    /// 签名账户
+    pub sender: Option<String>,
+    /// 交易哈希
+    pub tx_hash: Option<String>,
+    /// 交易 nonce
+    pub nonce: Option<i64>,
+    /// 被替换的交易哈希
+    pub replaced_tx_hashes: Vec<String>,
+    /// 所在区块
+    pub block_number: Option<i64>,
+    /// 消耗 gas
+    pub gas_used: Option<String>,
+    /// 错误信息
+    pub error: Option<String>,
+    /// 广播次数
+    pub attempts: i32,
+    /// 合约部署名称
+    pub deployment: Option<String>,
+    /// 创建时间
+    pub created_at: DateTime,
+    /// 更新时间
+    pub updated_at: DateTime,
+}
+
+impl From<&TxOutboxEntry> for TxOutboxEntryDto {
+    fn from(data: &TxOutboxEntry) -> TxOutboxEntryDto {
+        TxOutboxEntryDto {
+            id: data.id.map(|id| id.to_string()).unwrap_or_default(),
+            method: data.call.method_name().to_string(),
+            reference: data.reference.clone(),
+            status: data.status.clone(),
+            sender: data.sender.clone(),
+            tx_hash: data.tx_hash.clone(),
+            nonce: data.nonce,
+            replaced_tx_hashes: data.replaced_tx_hashes.clone(),
+            block_number: data.block_number,
+            gas_used: data.gas_used.clone(),
+            error: data.error.clone(),
+            attempts: data.attempts,
+            deployment: data.deployment.clone(),
+            created_at: data.created_at,
+            updated_at: data.updated_at,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use mongodb::bson::{DateTime, oid::ObjectId};
 use serde::{Deserialize, Serialize};
 
+use super::enterprise::Enterprise;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct User {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -14,8 +16,9 @@ pub struct User {
     pub login_timestamp: DateTime, // Keep track of the last login
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum UserRole {
+    #[default]
     Investor,
     EnterpriseAdmin,
     PlatformAdmin,
@@ -41,6 +44,44 @@ impl User {
         self.login_timestamp = DateTime::now();
         self.updated_at = DateTime::now();
     }
+
+    /// Whether this user may bind users to `enterprise`: its own wallet, an EnterpriseAdmin
+    /// already bound to it, or a PlatformAdmin
+    pub fn can_bind_to(&self, enterprise: &Enterprise) -> bool {
+        match self.role {
+            UserRole::PlatformAdmin => true,
+            UserRole::EnterpriseAdmin if enterprise.id.is_some() && self.enterprise_id == enterprise.id => true,
+            _ => self.wallet_address.eq_ignore_ascii_case(&enterprise.wallet_address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_enterprise_or_admins_can_bind() {
+        let mut enterprise = Enterprise::new("Acme".to_string(), "0xAbC0000000000000000000000000000000000001".to_string());
+        enterprise.id = Some(ObjectId::new());
+
+        let owner = User::new("0xabc0000000000000000000000000000000000001".to_string(), String::new(), UserRole::Investor);
+        assert!(owner.can_bind_to(&enterprise));
+
+        let stranger = User::new("0x0000000000000000000000000000000000000bad".to_string(), String::new(), UserRole::Investor);
+        assert!(!stranger.can_bind_to(&enterprise));
+
+        // An EnterpriseAdmin only administers the enterprise it is bound to
+        let mut admin = User::new("0x0000000000000000000000000000000000000a0a".to_string(), String::new(), UserRole::EnterpriseAdmin);
+        assert!(!admin.can_bind_to(&enterprise));
+        admin.enterprise_id = enterprise.id;
+        assert!(admin.can_bind_to(&enterprise));
+        admin.enterprise_id = Some(ObjectId::new());
+        assert!(!admin.can_bind_to(&enterprise));
+
+        let platform = User::new("0x0000000000000000000000000000000000000f0f".to_string(), String::new(), UserRole::PlatformAdmin);
+        assert!(platform.can_bind_to(&enterprise));
+    }
 }
 
 
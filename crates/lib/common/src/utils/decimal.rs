@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive};
+use mongodb::bson::Decimal128;
+
+/// 将 `Decimal128` 转换为 `BigDecimal` 以便进行算术运算
+pub fn to_big_decimal(value: &Decimal128) -> Option<BigDecimal> {
+    BigDecimal::from_str(&value.to_string()).ok()
+}
+
+/// 将 `BigDecimal` 转换回 `Decimal128` 以便存储
+pub fn to_decimal128(value: &BigDecimal) -> Option<Decimal128> {
+    Decimal128::from_str(&value.normalized().to_plain_string()).ok()
+}
+
+/// 判断是否为正整数 (链上 uint256 金额要求)
+pub fn is_positive_integer(value: &Decimal128) -> bool {
+    match to_big_decimal(value) {
+        Some(value) => value.is_integer() && value > BigDecimal::from(0),
+        None => false,
+    }
+}
+
+/// 累加多个 `Decimal128` 金额 (如同一交易中的多笔转账)
+pub fn sum_decimals(values: &[Decimal128]) -> Option<Decimal128> {
+    let mut total = BigDecimal::from(0);
+    for value in values {
+        total += to_big_decimal(value)?;
+    }
+    to_decimal128(&total)
+}
+
+/// 将年化利率百分比 (如 8.5) 转换为合约使用的基点 (如 850)
+pub fn apy_to_basis_points(apy: &Decimal128) -> Option<u64> {
+    let apy = to_big_decimal(apy)?;
+    (apy * BigDecimal::from(100)).with_scale_round(0, RoundingMode::HalfUp).to_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apy_to_basis_points_test() {
+        let apy = Decimal128::from_str("8.5").unwrap();
+        assert_eq!(apy_to_basis_points(&apy), Some(850));
+
+        let apy = Decimal128::from_str("5").unwrap();
+        assert_eq!(apy_to_basis_points(&apy), Some(500));
+
+        let negative = Decimal128::from_str("-1").unwrap();
+        assert_eq!(apy_to_basis_points(&negative), None);
+    }
+
+    #[test]
+    fn decimal128_round_trip_test() {
+        let value = Decimal128::from_str("1000000000000000000").unwrap();
+        let big = to_big_decimal(&value).unwrap();
+        let doubled = to_decimal128(&(big * BigDecimal::from(2))).unwrap();
+        assert_eq!(doubled.to_string(), "2000000000000000000");
+    }
+}
@@ -1,4 +1,5 @@
 pub mod get_time;
+pub mod decimal;
 
 use std::sync::{Mutex, OnceLock};
 use snowflake::SnowflakeIdBucket;
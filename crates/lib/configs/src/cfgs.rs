@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use structopt::StructOpt;
 
 /// 配置文件
@@ -8,10 +9,27 @@ pub struct Configs {
     pub server: Server,
     pub redis: Redis,
     pub jwt: Jwt,
+    /// 钱包登录 (EIP-4361) 配置
+    #[serde(default)]
+    pub siwe: Siwe,
+    /// ERP 系统 API Key 配置
+    #[serde(default)]
+    pub api_keys: ApiKeys,
     pub kafka: Kafka,
     ///  数据库 配置
     pub database: Database,
-
+    /// 链上与数据库票据对账配置
+    #[serde(default)]
+    pub reconcile: Reconcile,
+    /// 链上交易 gas 配置
+    #[serde(default)]
+    pub gas: Gas,
+    /// 链上交易重组复查配置
+    #[serde(default)]
+    pub reorg: Reorg,
+    /// 链与合约部署配置
+    #[serde(default)]
+    pub chains: Chains,
 }
 
 /// server 配置文件
@@ -40,6 +58,70 @@ pub struct Redis {
 #[derive(Clone,Debug, Deserialize)]
 pub struct Jwt {
     pub secret: String,
+    /// 访问令牌有效期(秒)
+    #[serde(default = "default_access_ttl_secs")]
+    pub access_ttl_secs: u64,
+    /// 刷新令牌 (登录会话) 有效期(秒)
+    #[serde(default = "default_refresh_ttl_secs")]
+    pub refresh_ttl_secs: u64,
+    /// 平台管理员钱包地址, 登录时授予 PlatformAdmin 角色
+    #[serde(default)]
+    pub platform_admins: Vec<String>,
+}
+
+fn default_access_ttl_secs() -> u64 {
+    15 * 60
+}
+
+fn default_refresh_ttl_secs() -> u64 {
+    7 * 24 * 3600
+}
+
+/// 钱包登录 (EIP-4361) 配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Siwe {
+    /// 登录消息中的域名, 须与前端页面的 host 一致
+    pub domain: String,
+    /// 登录消息中的 URI
+    pub uri: String,
+    /// 登录消息中的链 ID
+    pub chain_id: u64,
+    /// 钱包中展示的登录说明
+    pub statement: String,
+    /// 登录消息有效期(秒)
+    pub ttl_secs: u64,
+    /// 登录挑战存储: redis (多实例共享) / memory (单机开发)
+    pub challenge_store: String,
+}
+
+impl Default for Siwe {
+    fn default() -> Self {
+        Self {
+            domain: "localhost:3000".to_string(),
+            uri: "http://localhost:3000".to_string(),
+            chain_id: 688688,
+            statement: "Sign in to the Pharos RWA platform.".to_string(),
+            ttl_secs: 300,
+            challenge_store: "redis".to_string(),
+        }
+    }
+}
+
+/// ERP 系统 API Key (HMAC 请求签名) 配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ApiKeys {
+    /// 派生各 API Key 密钥的主密钥, 修改后已发放的密钥全部失效
+    pub master_secret: String,
+    /// 请求时间戳允许的偏差(秒), 随机数在两倍时长内不可重复使用
+    pub max_skew_secs: u64,
+}
+
+impl Default for ApiKeys {
+    fn default() -> Self {
+        Self { master_secret: "pharos_rwa_api_keys".to_string(), max_skew_secs: 300 }
+    }
 }
 
 /// Kafka 配置文件
@@ -76,6 +158,116 @@ pub struct Database {
     pub sync_tables: bool,
 }
 
+/// 票据对账任务配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Reconcile {
+    /// 是否启用后台对账
+    pub enabled: bool,
+    /// 对账间隔(秒)
+    pub interval_secs: u64,
+    /// 每批处理的票据数量
+    pub page_size: usize,
+}
+
+impl Default for Reconcile {
+    fn default() -> Self {
+        Self { enabled: true, interval_secs: 300, page_size: 100 }
+    }
+}
+
+/// 链上交易重组复查配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Reorg {
+    /// 是否启用后台重组复查
+    pub enabled: bool,
+    /// 复查间隔(秒)
+    pub interval_secs: u64,
+    /// 复查最近多少个区块内确认的交易
+    pub depth: u64,
+}
+
+impl Default for Reorg {
+    fn default() -> Self {
+        Self { enabled: true, interval_secs: 60, depth: 64 }
+    }
+}
+
+/// 链上交易 gas 配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Gas {
+    /// 交易类型: eip1559 / legacy
+    pub mode: String,
+    /// gas limit 在估算值上增加的百分比
+    pub buffer_percent: u64,
+    /// 最高 gas 费用上限 (gwei, legacy 模式下为 gas price 上限), 不设置则不限制
+    pub max_fee_gwei: Option<f64>,
+    /// 固定小费 (gwei), 不设置则使用节点估算
+    pub priority_fee_gwei: Option<f64>,
+}
+
+impl Default for Gas {
+    fn default() -> Self {
+        Self { mode: "eip1559".to_string(), buffer_percent: 25, max_fee_gwei: None, priority_fee_gwei: None }
+    }
+}
+
+/// 链与合约部署配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Chains {
+    /// 默认部署名称, 未指定部署的请求与历史数据使用它
+    pub default: String,
+    /// 合约部署, 键为部署名称
+    pub deployments: BTreeMap<String, Deployment>,
+    /// 节点连接健康检查间隔 (秒)
+    pub health_check_secs: u64,
+}
+
+impl Default for Chains {
+    fn default() -> Self {
+        Self { default: "default".to_string(), deployments: BTreeMap::new(), health_check_secs: 15 }
+    }
+}
+
+impl Chains {
+    /// 配置的部署; 未配置时为按环境变量连接的单个默认部署
+    pub fn deployments(&self) -> BTreeMap<String, Deployment> {
+        if self.deployments.is_empty() {
+            return BTreeMap::from([(self.default.clone(), Deployment::default())]);
+        }
+        self.deployments.clone()
+    }
+}
+
+/// 单个合约部署
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Deployment {
+    /// 节点 RPC 地址, 为空时读取环境变量 PHAROS_RPC_URL
+    pub rpc_url: String,
+    /// 备用节点 RPC 地址, 主地址不可用时依次切换
+    pub rpc_urls: Vec<String>,
+    /// 票据合约地址, 为空时读取环境变量 INVOICE_CONTRACT_ADDRESS
+    pub contract_address: String,
+    /// 保存签名私钥的环境变量名, 为空时使用 SIGNER_PRIVATE_KEY
+    pub signer_key_env: String,
+    /// 加密 JSON keystore 文件路径, 设置后不再读取明文私钥
+    pub keystore_path: String,
+    /// 保存 keystore 口令的环境变量名, 为空时使用 SIGNER_KEYSTORE_PASSWORD
+    pub keystore_password_env: String,
+    /// 远程签名服务地址 (web3signer 风格), 设置后优先于本地私钥与 keystore
+    pub remote_signer_url: String,
+    /// 远程签名服务中的签名密钥标识 (地址或公钥)
+    pub remote_signer_key: String,
+    /// 交易回执视为最终所需的确认区块数 (含所在区块), 0 或 1 表示首个回执即为最终
+    pub confirmations: u64,
+    /// 使用内存中的模拟合约, 不连接节点 (测试与演示)
+    pub fake: bool,
+}
+
 /// 数据库配置
 #[derive(Debug, Deserialize)]
 pub struct Tdengine {
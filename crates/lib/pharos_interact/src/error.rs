@@ -0,0 +1,143 @@
+//! Typed errors of contract interaction.
+//!
+//! Reverts are decoded from the node's revert data: `Error(string)` gives the `require`
+//! message, `Panic(uint256)` the Solidity panic code. The invoice ABI declares no custom
+//! errors, so any other selector is kept as raw hex.
+
+use std::fmt;
+
+use ethers::abi::{self, ParamType, Token};
+use ethers::prelude::*;
+use thiserror::Error;
+
+pub type ContractResult<T> = std::result::Result<T, ContractError>;
+
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0]; // Error(string)
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71]; // Panic(uint256)
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RevertReason {
+    /// `require(cond, "message")` / `revert("message")`
+    Message(String),
+    /// Solidity panic code, e.g. 0x11 for arithmetic overflow
+    Panic(u64),
+    /// Custom error or undecodable data, 0x-prefixed
+    Custom(String),
+    /// The node reported a revert without data
+    Unknown,
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Message(message) => write!(f, "{}", message),
+            RevertReason::Panic(code) => write!(f, "panic 0x{:02x}", code),
+            RevertReason::Custom(data) => write!(f, "custom error {}", data),
+            RevertReason::Unknown => write!(f, "no reason given"),
+        }
+    }
+}
+
+/// Decode ABI-encoded revert data.
+pub fn decode_revert_data(data: &[u8]) -> RevertReason {
+    if data.is_empty() {
+        return RevertReason::Unknown;
+    }
+    if data.len() >= 4 && data[..4] == ERROR_STRING_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[ParamType::String], &data[4..]) {
+            if let Some(Token::String(message)) = tokens.into_iter().next() {
+                return RevertReason::Message(message);
+            }
+        }
+    }
+    if data.len() >= 4 && data[..4] == PANIC_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[ParamType::Uint(256)], &data[4..]) {
+            if let Some(Token::Uint(code)) = tokens.into_iter().next() {
+                return RevertReason::Panic(code.low_u64());
+            }
+        }
+    }
+    RevertReason::Custom(format!("0x{}", hex::encode(data)))
+}
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("RPC request failed: {0}")]
+    Rpc(String),
+
+    #[error("Call would revert: {0}")]
+    Revert(RevertReason),
+
+    #[error("Transaction {0} was dropped from the mempool")]
+    Dropped(String),
+
+    #[error("Transaction {tx_hash} reverted on chain")]
+    Reverted { tx_hash: String, block_number: Option<u64> },
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Signing failed: {0}")]
+    Signer(String),
+}
+
+impl ContractError {
+    /// Classify an error returned by a middleware / provider call.
+    pub fn from_middleware<E: MiddlewareError>(e: &E) -> Self {
+        if let Some(response) = e.as_error_response() {
+            if let Some(data) = response.as_revert_data() {
+                return ContractError::Revert(decode_revert_data(&data));
+            }
+            // Some nodes only put the reason in the message: "execution reverted: <reason>"
+            if response.is_revert() {
+                return match response.message.split_once("reverted: ") {
+                    Some((_, reason)) => ContractError::Revert(RevertReason::Message(reason.to_string())),
+                    None => ContractError::Revert(RevertReason::Unknown),
+                };
+            }
+        }
+        ContractError::Rpc(e.to_string())
+    }
+
+    /// Classify an error returned by an abigen contract call.
+    pub fn from_call<M: Middleware>(e: &ethers::contract::ContractError<M>) -> Self {
+        match e {
+            ethers::contract::ContractError::Revert(data) => ContractError::Revert(decode_revert_data(data)),
+            ethers::contract::ContractError::MiddlewareError { e } => ContractError::from_middleware(e),
+            ethers::contract::ContractError::ProviderError { e } => ContractError::from_middleware(e),
+            other => ContractError::InvalidInput(other.to_string()),
+        }
+    }
+
+    /// Receipt status 0 as an error
+    pub fn reverted(receipt: &TransactionReceipt) -> Self {
+        ContractError::Reverted {
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            block_number: receipt.block_number.map(|block| block.as_u64()),
+        }
+    }
+}
+
+/// Map a failed parse of a call argument to `InvalidInput`
+pub(crate) fn invalid<E: fmt::Display>(what: &'static str) -> impl FnOnce(E) -> ContractError {
+    move |e| ContractError::InvalidInput(format!("{}: {}", what, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_revert_reasons() {
+        let mut data = ERROR_STRING_SELECTOR.to_vec();
+        data.extend(abi::encode(&[Token::String("Invoice already exists".to_string())]));
+        assert_eq!(decode_revert_data(&data), RevertReason::Message("Invoice already exists".to_string()));
+
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend(abi::encode(&[Token::Uint(0x11.into())]));
+        assert_eq!(decode_revert_data(&data), RevertReason::Panic(0x11));
+
+        assert_eq!(decode_revert_data(&[0xde, 0xad, 0xbe, 0xef]), RevertReason::Custom("0xdeadbeef".to_string()));
+        assert_eq!(decode_revert_data(&[]), RevertReason::Unknown);
+    }
+}
@@ -0,0 +1,607 @@
+//! In-memory stand-in for the invoice contract.
+//!
+//! `FakeInvoiceContract` keeps invoices, token batches and share holdings in memory and
+//! enforces the same rules as the deployed contract (duplicate invoices, batch lifecycle,
+//! pause), reverting with a message like the real one. It implements `ContractQuerier`,
+//! `ContractWriter` and `RawTransactionSender`, so the API and its workers run without a
+//! Pharos RPC in tests and demos. A "signed" transaction is the JSON of its `ContractCall`;
+//! it is executed when broadcast and mined instantly.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use log::info;
+
+use common::domain::dto::contract_call_dto::ContractCall;
+use common::domain::dto::invoice_dto::InvoiceDataDto;
+use common::domain::dto::query_invoice_dto::QueryParamsDto;
+use common::domain::dto::token_batch_dto::TokenBatchDto;
+use common::domain::dto::token_transfer_dto::{SharePurchaseReceiptDto, TransferReceiptDto};
+
+use crate::error::{invalid, ContractError, ContractResult, RevertReason};
+use crate::raw_transaction::{RawTransactionSender, SignedCall};
+use crate::wallet_signature::{decode_is_valid_signature, ValidatorPayload, EIP1271_MAGIC};
+use crate::{ContractQuerier, ContractWriter, InvoiceData};
+
+#[derive(Debug, Clone)]
+struct FakeBatch {
+    batch: TokenBatchDto,
+    sold: U256, // Shares purchased so far
+}
+
+#[derive(Debug, Clone, Default)]
+struct FakeState {
+    invoices: Vec<InvoiceDataDto>, // In creation order, like the contract's array
+    batches: HashMap<String, FakeBatch>,
+    holdings: HashMap<Address, HashMap<String, U256>>, // user -> batch id -> shares
+    paused: bool,
+    next_nonce: u64,
+    block_number: u64,
+    pending: HashMap<String, (u64, ContractCall)>, // Signed but not broadcast, by tx hash
+    receipts: HashMap<H256, TransactionReceipt>,
+    transfers: HashMap<String, TransferReceiptDto>, // ERC20 transfers seeded by tests
+    purchases: HashMap<String, SharePurchaseReceiptDto>, // Wallet-sent purchaseShares seeded by tests
+    wallets: HashMap<Address, FakeWallet>,          // Contract wallets seeded by tests
+}
+
+// Contract wallet accepting signatures of its owner key, deployed or counterfactual
+#[derive(Debug, Clone)]
+struct FakeWallet {
+    owner: Address,
+    factory: Option<Address>, // Set while the wallet is not deployed yet
+}
+
+impl FakeWallet {
+    // `isValidSignature` answer, the magic value when the owner signed `hash`
+    fn is_valid_signature(&self, calldata: &[u8]) -> ContractResult<Bytes> {
+        let (hash, signature) = decode_is_valid_signature(calldata).ok_or_else(|| revert("Unknown selector"))?;
+        let signed = Signature::try_from(signature.as_slice())
+            .ok()
+            .and_then(|signature| signature.recover(RecoveryMessage::Hash(hash)).ok())
+            .is_some_and(|recovered| recovered == self.owner);
+        let mut word = [0u8; 32];
+        if signed {
+            word[..4].copy_from_slice(&EIP1271_MAGIC);
+        }
+        Ok(Bytes::from(word.to_vec()))
+    }
+}
+
+/// Chain id reported by the fake, the usual local development chain id
+pub const FAKE_CHAIN_ID: u64 = 31337;
+
+fn revert(message: &str) -> ContractError {
+    ContractError::Revert(RevertReason::Message(message.to_string()))
+}
+
+fn parse_u256(value: &str, what: &'static str) -> ContractResult<U256> {
+    U256::from_dec_str(value).map_err(invalid(what))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+impl FakeState {
+    // Apply `call` sent by `sender`, leaving the state untouched when it reverts
+    fn execute(&mut self, call: &ContractCall, sender: Address) -> ContractResult<()> {
+        let mut next = self.clone();
+        next.apply(call, sender)?;
+        *self = next;
+        Ok(())
+    }
+
+    fn apply(&mut self, call: &ContractCall, sender: Address) -> ContractResult<()> {
+        let pausable = !matches!(call, ContractCall::Pause | ContractCall::Unpause);
+        if pausable && self.paused {
+            return Err(revert("Pausable: paused"));
+        }
+        match call {
+            ContractCall::BatchCreateInvoices { invoices } => {
+                for invoice in invoices {
+                    // Same parsing as the encoder of the real contract call
+                    InvoiceData::try_from(invoice.clone()).map_err(|e| ContractError::InvalidInput(format!("{:#}", e)))?;
+                    if self.find_invoice(&invoice.invoice_number).is_some() {
+                        return Err(revert("Invoice already exists"));
+                    }
+                    self.invoices.push(InvoiceDataDto {
+                        token_batch: String::new(),
+                        is_cleared: false,
+                        is_valid: true,
+                        ..invoice.clone()
+                    });
+                }
+            }
+            ContractCall::CreateTokenBatch { batch_id, invoice_numbers, stable_token_address, min_term, max_term, interest_rate } => {
+                let stable_token = stable_token_address.parse::<Address>().map_err(invalid("Invalid stable token address"))?;
+                let min = parse_u256(min_term, "Invalid min term format")?;
+                let max = parse_u256(max_term, "Invalid max term format")?;
+                parse_u256(interest_rate, "Invalid interest rate format")?;
+                if self.batches.contains_key(batch_id) {
+                    return Err(revert("Batch already exists"));
+                }
+                if invoice_numbers.is_empty() {
+                    return Err(revert("No invoices"));
+                }
+                if min > max {
+                    return Err(revert("Invalid term"));
+                }
+
+                let mut total = U256::zero();
+                let mut parties: Option<(String, String)> = None;
+                for number in invoice_numbers {
+                    let invoice = self.find_invoice(number).ok_or_else(|| revert("Invoice not found"))?;
+                    if !invoice.is_valid {
+                        return Err(revert("Invoice is invalid"));
+                    }
+                    if !invoice.token_batch.is_empty() {
+                        return Err(revert("Invoice already in batch"));
+                    }
+                    let invoice_parties = (invoice.payee.to_lowercase(), invoice.payer.to_lowercase());
+                    if parties.get_or_insert_with(|| invoice_parties.clone()) != &invoice_parties {
+                        return Err(revert("Invoices must share payee and payer"));
+                    }
+                    total += parse_u256(&invoice.amount, "Invalid amount format")?;
+                }
+                let (payee, payer) = parties.unwrap_or_default();
+
+                for number in invoice_numbers {
+                    if let Some(invoice) = self.invoices.iter_mut().find(|invoice| &invoice.invoice_number == number) {
+                        invoice.token_batch = batch_id.clone();
+                    }
+                }
+                let batch = TokenBatchDto {
+                    batch_id: batch_id.clone(),
+                    payee,
+                    payer,
+                    stable_token: format!("{:?}", stable_token),
+                    min_term: min_term.clone(),
+                    max_term: max_term.clone(),
+                    interest_rate: interest_rate.clone(),
+                    total_amount: total.to_string(),
+                    issue_date: "0".to_string(),
+                    is_signed: false,
+                    is_issued: false,
+                    invoice_numbers: invoice_numbers.clone(),
+                };
+                self.batches.insert(batch_id.clone(), FakeBatch { batch, sold: U256::zero() });
+            }
+            ContractCall::ConfirmTokenBatchIssue { batch_id } => {
+                let entry = self.batches.get_mut(batch_id).ok_or_else(|| revert("Batch not found"))?;
+                if entry.batch.is_issued {
+                    return Err(revert("Batch already issued"));
+                }
+                entry.batch.is_signed = true;
+                entry.batch.is_issued = true;
+                entry.batch.issue_date = now().to_string();
+            }
+            ContractCall::PurchaseShares { batch_id, amount } => {
+                let amount = parse_u256(amount, "Invalid amount format")?;
+                let entry = self.batches.get_mut(batch_id).ok_or_else(|| revert("Batch not found"))?;
+                if !entry.batch.is_issued {
+                    return Err(revert("Batch not issued"));
+                }
+                if amount.is_zero() {
+                    return Err(revert("Amount must be greater than 0"));
+                }
+                let total = parse_u256(&entry.batch.total_amount, "Invalid amount format")?;
+                if entry.sold + amount > total {
+                    return Err(revert("Insufficient shares"));
+                }
+                entry.sold += amount;
+                *self.holdings.entry(sender).or_default().entry(batch_id.clone()).or_default() += amount;
+            }
+            ContractCall::InvalidateInvoice { invoice_number } => {
+                let invoice = self
+                    .invoices
+                    .iter_mut()
+                    .find(|invoice| &invoice.invoice_number == invoice_number)
+                    .ok_or_else(|| revert("Invoice not found"))?;
+                if !invoice.is_valid {
+                    return Err(revert("Invoice already invalid"));
+                }
+                invoice.is_valid = false;
+            }
+            ContractCall::Pause => {
+                if self.paused {
+                    return Err(revert("Pausable: paused"));
+                }
+                self.paused = true;
+            }
+            ContractCall::Unpause => {
+                if !self.paused {
+                    return Err(revert("Pausable: not paused"));
+                }
+                self.paused = false;
+            }
+        }
+        Ok(())
+    }
+
+    fn find_invoice(&self, invoice_number: &str) -> Option<&InvoiceDataDto> {
+        self.invoices.iter().find(|invoice| invoice.invoice_number == invoice_number)
+    }
+
+    // Mine a transaction into its own block
+    fn mine(&mut self, tx_hash: H256, nonce: u64, from: Address, to: Address, success: bool) -> TransactionReceipt {
+        self.block_number += 1;
+        let receipt = TransactionReceipt {
+            transaction_hash: tx_hash,
+            block_number: Some(self.block_number.into()),
+            block_hash: Some(H256::from(keccak256(self.block_number.to_be_bytes()))),
+            from,
+            to: Some(to),
+            gas_used: Some(21_000.into()),
+            cumulative_gas_used: 21_000.into(),
+            status: Some(u64::from(success).into()),
+            ..Default::default()
+        };
+        self.receipts.insert(tx_hash, receipt.clone());
+        receipt
+    }
+}
+
+/// In-memory invoice contract with the semantics of the deployed one.
+#[derive(Debug)]
+pub struct FakeInvoiceContract {
+    address: Address,
+    sender: Address, // Plays the platform signer, the `msg.sender` of every write
+    state: Mutex<FakeState>,
+}
+
+impl Default for FakeInvoiceContract {
+    fn default() -> Self {
+        Self::new(Address::from_low_u64_be(0xfa4e), Address::from_low_u64_be(0x5e4d))
+    }
+}
+
+impl FakeInvoiceContract {
+    pub fn new(address: Address, sender: Address) -> Self {
+        Self {
+            address,
+            sender,
+            state: Mutex::new(FakeState::default()),
+        }
+    }
+
+    /// Make `get_transfer_receipt` return `receipt`, e.g. a repayment sent from a wallet
+    pub fn insert_transfer_receipt(&self, receipt: TransferReceiptDto) {
+        self.lock().transfers.insert(receipt.transaction_hash.to_lowercase(), receipt);
+    }
+
+    /// Make `get_purchase_receipt` return `receipt`, e.g. shares bought from an investor wallet
+    pub fn insert_purchase_receipt(&self, receipt: SharePurchaseReceiptDto) {
+        self.lock().purchases.insert(receipt.transaction_hash.to_lowercase(), receipt);
+    }
+
+    /// Shares of a batch held by `user`
+    pub fn shares_of(&self, user: Address, batch_id: &str) -> U256 {
+        self.lock().holdings.get(&user).and_then(|batches| batches.get(batch_id)).copied().unwrap_or_default()
+    }
+
+    /// Drop the receipt of a mined transaction, as a reorg would; its state change stays
+    pub fn drop_receipt(&self, tx_hash: &str) -> bool {
+        match tx_hash.parse::<H256>() {
+            Ok(hash) => self.lock().receipts.remove(&hash).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// Seed a contract wallet accepting signatures of `owner`; with a factory it stays
+    /// counterfactual, only deployable within a deployless EIP-6492 validation call
+    pub fn add_contract_wallet(&self, wallet: Address, owner: Address, factory: Option<Address>) {
+        self.lock().wallets.insert(wallet, FakeWallet { owner, factory });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Sign, broadcast and mine in one step, like `send_call` of the real contract
+    fn send(&self, call: ContractCall) -> ContractResult<TransactionReceipt> {
+        let mut state = self.lock();
+        state.execute(&call, self.sender)?;
+        let nonce = state.next_nonce;
+        state.next_nonce += 1;
+        let tx_hash = tx_hash_of(nonce, &call);
+        info!("Fake {} mined as {:?}", call.method_name(), tx_hash);
+        Ok(state.mine(tx_hash, nonce, self.sender, self.address, true))
+    }
+}
+
+fn tx_hash_of(nonce: u64, call: &ContractCall) -> H256 {
+    let payload = format!("{}:{}", nonce, serde_json::to_string(call).unwrap_or_default());
+    H256::from(keccak256(payload.as_bytes()))
+}
+
+#[async_trait::async_trait]
+impl ContractQuerier for FakeInvoiceContract {
+    fn contract_address(&self) -> String {
+        format!("{:?}", self.address)
+    }
+
+    async fn query_invoices(&self, params: QueryParamsDto) -> ContractResult<Vec<InvoiceDataDto>> {
+        // An empty filter value matches everything, like a zero address in the contract
+        let filter = |value: &Option<String>| value.as_deref().filter(|v| !v.is_empty()).map(str::to_lowercase);
+        let (payee, payer, invoice_number) = (filter(&params.payee), filter(&params.payer), filter(&params.invoice_number));
+        let check_valid = params.is_valid.unwrap_or(false);
+
+        let state = self.lock();
+        Ok(state
+            .invoices
+            .iter()
+            .filter(|invoice| payee.as_ref().is_none_or(|payee| invoice.payee.to_lowercase() == *payee))
+            .filter(|invoice| payer.as_ref().is_none_or(|payer| invoice.payer.to_lowercase() == *payer))
+            .filter(|invoice| invoice_number.as_ref().is_none_or(|number| invoice.invoice_number.to_lowercase() == *number))
+            .filter(|invoice| !check_valid || invoice.is_valid)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_token_batch(&self, batch_id: String) -> ContractResult<TokenBatchDto> {
+        let state = self.lock();
+        state.batches.get(&batch_id).map(|entry| entry.batch.clone()).ok_or_else(|| revert("Batch not found"))
+    }
+
+    async fn get_user_batches(&self, user_address: String) -> ContractResult<Vec<String>> {
+        let user = user_address.parse::<Address>().map_err(invalid("Invalid user address"))?;
+        let state = self.lock();
+        let mut batch_ids: Vec<String> = state.holdings.get(&user).map(|batches| batches.keys().cloned().collect()).unwrap_or_default();
+        batch_ids.sort();
+        Ok(batch_ids)
+    }
+
+    async fn get_transfer_receipt(&self, tx_hash: String) -> ContractResult<Option<TransferReceiptDto>> {
+        let hash = tx_hash.parse::<H256>().map_err(invalid("Invalid transaction hash"))?;
+        let state = self.lock();
+        if let Some(receipt) = state.transfers.get(&tx_hash.to_lowercase()) {
+            return Ok(Some(receipt.clone()));
+        }
+        Ok(state.receipts.get(&hash).map(|receipt| TransferReceiptDto {
+            transaction_hash: format!("{:?}", receipt.transaction_hash),
+            success: receipt.status == Some(1.into()),
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            block_timestamp: now(),
+            transfers: Vec::new(),
+        }))
+    }
+
+    async fn get_purchase_receipt(&self, tx_hash: String) -> ContractResult<Option<SharePurchaseReceiptDto>> {
+        tx_hash.parse::<H256>().map_err(invalid("Invalid transaction hash"))?;
+        Ok(self.lock().purchases.get(&tx_hash.to_lowercase()).cloned())
+    }
+}
+
+#[async_trait::async_trait]
+impl ContractWriter for FakeInvoiceContract {
+    async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> ContractResult<TransactionReceipt> {
+        self.send(ContractCall::BatchCreateInvoices { invoices })
+    }
+
+    async fn create_token_batch(
+        &self,
+        batch_id: String,
+        invoice_numbers: Vec<String>,
+        stable_token_address: String,
+        min_term_str: String,
+        max_term_str: String,
+        interest_rate_str: String,
+    ) -> ContractResult<TransactionReceipt> {
+        self.send(ContractCall::CreateTokenBatch {
+            batch_id,
+            invoice_numbers,
+            stable_token_address,
+            min_term: min_term_str,
+            max_term: max_term_str,
+            interest_rate: interest_rate_str,
+        })
+    }
+
+    async fn confirm_token_batch_issue(&self, batch_id: String) -> ContractResult<TransactionReceipt> {
+        self.send(ContractCall::ConfirmTokenBatchIssue { batch_id })
+    }
+
+    async fn purchase_shares(&self, batch_id: String, amount_str: String) -> ContractResult<TransactionReceipt> {
+        self.send(ContractCall::PurchaseShares { batch_id, amount: amount_str })
+    }
+
+    async fn invalidate_invoice(&self, invoice_number: String) -> ContractResult<TransactionReceipt> {
+        self.send(ContractCall::InvalidateInvoice { invoice_number })
+    }
+
+    async fn pause(&self) -> ContractResult<TransactionReceipt> {
+        self.send(ContractCall::Pause)
+    }
+
+    async fn unpause(&self) -> ContractResult<TransactionReceipt> {
+        self.send(ContractCall::Unpause)
+    }
+}
+
+#[async_trait::async_trait]
+impl RawTransactionSender for FakeInvoiceContract {
+    fn sender_address(&self) -> String {
+        format!("{:?}", self.sender)
+    }
+
+    async fn simulate(&self, call: &ContractCall) -> ContractResult<()> {
+        self.lock().clone().apply(call, self.sender)
+    }
+
+    async fn sign_call(&self, call: &ContractCall) -> ContractResult<SignedCall> {
+        let mut state = self.lock();
+        let nonce = state.next_nonce;
+        state.next_nonce += 1;
+        let tx_hash = format!("{:?}", tx_hash_of(nonce, call));
+        state.pending.insert(tx_hash.clone(), (nonce, call.clone()));
+        let raw_tx = serde_json::to_string(call).map_err(|e| ContractError::Signer(e.to_string()))?;
+        Ok(SignedCall {
+            tx_hash,
+            nonce,
+            raw_tx: format!("0x{}", hex::encode(raw_tx)),
+        })
+    }
+
+    async fn broadcast(&self, signed: &SignedCall) -> ContractResult<()> {
+        let hash = signed.tx_hash.parse::<H256>().map_err(invalid("Invalid transaction hash"))?;
+        let mut state = self.lock();
+        if state.receipts.contains_key(&hash) {
+            return Ok(()); // Already known
+        }
+        let call = match state.pending.remove(&signed.tx_hash) {
+            Some((_, call)) => call,
+            None => {
+                let raw = hex::decode(signed.raw_tx.trim_start_matches("0x")).map_err(invalid("Invalid raw transaction"))?;
+                serde_json::from_slice(&raw).map_err(invalid("Invalid raw transaction"))?
+            }
+        };
+        // The nonce is consumed either way; a revert on chain still mines a receipt with status 0
+        let success = state.execute(&call, self.sender).is_ok();
+        state.mine(hash, signed.nonce, self.sender, self.address, success);
+        Ok(())
+    }
+
+    async fn get_receipt(&self, tx_hash: &str) -> ContractResult<Option<TransactionReceipt>> {
+        let hash = tx_hash.parse::<H256>().map_err(invalid("Invalid transaction hash"))?;
+        Ok(self.lock().receipts.get(&hash).cloned())
+    }
+
+    async fn replace(&self, signed: &SignedCall) -> ContractResult<SignedCall> {
+        let mut state = self.lock();
+        let (nonce, call) = state
+            .pending
+            .remove(&signed.tx_hash)
+            .ok_or_else(|| ContractError::InvalidInput(format!("Transaction {} is not pending", signed.tx_hash)))?;
+        // Same nonce, new hash, as with bumped fees
+        let tx_hash = format!("{:?}", H256::from(keccak256(signed.tx_hash.as_bytes())));
+        state.pending.insert(tx_hash.clone(), (nonce, call));
+        Ok(SignedCall { tx_hash, ..signed.clone() })
+    }
+
+    async fn block_number(&self) -> ContractResult<u64> {
+        Ok(self.lock().block_number)
+    }
+
+    async fn chain_id(&self) -> ContractResult<u64> {
+        Ok(FAKE_CHAIN_ID)
+    }
+
+    async fn sender_balance(&self) -> ContractResult<U256> {
+        // Gas is free here, so the signer never runs dry
+        Ok(U256::exp10(21))
+    }
+
+    async fn code_at(&self, address: Address) -> ContractResult<Bytes> {
+        let state = self.lock();
+        let deployed = address == self.address || state.wallets.get(&address).is_some_and(|wallet| wallet.factory.is_none());
+        Ok(if deployed { Bytes::from(vec![0xfe]) } else { Bytes::default() })
+    }
+
+    async fn call_raw(&self, to: Option<Address>, data: Bytes) -> ContractResult<Bytes> {
+        let state = self.lock();
+        match to {
+            Some(to) => match state.wallets.get(&to) {
+                Some(wallet) if wallet.factory.is_none() => wallet.is_valid_signature(&data),
+                _ => Ok(Bytes::default()),
+            },
+            None => {
+                // Only the EIP-6492 validator is run as init code; the factory call deploys the
+                // wallet for the duration of the call
+                let payload = ValidatorPayload::decode(&data).ok_or_else(|| revert("Unknown init code"))?;
+                let zero = Bytes::from(vec![0u8; 32]);
+                match state.wallets.get(&payload.wallet) {
+                    Some(wallet) if wallet.factory.is_none() || wallet.factory == Some(payload.factory) => {
+                        Ok(wallet.is_valid_signature(&payload.validate_calldata).unwrap_or(zero))
+                    }
+                    _ => Ok(zero),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(number: &str, amount: &str) -> InvoiceDataDto {
+        InvoiceDataDto {
+            invoice_number: number.to_string(),
+            payee: "0x00000000000000000000000000000000000000aa".to_string(),
+            payer: "0x00000000000000000000000000000000000000bb".to_string(),
+            amount: amount.to_string(),
+            ipfs_hash: "Qm".to_string(),
+            contract_hash: "0x01".to_string(),
+            timestamp: "1700000000".to_string(),
+            due_date: "1710000000".to_string(),
+            token_batch: String::new(),
+            is_cleared: false,
+            is_valid: true,
+        }
+    }
+
+    fn batch_call(batch_id: &str, numbers: &[&str]) -> ContractCall {
+        ContractCall::CreateTokenBatch {
+            batch_id: batch_id.to_string(),
+            invoice_numbers: numbers.iter().map(|n| n.to_string()).collect(),
+            stable_token_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            min_term: "30".to_string(),
+            max_term: "90".to_string(),
+            interest_rate: "850".to_string(),
+        }
+    }
+
+    fn is_revert(result: ContractResult<TransactionReceipt>, message: &str) -> bool {
+        matches!(result, Err(ContractError::Revert(RevertReason::Message(m))) if m == message)
+    }
+
+    #[tokio::test]
+    async fn fake_contract_follows_batch_lifecycle() {
+        let contract = FakeInvoiceContract::default();
+        contract.batch_create_invoices(vec![invoice("INV-1", "600"), invoice("INV-2", "400")]).await.unwrap();
+        assert!(is_revert(contract.batch_create_invoices(vec![invoice("INV-1", "1")]).await, "Invoice already exists"));
+
+        contract.send(batch_call("B1", &["INV-1", "INV-2"])).unwrap();
+        let batch = contract.get_token_batch("B1".to_string()).await.unwrap();
+        assert_eq!(batch.total_amount, "1000");
+        assert!(is_revert(contract.purchase_shares("B1".to_string(), "100".to_string()).await, "Batch not issued"));
+
+        contract.confirm_token_batch_issue("B1".to_string()).await.unwrap();
+        contract.purchase_shares("B1".to_string(), "700".to_string()).await.unwrap();
+        assert!(is_revert(contract.purchase_shares("B1".to_string(), "400".to_string()).await, "Insufficient shares"));
+        assert_eq!(contract.shares_of(contract.sender, "B1"), 700.into());
+        assert_eq!(contract.get_user_batches(contract.sender_address()).await.unwrap(), vec!["B1".to_string()]);
+
+        contract.invalidate_invoice("INV-1".to_string()).await.unwrap();
+        let valid = contract.query_invoices(QueryParamsDto { is_valid: Some(true), ..Default::default() }).await.unwrap();
+        assert_eq!(valid.len(), 1);
+
+        contract.pause().await.unwrap();
+        assert!(is_revert(contract.invalidate_invoice("INV-2".to_string()).await, "Pausable: paused"));
+    }
+
+    #[tokio::test]
+    async fn fake_raw_transactions_mine_on_broadcast() {
+        let contract = FakeInvoiceContract::default();
+        let call = ContractCall::BatchCreateInvoices { invoices: vec![invoice("INV-1", "1")] };
+        contract.simulate(&call).await.unwrap();
+
+        let signed = contract.sign_call(&call).await.unwrap();
+        assert!(contract.get_receipt(&signed.tx_hash).await.unwrap().is_none());
+        let replacement = contract.replace(&signed).await.unwrap();
+        assert_eq!(replacement.nonce, signed.nonce);
+
+        contract.broadcast(&replacement).await.unwrap();
+        let receipt = contract.get_receipt(&replacement.tx_hash).await.unwrap().unwrap();
+        assert_eq!(receipt.status, Some(1.into()));
+        assert!(matches!(contract.simulate(&call).await, Err(ContractError::Revert(_))));
+
+        // A call that reverts on chain still mines, with status 0
+        let signed = contract.sign_call(&call).await.unwrap();
+        contract.broadcast(&signed).await.unwrap();
+        assert_eq!(contract.get_receipt(&signed.tx_hash).await.unwrap().unwrap().status, Some(0.into()));
+    }
+}
@@ -0,0 +1,179 @@
+//! Gas limit and fee policy shared by every contract write.
+//!
+//! The limit is the node's estimate plus `buffer_percent`. Fees follow `mode`: a legacy gas
+//! price, or EIP-1559 max/priority fees with an optional fixed priority fee. `max_fee_per_gas`
+//! caps whatever the node suggests in either mode.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::parse_units;
+use log::debug;
+
+use crate::error::{ContractError, ContractResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasMode {
+    Legacy,
+    Eip1559,
+}
+
+impl FromStr for GasMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "legacy" => Ok(GasMode::Legacy),
+            "eip1559" | "eip-1559" => Ok(GasMode::Eip1559),
+            other => Err(anyhow!("Unknown gas mode '{}', expected legacy or eip1559", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GasPolicy {
+    pub mode: GasMode,
+    pub buffer_percent: u64,                   // Added on top of the gas estimate
+    pub max_fee_per_gas: Option<U256>,         // Cap in wei (gas price in legacy mode)
+    pub max_priority_fee_per_gas: Option<U256>, // Fixed tip in wei, node estimate if unset
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        Self {
+            mode: GasMode::Eip1559,
+            buffer_percent: 25,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+        }
+    }
+}
+
+fn gwei_to_wei(gwei: f64) -> Result<U256> {
+    let wei = parse_units(gwei.to_string(), "gwei").map_err(|e| anyhow!("Invalid gwei amount {}: {}", gwei, e))?;
+    Ok(wei.into())
+}
+
+impl GasPolicy {
+    /// Build a policy from the config values, fees given in gwei.
+    pub fn new(mode: &str, buffer_percent: u64, max_fee_gwei: Option<f64>, priority_fee_gwei: Option<f64>) -> Result<Self> {
+        Ok(Self {
+            mode: mode.parse()?,
+            buffer_percent,
+            max_fee_per_gas: max_fee_gwei.map(gwei_to_wei).transpose()?,
+            max_priority_fee_per_gas: priority_fee_gwei.map(gwei_to_wei).transpose()?,
+        })
+    }
+
+    pub fn buffered_gas_limit(&self, estimate: U256) -> U256 {
+        estimate * (100 + self.buffer_percent) / 100
+    }
+
+    /// Legacy gas price: the node price, capped
+    pub fn legacy_gas_price(&self, node_gas_price: U256) -> U256 {
+        self.cap(node_gas_price)
+    }
+
+    /// EIP-1559 `(max_fee_per_gas, max_priority_fee_per_gas)` from the node estimate
+    pub fn eip1559_fees(&self, estimated_max_fee: U256, estimated_priority_fee: U256) -> (U256, U256) {
+        let priority_fee = self.max_priority_fee_per_gas.unwrap_or(estimated_priority_fee);
+        // The node estimate is base fee headroom plus its own tip; swap in ours
+        let max_fee = self.cap(estimated_max_fee.saturating_sub(estimated_priority_fee) + priority_fee);
+        (max_fee, priority_fee.min(max_fee))
+    }
+
+    /// Convert `tx` to the configured type, then set its gas limit and fees.
+    pub async fn apply<M: Middleware>(&self, client: &M, tx: &mut TypedTransaction) -> ContractResult<()> {
+        if tx.from().is_none() {
+            if let Some(sender) = client.default_sender() {
+                tx.set_from(sender);
+            }
+        }
+        *tx = self.convert(tx);
+
+        let estimate = client
+            .estimate_gas(tx, None)
+            .await
+            .map_err(|e| ContractError::from_middleware(&e))?;
+        tx.set_gas(self.buffered_gas_limit(estimate));
+
+        match tx {
+            TypedTransaction::Eip1559(inner) => {
+                let (estimated_max_fee, estimated_priority_fee) = client
+                    .estimate_eip1559_fees(None)
+                    .await
+                    .map_err(|e| ContractError::from_middleware(&e))?;
+                let (max_fee, priority_fee) = self.eip1559_fees(estimated_max_fee, estimated_priority_fee);
+                inner.max_fee_per_gas = Some(max_fee);
+                inner.max_priority_fee_per_gas = Some(priority_fee);
+            }
+            _ => {
+                let gas_price = client.get_gas_price().await.map_err(|e| ContractError::from_middleware(&e))?;
+                tx.set_gas_price(self.legacy_gas_price(gas_price));
+            }
+        }
+        debug!("Gas for transaction: estimate {}, limit {:?}, fees {:?}", estimate, tx.gas(), tx.gas_price());
+        Ok(())
+    }
+
+    fn convert(&self, tx: &TypedTransaction) -> TypedTransaction {
+        match (self.mode, tx) {
+            (GasMode::Legacy, TypedTransaction::Legacy(_)) | (GasMode::Eip1559, TypedTransaction::Eip1559(_)) => tx.clone(),
+            (GasMode::Legacy, _) => {
+                let mut legacy = TransactionRequest::new();
+                legacy.from = tx.from().copied();
+                legacy.to = tx.to().cloned();
+                legacy.value = tx.value().copied();
+                legacy.data = tx.data().cloned();
+                legacy.nonce = tx.nonce().copied();
+                legacy.chain_id = tx.chain_id();
+                TypedTransaction::Legacy(legacy)
+            }
+            (GasMode::Eip1559, _) => {
+                let mut eip1559 = Eip1559TransactionRequest::new();
+                eip1559.from = tx.from().copied();
+                eip1559.to = tx.to().cloned();
+                eip1559.value = tx.value().copied();
+                eip1559.data = tx.data().cloned();
+                eip1559.nonce = tx.nonce().copied();
+                eip1559.chain_id = tx.chain_id();
+                TypedTransaction::Eip1559(eip1559)
+            }
+        }
+    }
+
+    fn cap(&self, fee: U256) -> U256 {
+        match self.max_fee_per_gas {
+            Some(cap) => fee.min(cap),
+            None => fee,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gas_policy_buffers_and_caps() {
+        let policy = GasPolicy::new("eip1559", 25, Some(2.0), Some(0.5)).unwrap();
+        assert_eq!(policy.buffered_gas_limit(100_000.into()), 125_000.into());
+
+        // Estimate 1.6 gwei incl. 0.1 tip -> 1.5 headroom + 0.5 fixed tip = 2.0, at the cap
+        let (max_fee, priority_fee) = policy.eip1559_fees(1_600_000_000u64.into(), 100_000_000u64.into());
+        assert_eq!(max_fee, 2_000_000_000u64.into());
+        assert_eq!(priority_fee, 500_000_000u64.into());
+
+        // A spike is capped, and the tip never exceeds the max fee
+        let (max_fee, priority_fee) = policy.eip1559_fees(9_000_000_000u64.into(), 0.into());
+        assert_eq!(max_fee, 2_000_000_000u64.into());
+        assert!(priority_fee <= max_fee);
+
+        let legacy = GasPolicy::new("legacy", 0, Some(1.0), None).unwrap();
+        assert_eq!(legacy.mode, GasMode::Legacy);
+        assert_eq!(legacy.legacy_gas_price(3_000_000_000u64.into()), 1_000_000_000u64.into());
+        assert!(GasPolicy::new("fast", 0, None, None).is_err());
+    }
+}
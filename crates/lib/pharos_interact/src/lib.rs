@@ -4,6 +4,7 @@ use dotenv::dotenv;
 use ethers::prelude::*;
 use ethers::providers::{Http, Provider};
 use ethers::signers::{LocalWallet, Signer};
+use ethers::abi::AbiDecode;
 use ethers::types::{Address, U256};
 use std::convert::TryFrom;
 use std::env;
@@ -13,6 +14,31 @@ use log::error;
 use salvo_oapi::ToSchema;
 use common::domain::dto::invoice_dto::InvoiceDataDto;
 use common::domain::dto::query_invoice_dto::QueryParamsDto;
+use common::domain::dto::token_batch_dto::TokenBatchDto;
+use common::domain::dto::token_transfer_dto::{SharePurchaseReceiptDto, TokenTransferDto, TransferReceiptDto};
+use error::invalid;
+
+pub mod error;
+pub mod fake;
+pub mod gas;
+pub mod managed;
+pub mod nonce_manager;
+pub mod raw_transaction;
+pub mod registry;
+pub mod signer;
+pub mod wallet_signature;
+pub use error::{ContractError, ContractResult, RevertReason};
+pub use fake::FakeInvoiceContract;
+pub use gas::{GasMode, GasPolicy};
+pub use managed::{ConnectFuture, Connector, ManagedContract};
+pub use nonce_manager::NonceManager;
+pub use raw_transaction::{is_final, RawTransactionSender, SignedCall};
+pub use registry::ContractRegistry;
+pub use signer::{decrypt_keystore, PlatformSigner, RemoteSigner, SignerError, SigningBackend};
+pub use wallet_signature::{is_eip6492, verify_signature};
+
+/// Contract connected through an HTTP provider and the platform signer
+pub type PlatformContract = InvoiceContract<SignerMiddleware<Provider<Http>, PlatformSigner>>;
 
 // Regenerate bindings using the updated ABI
 abigen!(
@@ -59,21 +85,71 @@ impl From<InvoiceData> for InvoiceDataDto {
     }
 }
 
+impl From<InvoiceTokenBatch> for TokenBatchDto {
+    fn from(val: InvoiceTokenBatch) -> Self {
+        TokenBatchDto {
+            batch_id: val.batch_id,
+            payee: format!("{:?}", val.payee),              // Convert Address to hex string
+            payer: format!("{:?}", val.payer),              // Convert Address to hex string
+            stable_token: format!("{:?}", val.stable_token), // Convert Address to hex string
+            min_term: val.min_term.to_string(),             // Convert U256 to string
+            max_term: val.max_term.to_string(),
+            interest_rate: val.interest_rate.to_string(),
+            total_amount: val.total_amount.to_string(),
+            issue_date: val.issue_date.to_string(),
+            is_signed: val.is_signed,
+            is_issued: val.is_issued,
+            invoice_numbers: val.invoice_numbers,
+        }
+    }
+}
+
+/// Decode an ERC20 `Transfer(address,address,uint256)` log, ignoring any other event.
+pub fn decode_transfer_log(log: &Log) -> Option<TokenTransferDto> {
+    let transfer_topic = H256::from(ethers::utils::keccak256("Transfer(address,address,uint256)"));
+    if log.topics.len() != 3 || log.topics[0] != transfer_topic || log.data.len() != 32 {
+        return None;
+    }
+
+    Some(TokenTransferDto {
+        token: format!("{:?}", log.address),
+        from: format!("{:?}", Address::from(log.topics[1])),
+        to: format!("{:?}", Address::from(log.topics[2])),
+        amount: U256::from_big_endian(&log.data).to_string(),
+    })
+}
+
 // --- Contract Interaction Traits ---
 // These traits define the capabilities of contract interaction
 
 /// Trait for contract query operations (read-only)
 #[async_trait::async_trait]
-pub trait ContractQuerier {
+pub trait ContractQuerier: Send + Sync {
+    /// Address of the deployed invoice contract (also the ledger of RBT shares)
+    fn contract_address(&self) -> String;
+
     /// Query invoices based on filter parameters
-    async fn query_invoices(&self, params: QueryParamsDto) -> Result<Vec<InvoiceDataDto>>;
+    async fn query_invoices(&self, params: QueryParamsDto) -> ContractResult<Vec<InvoiceDataDto>>;
+
+    /// Get a token batch by its on-chain batch id
+    async fn get_token_batch(&self, batch_id: String) -> ContractResult<TokenBatchDto>;
+
+    /// Get the ids of all token batches a user takes part in
+    async fn get_user_batches(&self, user_address: String) -> ContractResult<Vec<String>>;
+
+    /// Get a mined transaction with its ERC20 transfers; `None` while it is still pending
+    async fn get_transfer_receipt(&self, tx_hash: String) -> ContractResult<Option<TransferReceiptDto>>;
+
+    /// Get a mined `purchaseShares` call to this contract; `None` while it is still pending.
+    /// Fails with `InvalidInput` when the transaction is anything else.
+    async fn get_purchase_receipt(&self, tx_hash: String) -> ContractResult<Option<SharePurchaseReceiptDto>>;
 }
 
 /// Trait for contract write operations that modify blockchain state
 #[async_trait::async_trait]
 pub trait ContractWriter: ContractQuerier {
     /// Create multiple invoices in a batch
-    async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> Result<Option<TransactionReceipt>>;
+    async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> ContractResult<TransactionReceipt>;
 
     /// Create a token batch from invoices
     async fn create_token_batch(
@@ -84,36 +160,59 @@ pub trait ContractWriter: ContractQuerier {
         min_term_str: String,
         max_term_str: String,
         interest_rate_str: String,
-    ) -> Result<Option<TransactionReceipt>>;
+    ) -> ContractResult<TransactionReceipt>;
 
     /// Confirm a token batch issue
-    async fn confirm_token_batch_issue(&self, batch_id: String) -> Result<Option<TransactionReceipt>>;
+    async fn confirm_token_batch_issue(&self, batch_id: String) -> ContractResult<TransactionReceipt>;
+
+    /// Purchase shares from a token batch, paid by the signer. The API does not call this,
+    /// investors send `purchaseShares` from their own wallets (see `get_purchase_receipt`)
+    async fn purchase_shares(&self, batch_id: String, amount_str: String) -> ContractResult<TransactionReceipt>;
 
-    /// Purchase shares from a token batch
-    async fn purchase_shares(&self, batch_id: String, amount_str: String) -> Result<Option<TransactionReceipt>>;
+    /// Mark an invoice as invalid
+    async fn invalidate_invoice(&self, invoice_number: String) -> ContractResult<TransactionReceipt>;
+
+    /// Pause all state-changing contract functions
+    async fn pause(&self) -> ContractResult<TransactionReceipt>;
+
+    /// Resume a paused contract
+    async fn unpause(&self) -> ContractResult<TransactionReceipt>;
 }
 
+/// A contract usable by the API server: direct reads and writes plus the raw-transaction
+/// primitives of the outbox. Implemented by `InvoiceContract` and `FakeInvoiceContract`.
+pub trait ChainContract: ContractWriter + RawTransactionSender {}
+
+impl<T: ContractWriter + RawTransactionSender + ?Sized> ChainContract for T {}
+
 // --- Contract Interaction Logic ---
 
 pub struct InvoiceContract<M: Middleware> {
     contract: InvoiceContractABI<M>,
     client: Arc<M>, // Keep client if needed for direct calls, otherwise remove
+    nonces: Arc<NonceManager>, // Shared by every write from the client's signer
+    gas: GasPolicy,            // Gas limit and fees of every write
+    confirmations: u64,        // Blocks a receipt needs before it is final
 }
 
 // Implement ContractQuerier for InvoiceContract
 #[async_trait::async_trait]
 impl<M: Middleware + Send + Sync + 'static> ContractQuerier for InvoiceContract<M> {
-    async fn query_invoices(&self, params_dto: QueryParamsDto) -> Result<Vec<InvoiceDataDto>> {
+    fn contract_address(&self) -> String {
+        format!("{:?}", self.contract.address())
+    }
+
+    async fn query_invoices(&self, params_dto: QueryParamsDto) -> ContractResult<Vec<InvoiceDataDto>> {
         // Convert QueryParamsDto to internal QueryParams
         let params = QueryParams {
             batch_id: "".to_string(), // Consider adding batch_id to QueryParamsDto if needed for filtering
             payee: match params_dto.payee {
-                Some(addr_str) => addr_str.parse::<Address>().context("Invalid payee address in query")?,
+                Some(addr_str) => addr_str.parse::<Address>().map_err(invalid("Invalid payee address in query"))?,
                 None => Address::zero(),
             },
             invoice_number: params_dto.invoice_number.unwrap_or_else(|| "".to_string()),
             payer: match params_dto.payer {
-                Some(addr_str) => addr_str.parse::<Address>().context("Invalid payer address in query")?,
+                Some(addr_str) => addr_str.parse::<Address>().map_err(invalid("Invalid payer address in query"))?,
                 None => Address::zero(),
             },
             // Assuming ABI might have changed or the booleans are handled differently.
@@ -127,7 +226,7 @@ impl<M: Middleware + Send + Sync + 'static> ContractQuerier for InvoiceContract<
         // Call the contract
         let result: QueryResult = self.contract.query_invoices(params).call().await.map_err(|e| {
             error!("Error calling queryInvoices: {}", e);
-            anyhow!("Contract query failed: {}", e)
+            ContractError::from_call(&e)
         })?;
 
         // Convert internal Vec<InvoiceData> to Vec<InvoiceDataDto>
@@ -135,95 +234,101 @@ impl<M: Middleware + Send + Sync + 'static> ContractQuerier for InvoiceContract<
 
         Ok(result_dto)
     }
-}
 
-// Implement ContractWriter for InvoiceContract
-#[async_trait::async_trait]
-impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M> {
-    async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> Result<Option<TransactionReceipt>> {
-        let invoice_data_vec: Result<Vec<InvoiceData>, _> = invoices.into_iter().map(InvoiceData::try_from).collect();
+    async fn get_token_batch(&self, batch_id: String) -> ContractResult<TokenBatchDto> {
+        let batch: InvoiceTokenBatch = self.contract.get_token_batch(batch_id.clone()).call().await.map_err(|e| {
+            error!("Error calling getTokenBatch for batch '{}': {}", batch_id, e);
+            ContractError::from_call(&e)
+        })?;
 
-        let invoice_data_vec = invoice_data_vec.context("Failed to parse one or more invoice data DTOs")?;
+        Ok(TokenBatchDto::from(batch))
+    }
 
-        // Clone data needed for estimation *before* moving it to the final send call
-        let data_for_estimation = invoice_data_vec.clone();
-        let data_for_send = invoice_data_vec; // Original vec will be moved here
+    async fn get_user_batches(&self, user_address: String) -> ContractResult<Vec<String>> {
+        let user = user_address.parse::<Address>().map_err(invalid("Invalid user address"))?;
 
-        // 1. Estimate required gas
-        let gas_estimate = self.contract.batch_create_invoices(data_for_estimation)
-            .estimate_gas()
-            .await
-            .map_err(|e| {
-                error!("Error estimating gas for batchCreateInvoices: {}", e);
-                anyhow!("Failed to estimate gas (potential revert): {}", e)
-            })?;
+        self.contract.get_user_batches(user).call().await.map_err(|e| {
+            error!("Error calling getUserBatches for user '{}': {}", user_address, e);
+            ContractError::from_call(&e)
+        })
+    }
 
-        // 2. Log estimate for debugging
-        log::warn!("Estimated gas for batchCreateInvoices: {}", gas_estimate);
+    async fn get_transfer_receipt(&self, tx_hash: String) -> ContractResult<Option<TransferReceiptDto>> {
+        let hash = tx_hash.parse::<H256>().map_err(invalid("Invalid transaction hash"))?;
 
-        // 3. Set gas limit with a buffer
-        let gas_limit = gas_estimate * 1; // Add a 25% buffer to the estimate
+        let receipt = match self.client.get_transaction_receipt(hash).await.map_err(|e| {
+            error!("Error fetching receipt of transaction '{}': {}", tx_hash, e);
+            ContractError::from_middleware(&e)
+        })? {
+            Some(receipt) => receipt,
+            None => return Ok(None),
+        };
+        let block_number = match receipt.block_number {
+            Some(block_number) => block_number,
+            None => return Ok(None),
+        };
+        let block = self.client.get_block(block_number).await.map_err(|e| {
+            error!("Error fetching block {} of transaction '{}': {}", block_number, tx_hash, e);
+            ContractError::from_middleware(&e)
+        })?;
 
-        
+        Ok(Some(TransferReceiptDto {
+            transaction_hash: format!("{:?}", receipt.transaction_hash),
+            success: receipt.status == Some(1.into()),
+            block_number: block_number.as_u64(),
+            block_timestamp: block.map(|b| b.timestamp.as_u64()).unwrap_or_default(),
+            transfers: receipt.logs.iter().filter_map(decode_transfer_log).collect(),
+        }))
+    }
 
-        // 4. Prepare the contract call object and hold it in a variable
-        // This ensures the ContractCall object lives long enough.
-        let call = self
-            .contract
-            .batch_create_invoices(data_for_send) // Move original data here
-            .gas(gas_limit); // Set the calculated gas limit
+    async fn get_purchase_receipt(&self, tx_hash: String) -> ContractResult<Option<SharePurchaseReceiptDto>> {
+        let hash = tx_hash.parse::<H256>().map_err(invalid("Invalid transaction hash"))?;
 
-        // 5. Send the transaction using the prepared call object
-        let pending_tx_result = call.send().await; // Call send().await separately
+        let tx = match self.client.get_transaction(hash).await.map_err(|e| {
+            error!("Error fetching transaction '{}': {}", tx_hash, e);
+            ContractError::from_middleware(&e)
+        })? {
+            Some(tx) => tx,
+            None => return Ok(None),
+        };
+        if tx.to != Some(self.contract.address()) {
+            return Err(ContractError::InvalidInput(format!("Transaction {} was not sent to the invoice contract", tx_hash)));
+        }
+        let call = PurchaseSharesCall::decode(&tx.input)
+            .map_err(|_| ContractError::InvalidInput(format!("Transaction {} is not a purchaseShares call", tx_hash)))?;
 
-        // 6. Handle potential errors during the send phase
-        let pending_tx = pending_tx_result.map_err(|e| {
-            error!("Error sending batchCreateInvoices transaction: {}", e);
-            anyhow!("Failed to send transaction: {}", e)
-        })?;
+        let Some(receipt) = self.get_transfer_receipt(tx_hash).await? else {
+            return Ok(None);
+        };
+        Ok(Some(SharePurchaseReceiptDto {
+            transaction_hash: receipt.transaction_hash,
+            success: receipt.success,
+            block_number: receipt.block_number,
+            block_timestamp: receipt.block_timestamp,
+            buyer: format!("{:?}", tx.from),
+            batch_id: call.batch_id,
+            amount: call.amount.to_string(),
+        }))
+    }
+}
 
-    
-        // 5. Wait for the transaction receipt
-        // The '.await' on the PendingTransaction resolves to Result<Option<TransactionReceipt>, ProviderError>
-        let receipt_result = pending_tx.await;
-
-        // Handle the result of waiting for the receipt
-        match receipt_result {
-            Ok(Some(receipt)) => {
-                // Transaction confirmed successfully
-                // Check receipt status
-                if receipt.status == Some(1.into()) {
-                    log::info!(
-                        "batchCreateInvoices transaction successful! Hash: {:?}, Block: {:?}",
-                        receipt.transaction_hash,
-                        receipt.block_number.unwrap_or_default()
-                    );
-                    Ok(Some(receipt)) // Return the successful receipt
-                } else {
-                    // Transaction confirmed but reverted (status 0)
-                    error!(
-                        "batchCreateInvoices transaction confirmed but reverted (status 0). Hash: {:?}, Block: {:?}. Check contract logic and parameters.",
-                        receipt.transaction_hash,
-                        receipt.block_number.unwrap_or_default()
-                    );
-                    // Return an error indicating the revert, including the receipt for context
-                    Err(anyhow!(
-                        "Transaction reverted (status 0). Receipt: {:?}", // Consider a more structured error
-                        receipt
-                    ))
-                }
-            }
-            Ok(None) => {
-                // Transaction was dropped from the mempool (should be rare with sufficient gas)
-                error!("batchCreateInvoices transaction was dropped from mempool and not confirmed.");
-                Err(anyhow!("Transaction dropped from mempool"))
-            }
-            Err(e) => {
-                // Error while waiting for confirmation (e.g., RPC provider issue)
-                error!("Error waiting for batchCreateInvoices transaction receipt: {}", e);
-                Err(anyhow!("Failed to get transaction receipt: {}", e)) // Wrap ProviderError into anyhow::Error
-            }
-        }
+// Implement ContractWriter for InvoiceContract
+#[async_trait::async_trait]
+impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M> {
+    async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> ContractResult<TransactionReceipt> {
+        let invoice_data_vec: Result<Vec<InvoiceData>, _> = invoices.into_iter().map(InvoiceData::try_from).collect();
+
+        let invoice_data_vec = invoice_data_vec.map_err(|e| ContractError::InvalidInput(format!("{:#}", e)))?;
+
+        // Gas limit (estimate plus buffer), fees and nonce are set by `send_call`
+        let call = self.contract.batch_create_invoices(invoice_data_vec);
+        let receipt = self.send_call(call, "batchCreateInvoices", "invoices").await?;
+        log::info!(
+            "batchCreateInvoices transaction successful! Hash: {:?}, Block: {:?}",
+            receipt.transaction_hash,
+            receipt.block_number.unwrap_or_default()
+        );
+        Ok(receipt)
     }
 
     async fn create_token_batch(
@@ -234,12 +339,12 @@ impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M
         min_term_str: String,
         max_term_str: String,
         interest_rate_str: String,
-    ) -> Result<Option<TransactionReceipt>> {
+    ) -> ContractResult<TransactionReceipt> {
         // Parse inputs
-        let stable_token = stable_token_address.parse::<Address>().context("Invalid stable token address")?;
-        let min_term = U256::from_dec_str(&min_term_str).context("Invalid min term format")?;
-        let max_term = U256::from_dec_str(&max_term_str).context("Invalid max term format")?;
-        let interest_rate = U256::from_dec_str(&interest_rate_str).context("Invalid interest rate format")?;
+        let stable_token = stable_token_address.parse::<Address>().map_err(invalid("Invalid stable token address"))?;
+        let min_term = U256::from_dec_str(&min_term_str).map_err(invalid("Invalid min term format"))?;
+        let max_term = U256::from_dec_str(&max_term_str).map_err(invalid("Invalid max term format"))?;
+        let interest_rate = U256::from_dec_str(&interest_rate_str).map_err(invalid("Invalid interest rate format"))?;
 
         let tx = self.contract.create_token_batch(
             batch_id.clone(), // Clone batch_id for potential logging
@@ -249,41 +354,35 @@ impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M
             max_term,
             interest_rate,
         );
-        let pending_tx = tx.send().await.map_err(|e| {
-            error!("Error sending createTokenBatch transaction for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to send createTokenBatch transaction: {}", e)
-        })?;
-        pending_tx.await.map_err(|e| {
-            error!("Error waiting for createTokenBatch transaction receipt for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to get createTokenBatch transaction receipt: {}", e)
-        })
+        self.send_call(tx, "createTokenBatch", &format!("batch '{}'", batch_id)).await
     }
 
-    async fn confirm_token_batch_issue(&self, batch_id: String) -> Result<Option<TransactionReceipt>> {
+    async fn confirm_token_batch_issue(&self, batch_id: String) -> ContractResult<TransactionReceipt> {
         let tx = self.contract.confirm_token_batch_issue(batch_id.clone());
-        let pending_tx = tx.send().await.map_err(|e| {
-            error!("Error sending confirmTokenBatchIssue transaction for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to send confirmTokenBatchIssue transaction: {}", e)
-        })?;
-        pending_tx.await.map_err(|e| {
-            error!("Error waiting for confirmTokenBatchIssue transaction receipt for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to get confirmTokenBatchIssue transaction receipt: {}", e)
-        })
+        self.send_call(tx, "confirmTokenBatchIssue", &format!("batch '{}'", batch_id)).await
     }
 
-    async fn purchase_shares(&self, batch_id: String, amount_str: String) -> Result<Option<TransactionReceipt>> {
+    async fn purchase_shares(&self, batch_id: String, amount_str: String) -> ContractResult<TransactionReceipt> {
         // Parse amount
-        let amount = U256::from_dec_str(&amount_str).context("Invalid amount format")?;
+        let amount = U256::from_dec_str(&amount_str).map_err(invalid("Invalid amount format"))?;
 
         let tx = self.contract.purchase_shares(batch_id.clone(), amount);
-        let pending_tx = tx.send().await.map_err(|e| {
-            error!("Error sending purchaseShares transaction for batch '{}' amount '{}': {}", batch_id, amount_str, e);
-            anyhow!("Failed to send purchaseShares transaction: {}", e)
-        })?;
-        pending_tx.await.map_err(|e| {
-            error!("Error waiting for purchaseShares transaction receipt for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to get purchaseShares transaction receipt: {}", e)
-        })
+        self.send_call(tx, "purchaseShares", &format!("batch '{}' amount '{}'", batch_id, amount_str)).await
+    }
+
+    async fn invalidate_invoice(&self, invoice_number: String) -> ContractResult<TransactionReceipt> {
+        let tx = self.contract.invalidate_invoice(invoice_number.clone());
+        self.send_call(tx, "invalidateInvoice", &format!("invoice '{}'", invoice_number)).await
+    }
+
+    async fn pause(&self) -> ContractResult<TransactionReceipt> {
+        let tx = self.contract.pause();
+        self.send_call(tx, "pause", "contract").await
+    }
+
+    async fn unpause(&self) -> ContractResult<TransactionReceipt> {
+        let tx = self.contract.unpause();
+        self.send_call(tx, "unpause", "contract").await
     }
 }
 
@@ -291,10 +390,70 @@ impl<M: Middleware + Send + Sync + 'static> InvoiceContract<M> {
     /// Creates a new instance of the InvoiceContract wrapper.
     pub fn new(address: Address, client: Arc<M>) -> Self {
         let contract = InvoiceContractABI::new(address, client.clone());
-        Self { contract, client }
+        let nonces = Arc::new(NonceManager::new(client.default_sender().unwrap_or_default()));
+        Self { contract, client, nonces, gas: GasPolicy::default(), confirmations: 1 }
+    }
+
+    /// Replace the default gas policy (25% buffer, EIP-1559, node fees).
+    pub fn with_gas_policy(mut self, gas: GasPolicy) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    pub fn gas_policy(&self) -> &GasPolicy {
+        &self.gas
     }
 
-    // No method implementations here - they're all in the trait impls
+    /// Treat receipts as final only once their block is `confirmations` deep (default 1).
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    /// Nonce allocator of the signing account
+    pub fn nonces(&self) -> &NonceManager {
+        &self.nonces
+    }
+
+    // Send a contract call with a locally allocated nonce and wait until its receipt is final
+    async fn send_call<D: ethers::abi::Detokenize + Send + Sync>(
+        &self,
+        call: FunctionCall<Arc<M>, M, D>,
+        method: &str,
+        target: &str,
+    ) -> ContractResult<TransactionReceipt> {
+        let mut call = call;
+        self.gas.apply(self.client.as_ref(), &mut call.tx).await.map_err(|e| {
+            error!("Error preparing gas for {} transaction for {}: {}", method, target, e);
+            e
+        })?;
+        let nonce = self.nonces.next(self.client.as_ref()).await?;
+        let call = call.nonce(nonce);
+        let pending_tx = match call.send().await {
+            Ok(pending_tx) => pending_tx,
+            Err(e) => {
+                error!("Error sending {} transaction for {} (nonce {}): {}", method, target, nonce, e);
+                self.nonces.handle_send_error(self.client.as_ref(), nonce, &e.to_string()).await;
+                return Err(ContractError::from_call(&e));
+            }
+        };
+        let tx_hash = format!("{:?}", *pending_tx);
+        let receipt = pending_tx.confirmations(self.confirmations as usize).await.map_err(|e| {
+            error!("Error waiting for {} transaction receipt for {}: {}", method, target, e);
+            ContractError::from_middleware(&e)
+        })?;
+        match receipt {
+            Some(receipt) if receipt.status == Some(1.into()) => Ok(receipt),
+            Some(receipt) => {
+                error!("{} transaction for {} reverted. Hash: {:?}, Block: {:?}", method, target, receipt.transaction_hash, receipt.block_number);
+                Err(ContractError::reverted(&receipt))
+            }
+            None => {
+                error!("{} transaction {} for {} was dropped from mempool", method, tx_hash, target);
+                Err(ContractError::Dropped(tx_hash))
+            }
+        }
+    }
 }
 
 // --- Initialization ---
@@ -311,16 +470,26 @@ impl<M: Middleware + Send + Sync + 'static> InvoiceContract<M> {
 /// # Errors
 /// Returns an error if environment variables are missing or invalid, or if connection
 /// to the blockchain fails.
-pub async fn initialize_contract_from_env() -> Result<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>> {
+pub async fn initialize_contract_from_env() -> Result<PlatformContract> {
     dotenv().ok(); // Load .env file
     let rpc_url = env::var("PHAROS_RPC_URL").context("Failed to read PHAROS_RPC_URL from environment")?;
     let contract_address_str = env::var("INVOICE_CONTRACT_ADDRESS").context("Failed to read INVOICE_CONTRACT_ADDRESS from environment")?;
     let private_key_str = env::var("SIGNER_PRIVATE_KEY").context("Failed to read SIGNER_PRIVATE_KEY from environment")?;
+    let wallet = private_key_str.parse::<LocalWallet>().context("Failed to parse private key")?;
 
+    initialize_contract(&rpc_url, &contract_address_str, Arc::new(wallet)).await
+}
+
+/// Connects to one contract deployment with an explicit RPC URL, contract address and signing backend.
+pub async fn initialize_contract(
+    rpc_url: &str,
+    contract_address_str: &str,
+    backend: Arc<dyn SigningBackend>,
+) -> Result<PlatformContract> {
     let provider = Provider::<Http>::try_from(rpc_url).context("Failed to create HTTP provider from RPC URL")?;
     let chain_id = provider.get_chainid().await.context("Failed to get chain ID from provider")?.as_u64();
-    let wallet = private_key_str.parse::<LocalWallet>().context("Failed to parse private key")?.with_chain_id(chain_id);
-    let client = Arc::new(SignerMiddleware::new(provider, wallet));
+    let signer = PlatformSigner::new(backend).with_chain_id(chain_id);
+    let client = Arc::new(SignerMiddleware::new(provider, signer));
     let contract_address = contract_address_str.parse::<Address>().context("Failed to parse contract address")?;
 
     Ok(InvoiceContract::new(contract_address, client))
@@ -397,7 +566,7 @@ mod tests {
             }
             Err(e) => {
                 println!("Failed to create invoices: {}", e);
-                return Err(e);
+                return Err(e.into());
             }
         }
 
@@ -435,7 +604,7 @@ mod tests {
             }
             Err(e) => {
                 println!("Failed to create token batch: {}", e);
-                return Err(e);
+                return Err(e.into());
             }
         }
 
@@ -460,7 +629,7 @@ mod tests {
             }
             Err(e) => {
                 println!("Failed to confirm token batch: {}", e);
-                return Err(e);
+                return Err(e.into());
             }
         }
 
@@ -487,13 +656,37 @@ mod tests {
             }
             Err(e) => {
                 println!("Failed to purchase shares: {}", e);
-                return Err(e);
+                return Err(e.into());
             }
         }
 
         Ok(())
     }
 
+    #[test]
+    fn test_decode_transfer_log() {
+        let token: Address = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".parse().unwrap();
+        let from: Address = "0x95459aed5538bfa47a194d3a0bbbe7a472b5dcd0".parse().unwrap();
+        let to: Address = "0x360a0e35b3e3b678069e3e84c20889a9399a3ff7".parse().unwrap();
+        let mut data = [0u8; 32];
+        U256::from(1_000_000u64).to_big_endian(&mut data);
+
+        let log = Log {
+            address: token,
+            topics: vec![H256::from(ethers::utils::keccak256("Transfer(address,address,uint256)")), H256::from(from), H256::from(to)],
+            data: Bytes::from(data.to_vec()),
+            ..Default::default()
+        };
+        let transfer = decode_transfer_log(&log).expect("transfer should decode");
+        assert_eq!(transfer.token, format!("{:?}", token));
+        assert_eq!(transfer.from, format!("{:?}", from));
+        assert_eq!(transfer.to, format!("{:?}", to));
+        assert_eq!(transfer.amount, "1000000");
+
+        let approval = Log { topics: vec![H256::zero(), H256::from(from), H256::from(to)], ..log };
+        assert!(decode_transfer_log(&approval).is_none());
+    }
+
     #[tokio::test]
     async fn test_query() -> Result<()> {
         // 注意：这个测试需要在有真实网络连接和环境配置的情况下运行
@@ -0,0 +1,146 @@
+//! Local nonce allocation for the platform signer.
+//!
+//! The account's pending transaction count is read from the node once, after that nonces are
+//! handed out from memory so concurrent writers never race for the same one. A nonce that was
+//! allocated but never reached the node (signing or sending failed) is released and handed out
+//! again before any new one, so later transactions are not left queued behind a gap. When the
+//! node rejects a nonce the counter is resynced from the chain.
+
+use std::collections::BTreeSet;
+
+use ethers::prelude::*;
+use log::{info, warn};
+use tokio::sync::Mutex;
+
+use crate::error::{ContractError, ContractResult};
+
+#[derive(Debug, Default)]
+struct NonceState {
+    next: Option<U256>,      // Next never-used nonce, `None` until loaded from the chain
+    released: BTreeSet<U256>, // Allocated but never broadcast, reused lowest first
+}
+
+impl NonceState {
+    fn take(&mut self, chain_next: impl FnOnce() -> U256) -> U256 {
+        if let Some(nonce) = self.released.pop_first() {
+            return nonce;
+        }
+        let nonce = *self.next.get_or_insert_with(chain_next);
+        self.next = Some(nonce + 1);
+        nonce
+    }
+
+    fn release(&mut self, nonce: U256) {
+        let Some(mut next) = self.next else { return };
+        if nonce >= next {
+            return;
+        }
+        self.released.insert(nonce);
+        // Shrink the counter instead of keeping a gap at its top
+        while next > U256::zero() && self.released.remove(&(next - 1)) {
+            next -= U256::one();
+        }
+        self.next = Some(next);
+    }
+
+    fn reset(&mut self, chain_next: U256) {
+        self.next = Some(chain_next);
+        self.released.clear();
+    }
+}
+
+/// Hands out nonces for one signing account.
+#[derive(Debug)]
+pub struct NonceManager {
+    address: Address,
+    state: Mutex<NonceState>,
+}
+
+/// Whether a node error means the nonce of the transaction was wrong for the account.
+pub fn is_nonce_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("nonce too low") || message.contains("nonce too high") || message.contains("invalid nonce")
+}
+
+impl NonceManager {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            state: Mutex::new(NonceState::default()),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Allocate the nonce for the next transaction.
+    pub async fn next<M: Middleware>(&self, client: &M) -> ContractResult<U256> {
+        let mut state = self.state.lock().await;
+        if state.next.is_none() {
+            let chain_next = self.chain_next(client).await?;
+            info!("Nonce manager for {:?} starting at {}", self.address, chain_next);
+            state.next = Some(chain_next);
+        }
+        Ok(state.take(|| U256::zero()))
+    }
+
+    /// Give back a nonce whose transaction never reached the node.
+    pub async fn release(&self, nonce: U256) {
+        self.state.lock().await.release(nonce);
+    }
+
+    /// Reload the counter from the node after it rejected a nonce.
+    pub async fn resync<M: Middleware>(&self, client: &M) -> ContractResult<U256> {
+        let chain_next = self.chain_next(client).await?;
+        let mut state = self.state.lock().await;
+        warn!("Nonce manager for {:?} resynced from {:?} to {}", self.address, state.next, chain_next);
+        state.reset(chain_next);
+        Ok(chain_next)
+    }
+
+    /// Release `nonce` after a failed send, or resync when the node rejected the nonce itself.
+    pub async fn handle_send_error<M: Middleware>(&self, client: &M, nonce: U256, message: &str) {
+        if is_nonce_error(message) {
+            if let Err(e) = self.resync(client).await {
+                warn!("Failed to resync nonce of {:?}: {}", self.address, e);
+            }
+        } else {
+            self.release(nonce).await;
+        }
+    }
+
+    async fn chain_next<M: Middleware>(&self, client: &M) -> ContractResult<U256> {
+        client
+            .get_transaction_count(self.address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| ContractError::from_middleware(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_nonces_are_reused_first() {
+        let mut state = NonceState::default();
+        assert_eq!(state.take(|| 7.into()), 7.into());
+        assert_eq!(state.take(|| 0.into()), 8.into());
+        assert_eq!(state.take(|| 0.into()), 9.into());
+
+        // A gap in the middle is filled before new nonces
+        state.release(8.into());
+        assert_eq!(state.take(|| 0.into()), 8.into());
+        assert_eq!(state.take(|| 0.into()), 10.into());
+
+        // Released nonces at the top shrink the counter
+        state.release(9.into());
+        state.release(10.into());
+        assert_eq!(state.next, Some(9.into()));
+        assert!(state.released.is_empty());
+
+        state.reset(20.into());
+        assert_eq!(state.take(|| 0.into()), 20.into());
+    }
+}
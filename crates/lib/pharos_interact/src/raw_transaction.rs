@@ -0,0 +1,296 @@
+//! Sign / broadcast / receipt primitives for contract writes.
+//!
+//! `ContractWriter` sends a transaction and waits for it in one step. These primitives split
+//! that up so a caller can persist the signed transaction (hash, nonce, raw bytes) *before*
+//! it is broadcast, and later re-broadcast or poll it after a restart. Nonces come from the
+//! contract's `NonceManager`, and a transaction stuck in the mempool can be `replace`d by one
+//! with the same nonce and higher fees. `simulate` runs a call against the current state so a
+//! revert is reported before anything is queued or signed. A receipt only counts as final once
+//! its block is `confirmations` deep; until then a reorg may still drop the transaction.
+
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::{keccak256, rlp};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+
+use common::domain::dto::contract_call_dto::ContractCall;
+
+use crate::error::{invalid, ContractError, ContractResult};
+use crate::nonce_manager::is_nonce_error;
+use crate::{InvoiceContract, InvoiceData};
+
+/// Minimum fee increase of a replacement transaction; nodes reject smaller bumps (geth: 10%)
+const REPLACEMENT_BUMP_PERCENT: u64 = 20;
+
+fn bumped(fee: U256) -> U256 {
+    fee * (100 + REPLACEMENT_BUMP_PERCENT) / 100
+}
+
+/// Raise the fees of `tx` for a replacement, never below the current network fees.
+fn bump_fees(tx: &mut TypedTransaction, current_max_fee: U256, current_priority_fee: U256) {
+    match tx {
+        TypedTransaction::Eip1559(inner) => {
+            let max_fee = bumped(inner.max_fee_per_gas.unwrap_or_default()).max(current_max_fee);
+            let priority_fee = bumped(inner.max_priority_fee_per_gas.unwrap_or_default()).max(current_priority_fee);
+            inner.max_fee_per_gas = Some(max_fee.max(priority_fee));
+            inner.max_priority_fee_per_gas = Some(priority_fee);
+        }
+        _ => {
+            let gas_price = bumped(tx.gas_price().unwrap_or_default()).max(current_max_fee);
+            tx.set_gas_price(gas_price);
+        }
+    }
+}
+
+/// A signed, not necessarily broadcast, contract transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedCall {
+    pub tx_hash: String,
+    pub nonce: u64,
+    pub raw_tx: String, // 0x-prefixed RLP of the signed transaction
+}
+
+#[async_trait::async_trait]
+pub trait RawTransactionSender: Send + Sync {
+    /// Address of the account signing the transactions
+    fn sender_address(&self) -> String;
+
+    /// Dry-run `call` from the signer against the latest state, surfacing a would-be revert
+    async fn simulate(&self, call: &ContractCall) -> ContractResult<()>;
+
+    /// Build, fill (nonce, gas, chain id) and sign the transaction for `call` without sending it
+    async fn sign_call(&self, call: &ContractCall) -> ContractResult<SignedCall>;
+
+    /// Broadcast a signed transaction; re-broadcasting one the node already knows is not an error
+    async fn broadcast(&self, signed: &SignedCall) -> ContractResult<()>;
+
+    /// Receipt of a mined transaction, `None` while it is pending or unknown
+    async fn get_receipt(&self, tx_hash: &str) -> ContractResult<Option<TransactionReceipt>>;
+
+    /// Re-sign a stuck transaction with the same nonce and higher fees, without sending it
+    async fn replace(&self, signed: &SignedCall) -> ContractResult<SignedCall>;
+
+    /// Number of the latest block
+    async fn block_number(&self) -> ContractResult<u64>;
+
+    /// Chain id reported by the node
+    async fn chain_id(&self) -> ContractResult<u64>;
+
+    /// Native balance of the signing account, in wei
+    async fn sender_balance(&self) -> ContractResult<U256>;
+
+    /// Deployed bytecode at `address`, empty for EOAs and not yet deployed wallets
+    async fn code_at(&self, address: Address) -> ContractResult<Bytes>;
+
+    /// Read-only `eth_call` of `data` sent to `to`, or run as init code when `to` is `None`
+    async fn call_raw(&self, to: Option<Address>, data: Bytes) -> ContractResult<Bytes>;
+
+    /// Blocks a receipt needs before it is final, counting the one it was mined in
+    fn confirmations(&self) -> u64 {
+        1
+    }
+}
+
+/// Whether a receipt mined in `receipt_block` is `confirmations` deep at `head`.
+pub fn is_final(receipt_block: u64, head: u64, confirmations: u64) -> bool {
+    head + 1 >= receipt_block + confirmations.max(1)
+}
+
+impl<M: Middleware + 'static, S: Signer + 'static> InvoiceContract<SignerMiddleware<M, S>> {
+    /// Unsigned transaction request for a contract call
+    pub fn build_transaction(&self, call: &ContractCall) -> ContractResult<TypedTransaction> {
+        let tx = match call {
+            ContractCall::BatchCreateInvoices { invoices } => {
+                let invoices: Vec<InvoiceData> = invoices
+                    .iter()
+                    .cloned()
+                    .map(InvoiceData::try_from)
+                    .collect::<anyhow::Result<_>>()
+                    .map_err(|e| ContractError::InvalidInput(format!("{:#}", e)))?;
+                self.contract.batch_create_invoices(invoices).tx
+            }
+            ContractCall::CreateTokenBatch { batch_id, invoice_numbers, stable_token_address, min_term, max_term, interest_rate } => {
+                self.contract
+                    .create_token_batch(
+                        batch_id.clone(),
+                        invoice_numbers.clone(),
+                        stable_token_address.parse::<Address>().map_err(invalid("Invalid stable token address"))?,
+                        U256::from_dec_str(min_term).map_err(invalid("Invalid min term format"))?,
+                        U256::from_dec_str(max_term).map_err(invalid("Invalid max term format"))?,
+                        U256::from_dec_str(interest_rate).map_err(invalid("Invalid interest rate format"))?,
+                    )
+                    .tx
+            }
+            ContractCall::ConfirmTokenBatchIssue { batch_id } => self.contract.confirm_token_batch_issue(batch_id.clone()).tx,
+            ContractCall::PurchaseShares { batch_id, amount } => {
+                self.contract
+                    .purchase_shares(batch_id.clone(), U256::from_dec_str(amount).map_err(invalid("Invalid amount format"))?)
+                    .tx
+            }
+            ContractCall::InvalidateInvoice { invoice_number } => self.contract.invalidate_invoice(invoice_number.clone()).tx,
+            ContractCall::Pause => self.contract.pause().tx,
+            ContractCall::Unpause => self.contract.unpause().tx,
+        };
+        Ok(tx)
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware + 'static, S: Signer + 'static> RawTransactionSender for InvoiceContract<SignerMiddleware<M, S>> {
+    fn sender_address(&self) -> String {
+        format!("{:?}", self.client.address())
+    }
+
+    async fn simulate(&self, call: &ContractCall) -> ContractResult<()> {
+        let mut tx = self.build_transaction(call)?;
+        tx.set_from(self.client.address());
+        self.client.call(&tx, None).await.map_err(|e| {
+            warn!("Simulation of {} failed: {}", call.method_name(), e);
+            ContractError::from_middleware(&e)
+        })?;
+        Ok(())
+    }
+
+    async fn sign_call(&self, call: &ContractCall) -> ContractResult<SignedCall> {
+        let mut tx = self.build_transaction(call)?;
+        self.gas.apply(self.client.as_ref(), &mut tx).await.map_err(|e| {
+            error!("Error preparing gas for {} transaction: {}", call.method_name(), e);
+            e
+        })?;
+        let nonce = self.nonces.next(self.client.as_ref()).await?;
+        tx.set_nonce(nonce);
+        if let Err(e) = self.client.fill_transaction(&mut tx, None).await {
+            error!("Error preparing {} transaction: {}", call.method_name(), e);
+            self.nonces.release(nonce).await;
+            return Err(ContractError::from_middleware(&e));
+        }
+
+        match self.sign(&tx).await {
+            Ok(signed) => Ok(signed),
+            Err(e) => {
+                error!("Error signing {} transaction: {}", call.method_name(), e);
+                self.nonces.release(nonce).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn broadcast(&self, signed: &SignedCall) -> ContractResult<()> {
+        let raw_tx: Bytes = signed.raw_tx.parse().map_err(invalid("Invalid raw transaction"))?;
+        match self.client.provider().send_raw_transaction(raw_tx).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().to_lowercase().contains("already known") => Ok(()),
+            Err(e) => {
+                error!("Error broadcasting transaction {}: {}", signed.tx_hash, e);
+                // Our local counter is out of step with the node; later allocations start from the chain again
+                if is_nonce_error(&e.to_string()) {
+                    if let Err(resync_error) = self.nonces.resync(self.client.as_ref()).await {
+                        warn!("Failed to resync nonce after broadcast error: {}", resync_error);
+                    }
+                }
+                Err(ContractError::from_middleware(&e))
+            }
+        }
+    }
+
+    async fn get_receipt(&self, tx_hash: &str) -> ContractResult<Option<TransactionReceipt>> {
+        let hash = tx_hash.parse::<H256>().map_err(invalid("Invalid transaction hash"))?;
+        self.client.get_transaction_receipt(hash).await.map_err(|e| {
+            error!("Error fetching receipt of transaction {}: {}", tx_hash, e);
+            ContractError::from_middleware(&e)
+        })
+    }
+
+    async fn replace(&self, signed: &SignedCall) -> ContractResult<SignedCall> {
+        let raw_tx: Bytes = signed.raw_tx.parse().map_err(invalid("Invalid raw transaction"))?;
+        let (mut tx, _) =
+            TypedTransaction::decode_signed(&rlp::Rlp::new(raw_tx.as_ref())).map_err(invalid("Failed to decode signed transaction"))?;
+
+        let (max_fee, priority_fee) = match tx {
+            TypedTransaction::Eip1559(_) => self.client.estimate_eip1559_fees(None).await,
+            _ => self.client.get_gas_price().await.map(|gas_price| (gas_price, U256::zero())),
+        }
+        .map_err(|e| ContractError::from_middleware(&e))?;
+        bump_fees(&mut tx, max_fee, priority_fee);
+
+        let replacement = self.sign(&tx).await?;
+        log::info!("Transaction {} (nonce {}) replaced by {}", signed.tx_hash, signed.nonce, replacement.tx_hash);
+        Ok(replacement)
+    }
+
+    async fn block_number(&self) -> ContractResult<u64> {
+        let block = self.client.get_block_number().await.map_err(|e| ContractError::from_middleware(&e))?;
+        Ok(block.as_u64())
+    }
+
+    async fn chain_id(&self) -> ContractResult<u64> {
+        let chain_id = self.client.get_chainid().await.map_err(|e| ContractError::from_middleware(&e))?;
+        Ok(chain_id.as_u64())
+    }
+
+    async fn sender_balance(&self) -> ContractResult<U256> {
+        self.client.get_balance(self.client.address(), None).await.map_err(|e| ContractError::from_middleware(&e))
+    }
+
+    async fn code_at(&self, address: Address) -> ContractResult<Bytes> {
+        self.client.get_code(address, None).await.map_err(|e| ContractError::from_middleware(&e))
+    }
+
+    async fn call_raw(&self, to: Option<Address>, data: Bytes) -> ContractResult<Bytes> {
+        let mut tx = TransactionRequest::new().data(data);
+        if let Some(to) = to {
+            tx = tx.to(to);
+        }
+        self.client.call(&tx.into(), None).await.map_err(|e| ContractError::from_middleware(&e))
+    }
+
+    fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+}
+
+impl<M: Middleware + 'static, S: Signer + 'static> InvoiceContract<SignerMiddleware<M, S>> {
+    // Sign a filled transaction without sending it
+    async fn sign(&self, tx: &TypedTransaction) -> ContractResult<SignedCall> {
+        let nonce = tx.nonce().copied().ok_or_else(|| ContractError::Signer("Nonce was not filled".to_string()))?;
+        let signature = self.client.signer().sign_transaction(tx).await.map_err(|e| ContractError::Signer(e.to_string()))?;
+        let raw_tx = tx.rlp_signed(&signature);
+
+        Ok(SignedCall {
+            tx_hash: format!("{:?}", H256::from(keccak256(&raw_tx))),
+            nonce: nonce.as_u64(),
+            raw_tx: format!("{}", raw_tx),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::transaction::eip2718::TypedTransaction;
+
+    #[test]
+    fn replacement_fees_are_bumped() {
+        let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest::new().max_fee_per_gas(100).max_priority_fee_per_gas(10));
+        bump_fees(&mut tx, 50.into(), 1.into());
+        let TypedTransaction::Eip1559(inner) = &tx else { unreachable!() };
+        assert_eq!(inner.max_fee_per_gas, Some(120.into()));
+        assert_eq!(inner.max_priority_fee_per_gas, Some(12.into()));
+
+        // The network price wins when it rose above the bump
+        let mut tx = TypedTransaction::Legacy(TransactionRequest::new().gas_price(100));
+        bump_fees(&mut tx, 200.into(), U256::zero());
+        assert_eq!(tx.gas_price(), Some(200.into()));
+    }
+
+    #[test]
+    fn receipt_is_final_at_confirmation_depth() {
+        assert!(is_final(100, 100, 1));
+        assert!(is_final(100, 100, 0));
+        assert!(!is_final(100, 100, 3));
+        assert!(!is_final(100, 101, 3));
+        assert!(is_final(100, 102, 3));
+    }
+}
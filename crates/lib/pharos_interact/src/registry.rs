@@ -0,0 +1,91 @@
+//! Contract deployments the process talks to, by name.
+//!
+//! Invoices, batches and chain jobs record the name of the deployment they live on; records
+//! without one (created before deployments were named) belong to the default deployment.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::{ChainContract, ManagedContract};
+
+#[derive(Clone)]
+pub struct ContractRegistry {
+    default: String,
+    contracts: BTreeMap<String, Arc<ManagedContract>>,
+}
+
+impl ContractRegistry {
+    pub fn new(default: &str) -> Self {
+        Self {
+            default: default.to_string(),
+            contracts: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, contract: Arc<ManagedContract>) {
+        self.contracts.insert(name.to_string(), contract);
+    }
+
+    /// Name of the default deployment
+    pub fn default_name(&self) -> &str {
+        &self.default
+    }
+
+    /// Deployment name a record lives on, the default when it has none
+    pub fn resolve_name<'a>(&'a self, deployment: Option<&'a str>) -> &'a str {
+        deployment.filter(|name| !name.is_empty()).unwrap_or(&self.default)
+    }
+
+    /// Contract of a deployment, the default one for `None`
+    pub fn get(&self, deployment: Option<&str>) -> Option<Arc<dyn ChainContract>> {
+        self.contracts.get(self.resolve_name(deployment)).map(|contract| contract.clone() as Arc<dyn ChainContract>)
+    }
+
+    /// Whether the deployment currently has a working RPC connection
+    pub fn is_connected(&self, deployment: Option<&str>) -> bool {
+        self.contracts.get(self.resolve_name(deployment)).is_some_and(|contract| contract.is_connected())
+    }
+
+    pub fn is_default(&self, deployment: &str) -> bool {
+        deployment == self.default
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.contracts.keys().cloned().collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Arc<ManagedContract>)> {
+        self.contracts.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContractQuerier, FakeInvoiceContract};
+    use ethers::types::Address;
+
+    fn fake(address: u64) -> Arc<ManagedContract> {
+        Arc::new(ManagedContract::fixed(Arc::new(FakeInvoiceContract::new(Address::from_low_u64_be(address), Address::zero()))))
+    }
+
+    #[test]
+    fn registry_resolves_default_deployment() {
+        let mut registry = ContractRegistry::new("testnet");
+        registry.insert("testnet", fake(1));
+        registry.insert("mainnet", fake(2));
+
+        assert_eq!(registry.resolve_name(None), "testnet");
+        assert_eq!(registry.resolve_name(Some("")), "testnet");
+        assert_eq!(registry.get(None).unwrap().contract_address(), format!("{:?}", Address::from_low_u64_be(1)));
+        assert_eq!(registry.get(Some("mainnet")).unwrap().contract_address(), format!("{:?}", Address::from_low_u64_be(2)));
+        assert!(registry.get(Some("devnet")).is_none());
+        assert!(registry.is_connected(None));
+        assert!(!registry.is_connected(Some("devnet")));
+        assert_eq!(registry.names(), vec!["mainnet".to_string(), "testnet".to_string()]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Signature verification for EOAs and smart contract wallets.
+//!
+//! An EOA signature is checked by ECDSA recovery. Contract wallets such as Safe multisigs have no
+//! key; they answer EIP-1271 `isValidSignature(hash, signature)` with the magic value
+//! `0x1626ba7e`. A counterfactual wallet that is not deployed yet wraps its signature per
+//! EIP-6492 as `abi.encode(factory, factoryCalldata, signature) ++ magicSuffix`; it is checked
+//! with a deployless `eth_call` of `VALIDATOR_INIT_CODE`, which runs the factory call and then
+//! `isValidSignature` inside the same call, so nothing is deployed for real.
+
+use ethers::abi::{self, ParamType, Token};
+use ethers::types::{Address, Bytes, RecoveryMessage, Signature, H256, U256};
+
+use crate::error::{ContractError, ContractResult};
+use crate::raw_transaction::RawTransactionSender;
+
+/// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`, also the value a valid check returns
+pub const EIP1271_MAGIC: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// Suffix marking an EIP-6492 wrapped signature
+pub const EIP6492_SUFFIX: [u8; 32] = [
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+];
+
+/// Constructor run by the deployless call. It expects four words after the code: factory,
+/// wallet, factory calldata length and `isValidSignature` calldata length, followed by both
+/// calldatas. It calls the factory (ignoring failure, the wallet may exist already), static-calls
+/// the wallet and returns the first word of its answer, or zero when that call failed.
+const VALIDATOR_INIT_CODE: [u8; 63] = [
+    0x61, 0x00, 0x3f, // PUSH2 63 (code size)
+    0x38, // CODESIZE
+    0x03, // SUB                          size of the appended data
+    0x61, 0x00, 0x3f, // PUSH2 63
+    0x60, 0x00, // PUSH1 0
+    0x39, // CODECOPY                     data -> memory[0..]
+    0x60, 0x00, // PUSH1 0                retSize
+    0x60, 0x00, // PUSH1 0                retOffset
+    0x60, 0x40, 0x51, // MLOAD(0x40)      argsSize: factory calldata length
+    0x60, 0x80, // PUSH1 0x80             argsOffset
+    0x60, 0x00, // PUSH1 0                value
+    0x60, 0x00, 0x51, // MLOAD(0x00)      factory
+    0x5a, // GAS
+    0xf1, // CALL
+    0x50, // POP
+    0x60, 0x00, 0x60, 0x00, 0x52, // MSTORE(0, 0)
+    0x60, 0x20, // PUSH1 0x20             retSize
+    0x60, 0x00, // PUSH1 0                retOffset
+    0x60, 0x60, 0x51, // MLOAD(0x60)      argsSize: isValidSignature calldata length
+    0x60, 0x40, 0x51, 0x60, 0x80, 0x01, // MLOAD(0x40) + 0x80   argsOffset
+    0x60, 0x20, 0x51, // MLOAD(0x20)      wallet
+    0x5a, // GAS
+    0xfa, // STATICCALL
+    0x60, 0x00, 0x51, // MLOAD(0)
+    0x02, // MUL                          zero unless the call succeeded
+    0x60, 0x00, 0x52, // MSTORE(0, result)
+    0x60, 0x20, 0x60, 0x00, 0xf3, // RETURN(0, 0x20)
+];
+
+/// Inputs of the deployless validator call
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ValidatorPayload {
+    pub factory: Address,
+    pub wallet: Address,
+    pub factory_calldata: Vec<u8>,
+    pub validate_calldata: Vec<u8>,
+}
+
+impl ValidatorPayload {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = VALIDATOR_INIT_CODE.to_vec();
+        data.extend_from_slice(&abi::encode(&[
+            Token::Address(self.factory),
+            Token::Address(self.wallet),
+            Token::Uint(U256::from(self.factory_calldata.len())),
+            Token::Uint(U256::from(self.validate_calldata.len())),
+        ]));
+        data.extend_from_slice(&self.factory_calldata);
+        data.extend_from_slice(&self.validate_calldata);
+        data
+    }
+
+    pub(crate) fn decode(data: &[u8]) -> Option<Self> {
+        let data = data.strip_prefix(VALIDATOR_INIT_CODE.as_slice())?;
+        let words = abi::decode(&[ParamType::Address, ParamType::Address, ParamType::Uint(256), ParamType::Uint(256)], data.get(..128)?).ok()?;
+        let (Token::Address(factory), Token::Address(wallet), Token::Uint(factory_len), Token::Uint(validate_len)) =
+            (&words[0], &words[1], &words[2], &words[3])
+        else {
+            return None;
+        };
+        let rest = &data[128..];
+        let split = factory_len.as_usize();
+        if rest.len() != split + validate_len.as_usize() {
+            return None;
+        }
+        Some(Self {
+            factory: *factory,
+            wallet: *wallet,
+            factory_calldata: rest[..split].to_vec(),
+            validate_calldata: rest[split..].to_vec(),
+        })
+    }
+}
+
+/// Calldata of `isValidSignature(hash, signature)`
+pub fn is_valid_signature_calldata(hash: H256, signature: &[u8]) -> Vec<u8> {
+    let mut data = EIP1271_MAGIC.to_vec();
+    data.extend_from_slice(&abi::encode(&[Token::FixedBytes(hash.as_bytes().to_vec()), Token::Bytes(signature.to_vec())]));
+    data
+}
+
+/// Split `isValidSignature` calldata into the hash and the signature
+pub fn decode_is_valid_signature(data: &[u8]) -> Option<(H256, Vec<u8>)> {
+    let args = data.strip_prefix(EIP1271_MAGIC.as_slice())?;
+    match abi::decode(&[ParamType::FixedBytes(32), ParamType::Bytes], args).ok()?.as_slice() {
+        [Token::FixedBytes(hash), Token::Bytes(signature)] => Some((H256::from_slice(hash), signature.clone())),
+        _ => None,
+    }
+}
+
+/// Wrap a signature of a not yet deployed wallet per EIP-6492
+pub fn wrap_eip6492(factory: Address, factory_calldata: &[u8], signature: &[u8]) -> Vec<u8> {
+    let mut data = abi::encode(&[Token::Address(factory), Token::Bytes(factory_calldata.to_vec()), Token::Bytes(signature.to_vec())]);
+    data.extend_from_slice(&EIP6492_SUFFIX);
+    data
+}
+
+// (factory, factory calldata, inner signature) of an EIP-6492 wrapped signature
+fn unwrap_eip6492(signature: &[u8]) -> ContractResult<Option<(Address, Vec<u8>, Vec<u8>)>> {
+    let Some(wrapped) = signature.strip_suffix(EIP6492_SUFFIX.as_slice()) else {
+        return Ok(None);
+    };
+    let invalid = || ContractError::InvalidInput("Malformed EIP-6492 signature".to_string());
+    match abi::decode(&[ParamType::Address, ParamType::Bytes, ParamType::Bytes], wrapped).map_err(|_| invalid())?.as_slice() {
+        [Token::Address(factory), Token::Bytes(calldata), Token::Bytes(inner)] => Ok(Some((*factory, calldata.clone(), inner.clone()))),
+        _ => Err(invalid()),
+    }
+}
+
+/// Whether `signature` is wrapped for a counterfactual wallet (EIP-6492)
+pub fn is_eip6492(signature: &[u8]) -> bool {
+    signature.ends_with(&EIP6492_SUFFIX)
+}
+
+/// Whether the word returned by `isValidSignature` is the EIP-1271 magic value
+pub fn is_magic_value(result: &[u8]) -> bool {
+    result.len() >= 4 && result[..4] == EIP1271_MAGIC
+}
+
+/// Whether `signature` is a valid signature of `hash` by `signer`, an EOA, a deployed contract
+/// wallet (EIP-1271) or a counterfactual one (EIP-6492).
+pub async fn verify_signature<S: RawTransactionSender + ?Sized>(chain: &S, signer: Address, hash: H256, signature: &[u8]) -> ContractResult<bool> {
+    if let Some((factory, factory_calldata, inner)) = unwrap_eip6492(signature)? {
+        if !chain.code_at(signer).await?.is_empty() {
+            return is_valid_eip1271(chain, signer, hash, &inner).await;
+        }
+        let payload = ValidatorPayload {
+            factory,
+            wallet: signer,
+            factory_calldata,
+            validate_calldata: is_valid_signature_calldata(hash, &inner),
+        };
+        let result = chain.call_raw(None, payload.encode().into()).await?;
+        return Ok(is_magic_value(&result));
+    }
+    if !chain.code_at(signer).await?.is_empty() {
+        return is_valid_eip1271(chain, signer, hash, signature).await;
+    }
+    let Ok(signature) = Signature::try_from(signature) else {
+        return Ok(false);
+    };
+    Ok(signature.recover(RecoveryMessage::Hash(hash)).is_ok_and(|recovered| recovered == signer))
+}
+
+async fn is_valid_eip1271<S: RawTransactionSender + ?Sized>(chain: &S, wallet: Address, hash: H256, signature: &[u8]) -> ContractResult<bool> {
+    match chain.call_raw(Some(wallet), is_valid_signature_calldata(hash, signature).into()).await {
+        Ok(result) => Ok(is_magic_value(&result)),
+        // Wallets revert on signatures they do not accept
+        Err(ContractError::Revert(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FakeInvoiceContract;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::utils::hash_message;
+
+    fn owner() -> LocalWallet {
+        "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn verifies_eoa_and_contract_wallet_signatures() {
+        let chain = FakeInvoiceContract::default();
+        let owner = owner();
+        let hash = hash_message("sign in");
+        let signature = owner.sign_message("sign in").await.unwrap().to_vec();
+
+        // EOA
+        assert!(verify_signature(&chain, owner.address(), hash, &signature).await.unwrap());
+        assert!(!verify_signature(&chain, Address::from_low_u64_be(9), hash, &signature).await.unwrap());
+
+        // Deployed contract wallet owned by the key (EIP-1271)
+        let safe = Address::from_low_u64_be(0x5afe);
+        chain.add_contract_wallet(safe, owner.address(), None);
+        assert!(verify_signature(&chain, safe, hash, &signature).await.unwrap());
+        assert!(!verify_signature(&chain, safe, hash_message("other"), &signature).await.unwrap());
+
+        // Counterfactual wallet (EIP-6492), checked through the deployless validator
+        let factory = Address::from_low_u64_be(0xfac7);
+        let counterfactual = Address::from_low_u64_be(0xcf);
+        chain.add_contract_wallet(counterfactual, owner.address(), Some(factory));
+        let wrapped = wrap_eip6492(factory, &[0xde, 0xad], &signature);
+        assert!(verify_signature(&chain, counterfactual, hash, &wrapped).await.unwrap());
+        assert!(!verify_signature(&chain, counterfactual, hash, &signature).await.unwrap());
+        // A wrapped signature of a wallet deployed since is checked with EIP-1271 directly
+        assert!(verify_signature(&chain, safe, hash, &wrap_eip6492(factory, &[], &signature)).await.unwrap());
+    }
+
+    #[test]
+    fn validator_payload_round_trips() {
+        let payload = ValidatorPayload {
+            factory: Address::from_low_u64_be(1),
+            wallet: Address::from_low_u64_be(2),
+            factory_calldata: vec![1, 2, 3],
+            validate_calldata: is_valid_signature_calldata(H256::repeat_byte(7), &[4, 5]),
+        };
+        assert_eq!(ValidatorPayload::decode(&payload.encode()), Some(payload.clone()));
+        assert_eq!(decode_is_valid_signature(&payload.validate_calldata), Some((H256::repeat_byte(7), vec![4, 5])));
+        assert_eq!(VALIDATOR_INIT_CODE.len(), 0x3f);
+    }
+}
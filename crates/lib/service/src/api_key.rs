@@ -0,0 +1,221 @@
+//! API keys for ERP integrations and HMAC request signing.
+//!
+//! ERP systems cannot sign with a wallet, so an enterprise issues API keys for them. A key is a
+//! public id plus a secret. The secret is never stored: it is derived from the server's master
+//! secret and a random per-key salt, and Mongo only keeps the salt and a hash of the secret, so
+//! a database dump alone cannot sign requests. Rotating draws a new salt, which retires the old
+//! secret at once.
+//!
+//! A signed request carries the key id, a Unix timestamp, a one-time nonce and
+//! `hex(HMAC-SHA256(secret, canonical_request(..)))`. Requests outside the allowed clock skew are
+//! rejected and a nonce is remembered in Redis for the whole window, so a captured request cannot
+//! be replayed.
+
+use std::sync::Arc;
+
+use ethers::utils::hex;
+use hmac::{Hmac, Mac};
+use mongodb::{bson::oid::ObjectId, Database};
+use redis::{AsyncCommands, Client, ExistenceCheck, SetExpiry, SetOptions};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use common::domain::entity::ApiKey;
+
+use crate::repository::ApiKeyRepository;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Error, Debug)]
+pub enum ApiKeyError {
+    #[error("API key not found")]
+    NotFound,
+
+    #[error("Unknown or revoked API key")]
+    UnknownKey,
+
+    #[error("Request timestamp outside the allowed window")]
+    StaleTimestamp,
+
+    #[error("Invalid nonce")]
+    InvalidNonce,
+
+    #[error("Nonce already used")]
+    Replayed,
+
+    #[error("Invalid request signature")]
+    InvalidSignature,
+
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+impl ApiKeyError {
+    /// Whether the error is the caller's fault (401) rather than ours (500)
+    pub fn is_rejection(&self) -> bool {
+        !matches!(self, ApiKeyError::Redis(_) | ApiKeyError::Database(_))
+    }
+}
+
+/// Parts of an incoming request covered by the signature
+#[derive(Debug, Clone)]
+pub struct SignedRequest<'a> {
+    pub key_id: &'a str,
+    pub timestamp: i64, // Unix seconds
+    pub nonce: &'a str,
+    pub signature: &'a str, // Hex HMAC-SHA256
+    pub method: &'a str,
+    pub path: &'a str, // Path and query as sent, e.g. `/rwa/erp/invoice/list?page=1`
+    pub body: &'a [u8],
+}
+
+/// String the client signs: method, path with query, timestamp, nonce and body hash, one per line
+pub fn canonical_request(method: &str, path: &str, timestamp: i64, nonce: &str, body: &[u8]) -> String {
+    format!("{}\n{}\n{}\n{}\n{}", method.to_uppercase(), path, timestamp, nonce, hex::encode(Sha256::digest(body)))
+}
+
+/// Hex HMAC-SHA256 of `message` keyed with `secret`
+pub fn sign(secret: &str, message: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Constant-time check of a hex signature
+fn verify(secret: &str, message: &str, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature.trim_start_matches("0x")) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn derive_secret(master_secret: &str, key_id: &str, salt: &str) -> String {
+    sign(master_secret, &format!("{}:{}", key_id, salt))
+}
+
+fn secret_hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn nonce_key(key_id: &str, nonce: &str) -> String {
+    format!("rwa:api_nonce:{}:{}", key_id, nonce)
+}
+
+fn random_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+pub struct ApiKeyService {
+    keys: ApiKeyRepository,
+    master_secret: String,
+}
+
+impl ApiKeyService {
+    pub fn new(db: &Database, master_secret: &str) -> Self {
+        Self {
+            keys: ApiKeyRepository::new(db),
+            master_secret: master_secret.to_string(),
+        }
+    }
+
+    /// Issue a key for an enterprise; returns it with its secret, shown to the caller only once
+    pub async fn create(&self, enterprise_id: ObjectId, name: &str, created_by: &str) -> Result<(ApiKey, String), ApiKeyError> {
+        let key_id = format!("rk_{}", random_token());
+        let salt = random_token();
+        let secret = derive_secret(&self.master_secret, &key_id, &salt);
+        let key = ApiKey::new(key_id, enterprise_id, name.to_string(), salt, secret_hash(&secret), created_by.to_string());
+        let key = self.keys.create(key).await?;
+        log::info!("API key {} created for enterprise {} by {}", key.key_id, enterprise_id, created_by);
+        Ok((key, secret))
+    }
+
+    /// Look up a key by its public id, revoked ones included
+    pub async fn find(&self, key_id: &str) -> Result<Option<ApiKey>, ApiKeyError> {
+        Ok(self.keys.find_by_key_id(key_id).await?)
+    }
+
+    /// Replace the secret of an active key of the enterprise; the old secret stops working
+    pub async fn rotate(&self, enterprise_id: ObjectId, key_id: &str) -> Result<(ApiKey, String), ApiKeyError> {
+        let salt = random_token();
+        let secret = derive_secret(&self.master_secret, key_id, &salt);
+        let key = self.keys.rotate(enterprise_id, key_id, &salt, &secret_hash(&secret)).await?.ok_or(ApiKeyError::NotFound)?;
+        log::info!("API key {} of enterprise {} rotated", key_id, enterprise_id);
+        Ok((key, secret))
+    }
+
+    pub async fn revoke(&self, enterprise_id: ObjectId, key_id: &str) -> Result<(), ApiKeyError> {
+        if !self.keys.revoke(enterprise_id, key_id).await? {
+            return Err(ApiKeyError::NotFound);
+        }
+        log::info!("API key {} of enterprise {} revoked", key_id, enterprise_id);
+        Ok(())
+    }
+
+    pub async fn list(&self, enterprise_id: ObjectId) -> Result<Vec<ApiKey>, ApiKeyError> {
+        Ok(self.keys.find_by_enterprise(enterprise_id).await?)
+    }
+
+    /// Check a signed request and consume its nonce; returns the key it was signed with
+    pub async fn authenticate(&self, redis: &Arc<Client>, request: &SignedRequest<'_>, now: i64, max_skew_secs: u64) -> Result<ApiKey, ApiKeyError> {
+        if request.timestamp.abs_diff(now) > max_skew_secs {
+            return Err(ApiKeyError::StaleTimestamp);
+        }
+        if request.nonce.is_empty() || request.nonce.len() > 64 || !request.nonce.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(ApiKeyError::InvalidNonce);
+        }
+        let key = match self.keys.find_by_key_id(request.key_id).await? {
+            Some(key) if !key.is_revoked() => key,
+            _ => return Err(ApiKeyError::UnknownKey),
+        };
+        let secret = derive_secret(&self.master_secret, &key.key_id, &key.salt);
+        if secret_hash(&secret) != key.secret_hash {
+            log::error!("Secret of API key {} does not match its hash; was the master secret changed?", key.key_id);
+            return Err(ApiKeyError::UnknownKey);
+        }
+        let message = canonical_request(request.method, request.path, request.timestamp, request.nonce, request.body);
+        if !verify(&secret, &message, request.signature) {
+            return Err(ApiKeyError::InvalidSignature);
+        }
+
+        // Only signed requests may burn a nonce; it is kept for as long as the timestamp is accepted
+        let mut conn = redis.get_multiplexed_async_connection().await?;
+        let options = SetOptions::default().conditional_set(ExistenceCheck::NX).with_expiration(SetExpiry::EX(2 * max_skew_secs.max(1)));
+        let fresh: Option<String> = conn.set_options(nonce_key(&key.key_id, request.nonce), 1, options).await?;
+        if fresh.is_none() {
+            return Err(ApiKeyError::Replayed);
+        }
+
+        if let Err(e) = self.keys.touch(&key.key_id).await {
+            log::warn!("Failed to record use of API key {}: {}", key.key_id, e);
+        }
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_covers_the_whole_request() {
+        let secret = derive_secret("master", "rk_1", "salt");
+        assert_ne!(secret, derive_secret("master", "rk_1", "other-salt"));
+        assert_ne!(secret, derive_secret("other-master", "rk_1", "salt"));
+
+        let message = canonical_request("post", "/rwa/erp/invoice/create", 1_700_000_000, "n-1", b"[]");
+        assert!(message.starts_with("POST\n/rwa/erp/invoice/create\n1700000000\nn-1\n"));
+        let signature = sign(&secret, &message);
+        assert!(verify(&secret, &message, &signature));
+        assert!(verify(&secret, &message, &signature.to_uppercase()));
+
+        let tampered = canonical_request("POST", "/rwa/erp/invoice/create", 1_700_000_000, "n-1", b"[{}]");
+        assert!(!verify(&secret, &tampered, &signature));
+        assert!(!verify(&derive_secret("master", "rk_1", "rotated"), &message, &signature));
+        assert!(!verify(&secret, &message, "not-hex"));
+    }
+}
@@ -0,0 +1,392 @@
+//! Asynchronous chain-write jobs.
+//!
+//! Endpoints enqueue a `ChainJob` in Redis and return its id straight away. A single worker
+//! reserves jobs one at a time (`BLMOVE` from the queue to a processing list, so a crash leaves
+//! them recoverable) and broadcasts them through the transaction outbox, so nonces keep the queue
+//! order. Waiting for the receipt happens in a separate task, up to `MAX_IN_FLIGHT` at once, which
+//! then applies the job's `JobEffect` to the database and acknowledges the job. The applied effect
+//! is kept on the outbox entry so `ReorgService` can roll it back if a reorg drops the transaction;
+//! a confirmed job whose effect could not be applied ends as `EffectFailed` instead.
+
+use std::sync::Arc;
+
+use ethers::types::TransactionReceipt;
+use mongodb::{
+    bson::{oid::ObjectId, DateTime},
+    Database,
+};
+use redis::{AsyncCommands, Client, Direction};
+use thiserror::Error;
+
+use common::domain::dto::chain_job_dto::{ChainJob, ChainJobStatus, JobEffect, JobReceiptDto};
+use common::domain::dto::contract_call_dto::ContractCall;
+use common::domain::entity::InvoiceBatchStatus;
+use pharos_interact::{ContractRegistry, RawTransactionSender};
+use tokio::sync::Semaphore;
+
+use crate::lifecycle::LifecycleService;
+use crate::outbox::{OutboxError, OutboxService};
+use crate::repository::{InvoiceBatchRepository, InvoiceRepository};
+
+const QUEUE_KEY: &str = "rwa:chain_jobs:queue";
+const PROCESSING_KEY: &str = "rwa:chain_jobs:processing";
+const JOB_TTL_SECONDS: u64 = 7 * 24 * 3600;
+const RESERVE_TIMEOUT_SECONDS: f64 = 5.0;
+const MAX_ATTEMPTS: u32 = 5;
+// Sent jobs waiting for their receipt at the same time
+const MAX_IN_FLIGHT: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum ChainJobError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
+    #[error("Invalid job data: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Outbox error: {0}")]
+    Outbox(#[from] OutboxError),
+
+    #[error("Invalid id in job effect: {0}")]
+    InvalidId(String),
+
+    #[error("Deployment '{0}' has no blockchain connection")]
+    Unavailable(String),
+}
+
+fn job_key(id: &str) -> String {
+    format!("rwa:chain_job:{}", id)
+}
+
+/// Summarise a mined receipt for the job status.
+pub fn receipt_summary(receipt: &TransactionReceipt) -> JobReceiptDto {
+    JobReceiptDto {
+        transaction_hash: format!("{:?}", receipt.transaction_hash),
+        block_number: receipt.block_number.map(|block| block.as_u64()),
+        gas_used: receipt.gas_used.map(|gas| gas.to_string()),
+        success: receipt.status == Some(1.into()),
+    }
+}
+
+/// Redis-backed storage and FIFO queue of chain jobs.
+#[derive(Clone)]
+pub struct JobQueue {
+    client: Arc<Client>,
+}
+
+impl JobQueue {
+    pub fn new(client: Arc<Client>) -> Self {
+        Self { client }
+    }
+
+    /// Store a new job for the contract on `deployment` and put it at the back of the queue.
+    pub async fn enqueue(
+        &self,
+        deployment: &str,
+        call: ContractCall,
+        effect: JobEffect,
+        reference: Option<String>,
+        requested_by: &str,
+    ) -> Result<ChainJob, ChainJobError> {
+        let job = ChainJob::new(uuid::Uuid::new_v4().to_string(), deployment.to_string(), call, effect, reference, requested_by.to_string());
+        self.save(&job).await?;
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = conn.lpush(QUEUE_KEY, &job.id).await?;
+        log::info!("Chain job {} ({}) queued by {}", job.id, job.call.method_name(), requested_by);
+        Ok(job)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<ChainJob>, ChainJobError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let data: Option<String> = conn.get(job_key(id)).await?;
+        match data {
+            Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn save(&self, job: &ChainJob) -> Result<(), ChainJobError> {
+        let data = serde_json::to_string(job)?;
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = conn.set_ex(job_key(&job.id), data, JOB_TTL_SECONDS).await?;
+        Ok(())
+    }
+
+    /// Wait briefly for the next job id and move it to the processing list.
+    pub async fn reserve(&self) -> Result<Option<String>, ChainJobError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let id: Option<String> = conn.blmove(QUEUE_KEY, PROCESSING_KEY, Direction::Right, Direction::Left, RESERVE_TIMEOUT_SECONDS).await?;
+        Ok(id)
+    }
+
+    /// Drop a finished job from the processing list.
+    pub async fn ack(&self, id: &str) -> Result<(), ChainJobError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = conn.lrem(PROCESSING_KEY, 1, id).await?;
+        Ok(())
+    }
+
+    /// Put a reserved job back at the end of the queue.
+    pub async fn requeue(&self, id: &str) -> Result<(), ChainJobError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = conn.lpush(QUEUE_KEY, id).await?;
+        let _: () = conn.lrem(PROCESSING_KEY, 1, id).await?;
+        Ok(())
+    }
+
+    /// Move jobs left in processing by a previous worker back to the queue. Returns how many moved.
+    pub async fn requeue_processing(&self) -> Result<usize, ChainJobError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let mut moved = 0;
+        loop {
+            let id: Option<String> = conn.lmove(PROCESSING_KEY, QUEUE_KEY, Direction::Right, Direction::Right).await?;
+            if id.is_none() {
+                return Ok(moved);
+            }
+            moved += 1;
+        }
+    }
+}
+
+/// Executes queued jobs through the outbox and applies their effects.
+pub struct ChainJobRunner {
+    queue: JobQueue,
+    outbox: OutboxService,
+    batches: InvoiceBatchRepository,
+    invoices: InvoiceRepository,
+    lifecycle: LifecycleService,
+    in_flight: Arc<Semaphore>,
+}
+
+impl ChainJobRunner {
+    pub fn new(db: &Database, queue: JobQueue) -> Self {
+        Self {
+            queue,
+            outbox: OutboxService::new(db),
+            batches: InvoiceBatchRepository::new(db),
+            invoices: InvoiceRepository::new(db),
+            lifecycle: LifecycleService::new(db),
+            in_flight: Arc::new(Semaphore::new(MAX_IN_FLIGHT)),
+        }
+    }
+
+    /// Reserve the next job and broadcast it on the contract of its deployment; its receipt is
+    /// awaited in a spawned task. Returns false when the queue stayed empty.
+    pub async fn run_next(self: &Arc<Self>, contracts: &ContractRegistry) -> Result<bool, ChainJobError> {
+        // Stop taking jobs while too many sent ones still wait for receipts
+        let permit = Arc::clone(&self.in_flight).acquire_owned().await.expect("in-flight semaphore is never closed");
+        let Some(id) = self.queue.reserve().await? else {
+            return Ok(false);
+        };
+        let Some(mut job) = self.queue.get(&id).await? else {
+            log::warn!("Chain job {} expired before it ran", id);
+            self.queue.ack(&id).await?;
+            return Ok(true);
+        };
+
+        // Jobs queued before deployments were named run on the default one
+        job.deployment = contracts.resolve_name(Some(&job.deployment)).to_string();
+        let Some(sender) = contracts.get(Some(&job.deployment)) else {
+            log::error!("Chain job {} targets unknown deployment '{}'", id, job.deployment);
+            job.status = ChainJobStatus::Failed;
+            job.error = Some(format!("Unknown deployment '{}'", job.deployment));
+            self.save(&mut job).await?;
+            self.queue.ack(&id).await?;
+            return Ok(true);
+        };
+        // Leave the job queued until the deployment reconnects; attempts are not spent on outages
+        if !contracts.is_connected(Some(&job.deployment)) {
+            self.queue.requeue(&id).await?;
+            return Err(ChainJobError::Unavailable(job.deployment));
+        }
+
+        job.attempts += 1;
+        match self.broadcast(sender.as_ref(), &mut job).await {
+            Ok(true) => {
+                let runner = Arc::clone(self);
+                tokio::spawn(async move {
+                    let result = runner.confirm(sender.as_ref(), &mut job).await;
+                    if let Err(e) = runner.finish(&mut job, result).await {
+                        log::error!("Failed to store the outcome of chain job {}: {}", job.id, e);
+                    }
+                    drop(permit);
+                });
+            }
+            result => self.finish(&mut job, result).await?,
+        }
+        Ok(true)
+    }
+
+    // Save the job and take it off the processing list, or put it back for a retry on Ok(false)
+    async fn finish(&self, job: &mut ChainJob, result: Result<bool, ChainJobError>) -> Result<(), ChainJobError> {
+        match result {
+            Ok(true) => {
+                self.save(job).await?;
+                self.queue.ack(&job.id).await?;
+            }
+            Ok(false) => {
+                self.save(job).await?;
+                self.queue.requeue(&job.id).await?;
+            }
+            Err(e) => {
+                job.status = ChainJobStatus::Failed;
+                job.error = Some(e.to_string());
+                self.save(job).await?;
+                self.queue.ack(&job.id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Record and broadcast the job's transaction; Ok(false) means it should be retried later
+    async fn broadcast<S: RawTransactionSender + ?Sized>(&self, sender: &S, job: &mut ChainJob) -> Result<bool, ChainJobError> {
+        let method = job.call.method_name();
+        let outbox_id = match self.outbox_id(job) {
+            Some(id) => id,
+            None => {
+                let id = self.outbox.record(job.call.clone(), job.reference.clone(), &job.deployment).await?;
+                job.outbox_id = Some(id.to_hex());
+                self.save(job).await?;
+                id
+            }
+        };
+
+        match self.outbox.send(sender, outbox_id).await {
+            Ok(tx_hash) => {
+                if job.status != ChainJobStatus::Sent {
+                    job.status = ChainJobStatus::Sent;
+                    job.tx_hash = Some(tx_hash);
+                    job.error = None;
+                    self.save(job).await?;
+                }
+                Ok(true)
+            }
+            Err(OutboxError::Broadcast { reason, .. }) if job.attempts < MAX_ATTEMPTS => {
+                log::warn!("Chain job {} ({}) broadcast failed, will retry: {}", job.id, method, reason);
+                job.error = Some(reason);
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Wait for the receipt of a sent job and apply its effect; Ok(false) means check again later
+    async fn confirm<S: RawTransactionSender + ?Sized>(&self, sender: &S, job: &mut ChainJob) -> Result<bool, ChainJobError> {
+        let method = job.call.method_name();
+        let outbox_id = self.outbox_id(job).ok_or_else(|| ChainJobError::InvalidId(job.outbox_id.clone().unwrap_or_default()))?;
+
+        let receipt = match self.outbox.wait(sender, outbox_id).await {
+            Ok(receipt) => receipt,
+            Err(OutboxError::ReceiptTimeout(tx_hash)) => {
+                log::warn!("Chain job {} ({}) has no receipt for {} yet, will check again", job.id, method, tx_hash);
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let summary = receipt_summary(&receipt);
+        job.tx_hash = Some(summary.transaction_hash.clone());
+        if summary.success {
+            job.status = ChainJobStatus::Confirmed;
+            log::info!("Chain job {} ({}) confirmed in tx {}", job.id, method, summary.transaction_hash);
+            // The transaction is final; a failed database update is reported on the job, not retried
+            match self.apply_effect(job, &summary.transaction_hash).await {
+                Ok(()) => {
+                    if let Err(e) = self.outbox.record_effect(outbox_id, &job.effect).await {
+                        log::warn!("Chain job {} ({}) effect applied but not recorded for reorg checks: {}", job.id, method, e);
+                    }
+                }
+                Err(e) => {
+                    log::error!("Chain job {} ({}) confirmed but applying its effect failed: {}", job.id, method, e);
+                    let error = format!("Confirmed on chain but failed to record the result: {}", e);
+                    job.status = ChainJobStatus::EffectFailed;
+                    if let Err(e) = self.outbox.record_effect_error(outbox_id, &error).await {
+                        log::warn!("Chain job {} ({}) effect failure not recorded on the outbox entry: {}", job.id, method, e);
+                    }
+                    job.error = Some(error);
+                }
+            }
+        } else {
+            job.status = ChainJobStatus::Reverted;
+            job.error = Some(format!("{} reverted", method));
+            log::error!("Chain job {} ({}) reverted in tx {}", job.id, method, summary.transaction_hash);
+        }
+        job.receipt = Some(summary);
+        Ok(true)
+    }
+
+    fn outbox_id(&self, job: &ChainJob) -> Option<ObjectId> {
+        job.outbox_id.as_deref().and_then(|id| ObjectId::parse_str(id).ok())
+    }
+
+    async fn apply_effect(&self, job: &ChainJob, tx_hash: &str) -> Result<(), String> {
+        match &job.effect {
+            JobEffect::None => Ok(()),
+            JobEffect::ImportInvoices => {
+                let ContractCall::BatchCreateInvoices { invoices } = &job.call else {
+                    return Err("ImportInvoices requires a batchCreateInvoices call".to_string());
+                };
+                for invoice in invoices {
+                    match self.invoices.find_by_invoice_number(&invoice.invoice_number).await {
+                        Ok(Some(_)) => {}
+                        Ok(None) => {
+                            self.invoices.create_from_blockchain(invoice, Some(&job.deployment)).await.map_err(|e| e.to_string())?;
+                        }
+                        Err(e) => return Err(e.to_string()),
+                    }
+                }
+                Ok(())
+            }
+            JobEffect::RecordChainBatch { batch_id, chain_batch_id, stable_token_address } => {
+                let batch_id = parse_id(batch_id)?;
+                self.batches
+                    .record_chain_creation(batch_id, chain_batch_id, &stable_token_address.to_lowercase(), tx_hash, &job.deployment)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(())
+            }
+            JobEffect::ConfirmBatchIssue { batch_id, rbt_token_address } => {
+                let batch_id = parse_id(batch_id)?;
+                self.batches.record_issuance(batch_id, rbt_token_address.as_deref(), tx_hash).await.map_err(|e| e.to_string())?;
+                self.lifecycle
+                    .transition_batch(batch_id, InvoiceBatchStatus::Issued, &job.requested_by)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(())
+            }
+        }
+    }
+
+    async fn save(&self, job: &mut ChainJob) -> Result<(), ChainJobError> {
+        job.updated_at = DateTime::now();
+        self.queue.save(job).await
+    }
+}
+
+fn parse_id(id: &str) -> Result<ObjectId, String> {
+    ObjectId::parse_str(id).map_err(|_| ChainJobError::InvalidId(id.to_string()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{H256, U64};
+
+    #[test]
+    fn receipt_summary_reports_status() {
+        let mut receipt = TransactionReceipt {
+            transaction_hash: H256::from_low_u64_be(1),
+            block_number: Some(U64::from(42)),
+            gas_used: Some(21000.into()),
+            status: Some(U64::from(1)),
+            ..Default::default()
+        };
+        let summary = receipt_summary(&receipt);
+        assert!(summary.success);
+        assert_eq!(summary.block_number, Some(42));
+        assert_eq!(summary.gas_used.as_deref(), Some("21000"));
+
+        receipt.status = Some(U64::zero());
+        assert!(!receipt_summary(&receipt).success);
+    }
+}
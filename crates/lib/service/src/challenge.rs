@@ -0,0 +1,88 @@
+//! Storage of issued login challenges.
+//!
+//! A challenge is issued by `/challenge` and consumed by `/login`, which may be served by another
+//! api-server replica, so the default store is Redis: `SET EX` on issue and an atomic `GETDEL` on
+//! login, so a challenge can be used exactly once across all replicas. The in-process moka store
+//! is kept for single-node development without Redis.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use moka::future::Cache;
+use redis::{AsyncCommands, Client};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ChallengeError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+}
+
+fn challenge_key(request_id: &str) -> String {
+    format!("rwa:login_challenge:{}", request_id)
+}
+
+enum Backend {
+    Redis(Arc<Client>),
+    Memory(Cache<String, String>),
+}
+
+pub struct ChallengeStore {
+    backend: Backend,
+    ttl: Duration,
+}
+
+impl ChallengeStore {
+    /// Shared store for multi-instance deployments
+    pub fn redis(client: Arc<Client>, ttl: Duration) -> Self {
+        Self { backend: Backend::Redis(client), ttl }
+    }
+
+    /// Process-local store for single-node development
+    pub fn memory(ttl: Duration) -> Self {
+        let cache = Cache::builder().time_to_live(ttl).max_capacity(10_000).build();
+        Self { backend: Backend::Memory(cache), ttl }
+    }
+
+    pub fn is_shared(&self) -> bool {
+        matches!(self.backend, Backend::Redis(_))
+    }
+
+    /// Store the challenge issued for `request_id` until it expires
+    pub async fn put(&self, request_id: &str, challenge: &str) -> Result<(), ChallengeError> {
+        match &self.backend {
+            Backend::Redis(client) => {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let _: () = conn.set_ex(challenge_key(request_id), challenge, self.ttl.as_secs().max(1)).await?;
+            }
+            Backend::Memory(cache) => cache.insert(request_id.to_string(), challenge.to_string()).await,
+        }
+        Ok(())
+    }
+
+    /// Remove and return the challenge of `request_id`; None when unknown, expired or already used
+    pub async fn take(&self, request_id: &str) -> Result<Option<String>, ChallengeError> {
+        match &self.backend {
+            Backend::Redis(client) => {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                Ok(conn.get_del(challenge_key(request_id)).await?)
+            }
+            Backend::Memory(cache) => Ok(cache.remove(request_id).await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn challenge_is_taken_once() {
+        let store = ChallengeStore::memory(Duration::from_secs(60));
+        store.put("req-1", "message").await.unwrap();
+
+        assert_eq!(store.take("req-1").await.unwrap().as_deref(), Some("message"));
+        assert_eq!(store.take("req-1").await.unwrap(), None);
+        assert_eq!(store.take("req-2").await.unwrap(), None);
+    }
+}
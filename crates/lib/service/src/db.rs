@@ -2,7 +2,7 @@ use mongodb::{Client, Database, options::ClientOptions};
 use log::{info, error};
 use mongodb::options::Credential;
 use configs::cfgs::Database as DbConfig;
-use common::domain::entity::{Repayment, User};
+use common::domain::entity::{ApiKey, Invoice, RbtHolding, Repayment, SettlementNft, SharePurchase, TxOutboxEntry, User};
 
 // MongoDB client initialization
 pub async fn init_mongodb(db_config: &DbConfig) -> Result<Database, mongodb::error::Error> {
@@ -29,6 +29,12 @@ pub async fn init_mongodb(db_config: &DbConfig) -> Result<Database, mongodb::err
     Ok(db)
 }
 
+// E11000: an insert or update violated a unique index
+pub fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    use mongodb::error::{ErrorKind, WriteFailure};
+    matches!(e.kind.as_ref(), ErrorKind::Write(WriteFailure::WriteError(we)) if we.code == 11000)
+}
+
 // Helper function to get collection names
 pub async fn get_collection_names(db: &Database) -> Result<Vec<String>, mongodb::error::Error> {
     let names = db.list_collection_names().await?;
@@ -62,5 +68,69 @@ pub async fn create_indexes(db: &Database) -> Result<(), mongodb::error::Error>
     db.collection::<Repayment>("repayments")
         .create_index(index)
         .await?;
+
+    // One holding document per (user, batch) so purchases can be upserted
+    let options = IndexOptions::builder().unique(true).build();
+    let index = IndexModel::builder()
+        .keys(doc! { "user_id": 1, "batch_id": 1 })
+        .options(options)
+        .build();
+
+    db.collection::<RbtHolding>("rbt_holdings")
+        .create_index(index)
+        .await?;
+
+    // At most one settlement record per batch
+    let options = IndexOptions::builder().unique(true).build();
+    let index = IndexModel::builder()
+        .keys(doc! { "batch_id": 1 })
+        .options(options)
+        .build();
+
+    db.collection::<SettlementNft>("settlement_nfts")
+        .create_index(index)
+        .await?;
+
+    // The outbox resumer scans open entries in creation order
+    let index = IndexModel::builder()
+        .keys(doc! { "status": 1, "created_at": 1 })
+        .build();
+
+    db.collection::<TxOutboxEntry>("tx_outbox")
+        .create_index(index)
+        .await?;
+
+    // ERP requests look keys up by their public id
+    let options = IndexOptions::builder().unique(true).build();
+    let index = IndexModel::builder()
+        .keys(doc! { "key_id": 1 })
+        .options(options)
+        .build();
+
+    db.collection::<ApiKey>("api_keys")
+        .create_index(index)
+        .await?;
+
+    // Invoice numbers are the business key shared with the chain and ERP systems
+    let options = IndexOptions::builder().unique(true).build();
+    let index = IndexModel::builder()
+        .keys(doc! { "invoice_number": 1 })
+        .options(options)
+        .build();
+
+    db.collection::<Invoice>("invoices")
+        .create_index(index)
+        .await?;
+
+    // A purchase transaction credits shares once
+    let options = IndexOptions::builder().unique(true).build();
+    let index = IndexModel::builder()
+        .keys(doc! { "transaction_hash": 1 })
+        .options(options)
+        .build();
+
+    db.collection::<SharePurchase>("share_purchases")
+        .create_index(index)
+        .await?;
     Ok(())
 } 
\ No newline at end of file
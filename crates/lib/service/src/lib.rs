@@ -1,14 +1,38 @@
 #![allow(warnings)]
 pub mod db;
+pub mod api_key;
 pub mod cache;
+pub mod chain_job;
+pub mod challenge;
 pub mod error;
 pub mod repository;
+pub mod lifecycle;
+pub mod outbox;
+pub mod purchase;
+pub mod reconcile;
+pub mod reorg;
+pub mod repayment;
+pub mod session;
+pub mod settlement;
+pub mod siwe;
 
 // Re-export key items for easier access from other crates
 pub use db::{create_indexes, init_mongodb};
+pub use api_key::{ApiKeyError, ApiKeyService, SignedRequest};
 pub use cache::init_redis_client;
+pub use chain_job::{ChainJobError, ChainJobRunner, JobQueue};
+pub use challenge::{ChallengeError, ChallengeStore};
 pub use error::ServiceError;
-pub use repository::{EnterpriseRepository, InvoiceRepository, UserRepository};
+pub use lifecycle::{LifecycleError, LifecycleService};
+pub use outbox::{OutboxError, OutboxService};
+pub use purchase::{PurchaseError, PurchaseService};
+pub use reconcile::{ReconcileError, ReconcileService};
+pub use reorg::{ReorgError, ReorgService};
+pub use repayment::{RepaymentError, RepaymentService};
+pub use session::{Session, SessionError, SessionStore};
+pub use settlement::{SettlementError, SettlementService};
+pub use siwe::{SiweError, SiweExpectation, SiweMessage, WalletCodeCache};
+pub use repository::{EnterpriseRepository, InvoiceBatchRepository, InvoiceRepository, UserRepository};
 
 // Optional: Define a struct to hold initialized clients/pools
 // pub struct ServiceContext {
@@ -0,0 +1,306 @@
+//! Lifecycle state machine for invoices and invoice batches.
+//!
+//! Every status change of an `Invoice` or `InvoiceBatch` goes through `LifecycleService`,
+//! which rejects edges that are not listed in `invoice_transition_allowed` /
+//! `batch_transition_allowed`, keeps member invoices consistent with their batch,
+//! records each change in the `status_transitions` collection and issues the settlement
+//! record once a batch is `Settled`.
+
+use mongodb::{bson::oid::ObjectId, Database};
+use thiserror::Error;
+
+use common::domain::entity::{Invoice, InvoiceBatch, InvoiceBatchStatus, InvoiceStatus, StatusTransition, TransitionEntity};
+
+use crate::repository::{InvoiceBatchRepository, InvoiceRepository, StatusTransitionRepository};
+use crate::settlement::SettlementService;
+
+#[derive(Error, Debug)]
+pub enum LifecycleError {
+    #[error("Illegal invoice transition: {from:?} -> {to:?}")]
+    IllegalInvoiceTransition { from: InvoiceStatus, to: InvoiceStatus },
+
+    #[error("Illegal batch transition: {from:?} -> {to:?}")]
+    IllegalBatchTransition { from: InvoiceBatchStatus, to: InvoiceBatchStatus },
+
+    #[error("Invoice not found: {0}")]
+    InvoiceNotFound(ObjectId),
+
+    #[error("Batch not found: {0}")]
+    BatchNotFound(ObjectId),
+
+    #[error("Inconsistent state: {0}")]
+    Inconsistent(String),
+
+    #[error("Status of {0} was changed concurrently")]
+    ConcurrentModification(ObjectId),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+/// Allowed invoice edges: Pending → Verified → Packaged → Repaid, with overdue/default branches.
+/// `Packaged → Verified` is the edge taken when an invoice is removed from a batch.
+pub fn invoice_transition_allowed(from: &InvoiceStatus, to: &InvoiceStatus) -> bool {
+    use InvoiceStatus::*;
+    matches!(
+        (from, to),
+        (Pending, Verified)
+            | (Verified, Packaged)
+            | (Verified, Repaid)
+            | (Verified, Overdue)
+            | (Packaged, Verified)
+            | (Packaged, Repaid)
+            | (Packaged, Overdue)
+            | (Packaged, Defaulted)
+            | (Overdue, Repaid)
+            | (Overdue, Defaulted)
+    )
+}
+
+/// Allowed batch edges: Packaging → Issued → Trading → Repaying → Settled/Defaulted.
+/// A batch may skip `Trading` if no shares were sold, and may default from any post-issue state.
+pub fn batch_transition_allowed(from: &InvoiceBatchStatus, to: &InvoiceBatchStatus) -> bool {
+    use InvoiceBatchStatus::*;
+    matches!(
+        (from, to),
+        (Packaging, Issued)
+            | (Issued, Trading)
+            | (Issued, Repaying)
+            | (Issued, Defaulted)
+            | (Trading, Repaying)
+            | (Trading, Defaulted)
+            | (Repaying, Settled)
+            | (Repaying, Defaulted)
+    )
+}
+
+/// Status member invoices must follow when their batch reaches `batch_status`, if any.
+fn member_status_for(batch_status: &InvoiceBatchStatus) -> Option<InvoiceStatus> {
+    match batch_status {
+        InvoiceBatchStatus::Settled => Some(InvoiceStatus::Repaid),
+        InvoiceBatchStatus::Defaulted => Some(InvoiceStatus::Defaulted),
+        _ => None,
+    }
+}
+
+pub struct LifecycleService {
+    invoices: InvoiceRepository,
+    batches: InvoiceBatchRepository,
+    transitions: StatusTransitionRepository,
+    settlements: SettlementService,
+}
+
+impl LifecycleService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            invoices: InvoiceRepository::new(db),
+            batches: InvoiceBatchRepository::new(db),
+            transitions: StatusTransitionRepository::new(db),
+            settlements: SettlementService::new(db),
+        }
+    }
+
+    /// Move a single invoice to `to`. Packaging edges go through `package_invoice`/`unpackage_invoice`.
+    pub async fn transition_invoice(&self, invoice_id: ObjectId, to: InvoiceStatus, actor: &str) -> Result<Invoice, LifecycleError> {
+        let invoice = self.load_invoice(invoice_id).await?;
+        if to == InvoiceStatus::Packaged || (invoice.status == InvoiceStatus::Packaged && to == InvoiceStatus::Verified) {
+            return Err(LifecycleError::Inconsistent("Packaging changes must go through the batch".to_string()));
+        }
+        self.apply_invoice_transition(&invoice, to, actor).await?;
+        self.load_invoice(invoice_id).await
+    }
+
+    /// Add a `Verified` invoice to a batch that is still `Packaging` (invoice → `Packaged`).
+    pub async fn package_invoice(&self, batch_id: ObjectId, invoice_id: ObjectId, actor: &str) -> Result<Invoice, LifecycleError> {
+        let batch = self.load_batch(batch_id).await?;
+        Self::ensure_packaging(&batch)?;
+        let invoice = self.load_invoice(invoice_id).await?;
+        if invoice.batch_id.is_some() {
+            return Err(LifecycleError::Inconsistent("Invoice is already packaged into a batch".to_string()));
+        }
+        if !invoice_transition_allowed(&invoice.status, &InvoiceStatus::Packaged) {
+            return Err(LifecycleError::IllegalInvoiceTransition { from: invoice.status, to: InvoiceStatus::Packaged });
+        }
+
+        if self.invoices.add_to_batch(invoice_id, batch_id).await?.matched_count == 0 {
+            return Err(LifecycleError::ConcurrentModification(invoice_id));
+        }
+        self.record(TransitionEntity::Invoice, invoice_id, format!("{:?}", invoice.status), format!("{:?}", InvoiceStatus::Packaged), actor)
+            .await?;
+        self.load_invoice(invoice_id).await
+    }
+
+    /// Take an invoice out of a batch that is still `Packaging` (invoice → `Verified`).
+    pub async fn unpackage_invoice(&self, batch_id: ObjectId, invoice_id: ObjectId, actor: &str) -> Result<Invoice, LifecycleError> {
+        let batch = self.load_batch(batch_id).await?;
+        Self::ensure_packaging(&batch)?;
+        let invoice = self.load_invoice(invoice_id).await?;
+        if invoice.batch_id != Some(batch_id) {
+            return Err(LifecycleError::Inconsistent("Invoice is not part of this batch".to_string()));
+        }
+        if !invoice_transition_allowed(&invoice.status, &InvoiceStatus::Verified) {
+            return Err(LifecycleError::IllegalInvoiceTransition { from: invoice.status, to: InvoiceStatus::Verified });
+        }
+
+        if self.invoices.remove_from_batch(invoice_id, batch_id).await?.matched_count == 0 {
+            return Err(LifecycleError::ConcurrentModification(invoice_id));
+        }
+        self.record(TransitionEntity::Invoice, invoice_id, format!("{:?}", invoice.status), format!("{:?}", InvoiceStatus::Verified), actor)
+            .await?;
+        self.load_invoice(invoice_id).await
+    }
+
+    /// Move a batch to `to`, cascading `Settled`/`Defaulted` to its member invoices.
+    pub async fn transition_batch(&self, batch_id: ObjectId, to: InvoiceBatchStatus, actor: &str) -> Result<InvoiceBatch, LifecycleError> {
+        let batch = self.load_batch(batch_id).await?;
+        if !batch_transition_allowed(&batch.status, &to) {
+            return Err(LifecycleError::IllegalBatchTransition { from: batch.status, to });
+        }
+
+        let members = self.invoices.find_by_batch(batch_id).await?;
+        if batch.status == InvoiceBatchStatus::Packaging {
+            // Leaving Packaging freezes the member list, so it must be non-empty and fully packaged
+            if members.is_empty() {
+                return Err(LifecycleError::Inconsistent("Batch has no invoices".to_string()));
+            }
+            if let Some(invoice) = members.iter().find(|invoice| invoice.status != InvoiceStatus::Packaged) {
+                return Err(LifecycleError::Inconsistent(format!(
+                    "Invoice {} is {:?}, expected Packaged",
+                    invoice.invoice_number, invoice.status
+                )));
+            }
+        }
+
+        // Validate the cascade up-front so a rejected member does not leave the batch half-moved
+        let member_target = member_status_for(&to);
+        if let Some(target) = &member_target {
+            for invoice in members.iter().filter(|invoice| &invoice.status != target) {
+                if !invoice_transition_allowed(&invoice.status, target) {
+                    return Err(LifecycleError::IllegalInvoiceTransition { from: invoice.status.clone(), to: target.clone() });
+                }
+            }
+        }
+
+        if self.batches.compare_and_set_status(batch_id, batch.status.clone(), to.clone()).await?.matched_count == 0 {
+            return Err(LifecycleError::ConcurrentModification(batch_id));
+        }
+        self.record(TransitionEntity::InvoiceBatch, batch_id, format!("{:?}", batch.status), format!("{:?}", to), actor)
+            .await?;
+
+        if let Some(target) = member_target {
+            for invoice in members.iter().filter(|invoice| invoice.status != target) {
+                self.apply_invoice_transition(invoice, target.clone(), actor).await?;
+            }
+        }
+
+        let batch = self.load_batch(batch_id).await?;
+        if batch.status == InvoiceBatchStatus::Settled {
+            // The status change stands even if this fails; the record is re-issued on lookup
+            if let Err(e) = self.settlements.issue(&batch).await {
+                log::error!("Batch {} settled but settlement record was not issued: {}", batch_id, e);
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Undo a batch status change whose transaction was dropped by a reorg. Bypasses
+    /// `batch_transition_allowed`, and only applies while the batch is still in `from`.
+    pub async fn rollback_batch(&self, batch_id: ObjectId, from: InvoiceBatchStatus, to: InvoiceBatchStatus, actor: &str) -> Result<InvoiceBatch, LifecycleError> {
+        if self.batches.compare_and_set_status(batch_id, from.clone(), to.clone()).await?.matched_count == 0 {
+            return Err(LifecycleError::ConcurrentModification(batch_id));
+        }
+        self.record(TransitionEntity::InvoiceBatch, batch_id, format!("{:?}", from), format!("{:?}", to), actor)
+            .await?;
+        self.load_batch(batch_id).await
+    }
+
+    /// Audit history of an invoice or batch, oldest first.
+    pub async fn history(&self, entity_type: TransitionEntity, entity_id: ObjectId) -> Result<Vec<StatusTransition>, LifecycleError> {
+        Ok(self.transitions.find_by_entity(entity_type, entity_id).await?)
+    }
+
+    async fn apply_invoice_transition(&self, invoice: &Invoice, to: InvoiceStatus, actor: &str) -> Result<(), LifecycleError> {
+        let invoice_id = invoice.id.ok_or_else(|| LifecycleError::Inconsistent("Invoice has no id".to_string()))?;
+        if !invoice_transition_allowed(&invoice.status, &to) {
+            return Err(LifecycleError::IllegalInvoiceTransition { from: invoice.status.clone(), to });
+        }
+        if self.invoices.compare_and_set_status(invoice_id, invoice.status.clone(), to.clone()).await?.matched_count == 0 {
+            return Err(LifecycleError::ConcurrentModification(invoice_id));
+        }
+        self.record(TransitionEntity::Invoice, invoice_id, format!("{:?}", invoice.status), format!("{:?}", to), actor)
+            .await
+    }
+
+    async fn record(&self, entity_type: TransitionEntity, entity_id: ObjectId, from: String, to: String, actor: &str) -> Result<(), LifecycleError> {
+        log::info!("{:?} {} transition {} -> {} by {}", entity_type, entity_id, from, to, actor);
+        self.transitions
+            .record(StatusTransition::new(entity_type, entity_id, from, to, actor.to_string()))
+            .await?;
+        Ok(())
+    }
+
+    async fn load_invoice(&self, invoice_id: ObjectId) -> Result<Invoice, LifecycleError> {
+        self.invoices.find_by_id(invoice_id).await?.ok_or(LifecycleError::InvoiceNotFound(invoice_id))
+    }
+
+    async fn load_batch(&self, batch_id: ObjectId) -> Result<InvoiceBatch, LifecycleError> {
+        self.batches.find_by_id(batch_id).await?.ok_or(LifecycleError::BatchNotFound(batch_id))
+    }
+
+    fn ensure_packaging(batch: &InvoiceBatch) -> Result<(), LifecycleError> {
+        if batch.status != InvoiceBatchStatus::Packaging {
+            return Err(LifecycleError::Inconsistent(format!(
+                "Batch is {:?}, invoices can only change while Packaging",
+                batch.status
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invoice_happy_path_is_allowed() {
+        assert!(invoice_transition_allowed(&InvoiceStatus::Pending, &InvoiceStatus::Verified));
+        assert!(invoice_transition_allowed(&InvoiceStatus::Verified, &InvoiceStatus::Packaged));
+        assert!(invoice_transition_allowed(&InvoiceStatus::Packaged, &InvoiceStatus::Repaid));
+    }
+
+    #[test]
+    fn invoice_cannot_skip_or_leave_terminal_states() {
+        assert!(!invoice_transition_allowed(&InvoiceStatus::Pending, &InvoiceStatus::Repaid));
+        assert!(!invoice_transition_allowed(&InvoiceStatus::Pending, &InvoiceStatus::Packaged));
+        assert!(!invoice_transition_allowed(&InvoiceStatus::Repaid, &InvoiceStatus::Pending));
+        assert!(!invoice_transition_allowed(&InvoiceStatus::Defaulted, &InvoiceStatus::Repaid));
+        assert!(!invoice_transition_allowed(&InvoiceStatus::Verified, &InvoiceStatus::Verified));
+    }
+
+    #[test]
+    fn batch_happy_path_is_allowed() {
+        use InvoiceBatchStatus::*;
+        let path = [Packaging, Issued, Trading, Repaying, Settled];
+        for edge in path.windows(2) {
+            assert!(batch_transition_allowed(&edge[0], &edge[1]), "{:?} -> {:?}", edge[0], edge[1]);
+        }
+        assert!(batch_transition_allowed(&Repaying, &Defaulted));
+    }
+
+    #[test]
+    fn batch_cannot_skip_issuance_or_reopen() {
+        use InvoiceBatchStatus::*;
+        assert!(!batch_transition_allowed(&Packaging, &Trading));
+        assert!(!batch_transition_allowed(&Packaging, &Settled));
+        assert!(!batch_transition_allowed(&Settled, &Repaying));
+        assert!(!batch_transition_allowed(&Issued, &Packaging));
+    }
+
+    #[test]
+    fn settlement_cascades_to_member_invoices() {
+        assert_eq!(member_status_for(&InvoiceBatchStatus::Settled), Some(InvoiceStatus::Repaid));
+        assert_eq!(member_status_for(&InvoiceBatchStatus::Defaulted), Some(InvoiceStatus::Defaulted));
+        assert_eq!(member_status_for(&InvoiceBatchStatus::Trading), None);
+    }
+}
@@ -0,0 +1,306 @@
+//! Durable outbox for contract writes.
+//!
+//! Each write is stored in `tx_outbox` before anything touches the chain. The signed
+//! transaction (hash, nonce, raw bytes) is persisted *before* it is broadcast, so after a
+//! crash `resume` can re-broadcast the exact same transaction or pick up its receipt instead
+//! of sending a second one. A transaction without a receipt for `REPLACE_AFTER` is replaced by
+//! one with the same nonce and higher fees; every earlier hash is kept and checked for a receipt,
+//! since any of them may end up mined. A receipt is only recorded once its block is as deep as the
+//! sender's `confirmations`; `ReorgService` later re-checks recent ones.
+
+use std::time::Duration;
+
+use ethers::types::TransactionReceipt;
+use mongodb::{bson::oid::ObjectId, Database};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use common::domain::dto::contract_call_dto::ContractCall;
+use common::domain::entity::{TxOutboxEntry, TxOutboxStatus};
+use common::domain::dto::chain_job_dto::JobEffect;
+use pharos_interact::{is_final, RawTransactionSender, SignedCall};
+
+use crate::repository::TxOutboxRepository;
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+const REPLACE_AFTER: Duration = Duration::from_secs(60);
+const MAX_REPLACEMENTS: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum OutboxError {
+    #[error("Failed to sign {method}: {reason}")]
+    Sign { method: String, reason: String },
+
+    #[error("Failed to broadcast {tx_hash}: {reason}")]
+    Broadcast { tx_hash: String, reason: String },
+
+    #[error("No final receipt for {0} yet; it stays in the outbox and will be resumed")]
+    ReceiptTimeout(String),
+
+    #[error("Outbox entry failed: {0}")]
+    Failed(String),
+
+    #[error("Inconsistent outbox entry: {0}")]
+    Inconsistent(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+pub struct OutboxService {
+    entries: TxOutboxRepository,
+}
+
+impl OutboxService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            entries: TxOutboxRepository::new(db),
+        }
+    }
+
+    /// Record `call`, sign, persist, broadcast and wait for its receipt to be final.
+    /// A reverted transaction is returned as a receipt with status 0, not as an error.
+    pub async fn execute<S: RawTransactionSender + ?Sized>(
+        &self,
+        sender: &S,
+        call: ContractCall,
+        reference: Option<String>,
+        deployment: &str,
+    ) -> Result<TransactionReceipt, OutboxError> {
+        let id = self.record(call, reference, deployment).await?;
+        self.send(sender, id).await?;
+        self.wait(sender, id).await
+    }
+
+    /// Store the intended call as a `Pending` entry without touching the chain.
+    pub async fn record(&self, call: ContractCall, reference: Option<String>, deployment: &str) -> Result<ObjectId, OutboxError> {
+        let entry = self.entries.create(TxOutboxEntry::new(call, reference, Some(deployment.to_string()))).await?;
+        entry.id.ok_or_else(|| OutboxError::Inconsistent("Outbox entry has no id".to_string()))
+    }
+
+    /// Sign (if needed) and broadcast an entry. Returns the transaction hash.
+    /// Safe to call again for an entry that was already signed or sent.
+    pub async fn send<S: RawTransactionSender + ?Sized>(&self, sender: &S, id: ObjectId) -> Result<String, OutboxError> {
+        let mut entry = self.load(id).await?;
+        let method = entry.call.method_name();
+
+        if entry.status == TxOutboxStatus::Failed {
+            return Err(OutboxError::Failed(entry.error.unwrap_or_default()));
+        }
+
+        if entry.status == TxOutboxStatus::Pending {
+            let signed = match sender.sign_call(&entry.call).await {
+                Ok(signed) => signed,
+                Err(e) => {
+                    self.entries.mark_failed(id, &e.to_string()).await?;
+                    return Err(OutboxError::Sign { method: method.to_string(), reason: e.to_string() });
+                }
+            };
+            let updated = self
+                .entries
+                .mark_signed(id, &sender.sender_address(), &signed.tx_hash, signed.nonce as i64, &signed.raw_tx)
+                .await?;
+            if updated.matched_count == 0 {
+                return Err(OutboxError::Inconsistent(format!("Outbox entry {} was signed concurrently", id)));
+            }
+            log::info!("Outbox entry {} ({}) signed: tx {} nonce {}", id, method, signed.tx_hash, signed.nonce);
+            entry.status = TxOutboxStatus::Signed;
+            entry.tx_hash = Some(signed.tx_hash);
+            entry.nonce = Some(signed.nonce as i64);
+            entry.raw_tx = Some(signed.raw_tx);
+        }
+
+        let tx_hash = entry.tx_hash.clone().ok_or_else(|| OutboxError::Inconsistent(format!("Outbox entry {} has no tx hash", id)))?;
+
+        if entry.status == TxOutboxStatus::Signed {
+            // The broadcast may have gone through before a crash; do not send it again then
+            if let Ok(Some(_)) = self.find_receipt(sender, &entry).await {
+                self.entries.mark_submitted(id).await?;
+                return Ok(tx_hash);
+            }
+            let signed = SignedCall {
+                tx_hash: tx_hash.clone(),
+                nonce: entry.nonce.unwrap_or_default() as u64,
+                raw_tx: entry.raw_tx.clone().ok_or_else(|| OutboxError::Inconsistent(format!("Outbox entry {} has no raw tx", id)))?,
+            };
+            if let Err(e) = sender.broadcast(&signed).await {
+                let reason = e.to_string();
+                // The nonce was used by another transaction, so this one can never be mined
+                if reason.to_lowercase().contains("nonce too low") {
+                    self.entries.mark_failed(id, &reason).await?;
+                } else {
+                    self.entries.record_error(id, &reason).await?;
+                }
+                return Err(OutboxError::Broadcast { tx_hash, reason });
+            }
+            self.entries.mark_submitted(id).await?;
+        }
+
+        Ok(tx_hash)
+    }
+
+    /// Wait for the receipt of a sent entry to be final and record it.
+    pub async fn wait<S: RawTransactionSender + ?Sized>(&self, sender: &S, id: ObjectId) -> Result<TransactionReceipt, OutboxError> {
+        let entry = self.load(id).await?;
+        if entry.tx_hash.is_none() {
+            return Err(OutboxError::Inconsistent(format!("Outbox entry {} was never signed", id)));
+        }
+        self.wait_for_receipt(sender, id, entry).await
+    }
+
+    /// Drive every open entry of `deployment` left by a previous process to a receipt; entries
+    /// recorded before deployments were named go to the default one. Returns how many finished.
+    pub async fn resume<S: RawTransactionSender + ?Sized>(&self, sender: &S, deployment: &str, is_default: bool) -> Result<usize, OutboxError> {
+        let open: Vec<TxOutboxEntry> = self
+            .entries
+            .find_open()
+            .await?
+            .into_iter()
+            .filter(|entry| match entry.deployment.as_deref() {
+                Some(name) => name == deployment,
+                None => is_default,
+            })
+            .collect();
+        if !open.is_empty() {
+            log::info!("Resuming {} open outbox entries on {}", open.len(), deployment);
+        }
+
+        let mut finished = 0;
+        for entry in open {
+            let Some(id) = entry.id else { continue };
+            let method = entry.call.method_name();
+            let result = match self.send(sender, id).await {
+                Ok(_) => self.wait(sender, id).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(receipt) => {
+                    finished += 1;
+                    log::info!("Outbox entry {} ({}) finished with tx {:?}", id, method, receipt.transaction_hash);
+                }
+                Err(e) => log::warn!("Outbox entry {} ({}) not finished: {}", id, method, e),
+            }
+        }
+        Ok(finished)
+    }
+
+    /// Remember the database change made for a confirmed entry, so a reorg can roll it back.
+    pub async fn record_effect(&self, id: ObjectId, effect: &JobEffect) -> Result<(), OutboxError> {
+        self.entries.mark_effect_applied(id, effect).await?;
+        Ok(())
+    }
+
+    /// Note on a confirmed entry that its database change could not be made, so nothing is rolled back for it.
+    pub async fn record_effect_error(&self, id: ObjectId, error: &str) -> Result<(), OutboxError> {
+        self.entries.record_error(id, error).await?;
+        Ok(())
+    }
+
+    pub async fn recent(&self, limit: i64) -> Result<Vec<TxOutboxEntry>, OutboxError> {
+        Ok(self.entries.find_recent(limit).await?)
+    }
+
+    async fn load(&self, id: ObjectId) -> Result<TxOutboxEntry, OutboxError> {
+        self.entries.find_by_id(id).await?.ok_or_else(|| OutboxError::Inconsistent(format!("Outbox entry {} not found", id)))
+    }
+
+    async fn wait_for_receipt<S: RawTransactionSender + ?Sized>(
+        &self,
+        sender: &S,
+        id: ObjectId,
+        mut entry: TxOutboxEntry,
+    ) -> Result<TransactionReceipt, OutboxError> {
+        let deadline = Instant::now() + RECEIPT_TIMEOUT;
+        let mut replace_at = Instant::now() + REPLACE_AFTER;
+        loop {
+            match self.find_receipt(sender, &entry).await {
+                Ok(Some(receipt)) => match self.is_deep_enough(sender, &receipt).await {
+                    Ok(true) => {
+                        self.finish(id, &receipt).await?;
+                        return Ok(receipt);
+                    }
+                    // Mined but not final yet; it is not replaced while it waits for confirmations
+                    Ok(false) => replace_at = Instant::now() + REPLACE_AFTER,
+                    Err(e) => {
+                        self.entries.record_error(id, &e).await?;
+                    }
+                },
+                Ok(None) => {}
+                Err(e) => {
+                    self.entries.record_error(id, &e.to_string()).await?;
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(OutboxError::ReceiptTimeout(entry.tx_hash.unwrap_or_default()));
+            }
+            if Instant::now() >= replace_at && entry.replaced_tx_hashes.len() < MAX_REPLACEMENTS {
+                if let Err(e) = self.replace_stuck(sender, id, &mut entry).await {
+                    log::warn!("Failed to replace stuck outbox entry {}: {}", id, e);
+                    self.entries.record_error(id, &e.to_string()).await?;
+                }
+                replace_at = Instant::now() + REPLACE_AFTER;
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+
+    // Receipt of the current transaction or of any one it replaced
+    async fn find_receipt<S: RawTransactionSender + ?Sized>(&self, sender: &S, entry: &TxOutboxEntry) -> Result<Option<TransactionReceipt>, String> {
+        for tx_hash in entry.tx_hash.iter().chain(entry.replaced_tx_hashes.iter().rev()) {
+            if let Some(receipt) = sender.get_receipt(tx_hash).await.map_err(|e| e.to_string())? {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn is_deep_enough<S: RawTransactionSender + ?Sized>(&self, sender: &S, receipt: &TransactionReceipt) -> Result<bool, String> {
+        let Some(block) = receipt.block_number else {
+            return Ok(false);
+        };
+        let head = sender.block_number().await.map_err(|e| e.to_string())?;
+        Ok(is_final(block.as_u64(), head, sender.confirmations()))
+    }
+
+    // Re-sign with higher fees under the same nonce, persist, then broadcast
+    async fn replace_stuck<S: RawTransactionSender + ?Sized>(&self, sender: &S, id: ObjectId, entry: &mut TxOutboxEntry) -> Result<(), OutboxError> {
+        let (Some(tx_hash), Some(nonce), Some(raw_tx)) = (entry.tx_hash.clone(), entry.nonce, entry.raw_tx.clone()) else {
+            return Err(OutboxError::Inconsistent(format!("Outbox entry {} has no signed transaction", id)));
+        };
+        let stuck = SignedCall { tx_hash: tx_hash.clone(), nonce: nonce as u64, raw_tx };
+        let replacement = sender.replace(&stuck).await.map_err(|e| OutboxError::Sign {
+            method: entry.call.method_name().to_string(),
+            reason: e.to_string(),
+        })?;
+
+        let updated = self.entries.mark_replaced(id, &tx_hash, &replacement.tx_hash, &replacement.raw_tx).await?;
+        if updated.matched_count == 0 {
+            return Err(OutboxError::Inconsistent(format!("Outbox entry {} was replaced concurrently", id)));
+        }
+        entry.replaced_tx_hashes.push(tx_hash.clone());
+        entry.tx_hash = Some(replacement.tx_hash.clone());
+        entry.raw_tx = Some(replacement.raw_tx.clone());
+        log::warn!("Outbox entry {} stuck at tx {} (nonce {}), replaced by {}", id, tx_hash, nonce, replacement.tx_hash);
+
+        // "nonce too low" here means one of the earlier transactions was mined meanwhile
+        sender.broadcast(&replacement).await.map_err(|e| OutboxError::Broadcast {
+            tx_hash: replacement.tx_hash,
+            reason: e.to_string(),
+        })
+    }
+
+    async fn finish(&self, id: ObjectId, receipt: &TransactionReceipt) -> Result<(), OutboxError> {
+        let status = if receipt.status == Some(1.into()) { TxOutboxStatus::Confirmed } else { TxOutboxStatus::Reverted };
+        self.entries
+            .mark_mined(
+                id,
+                status,
+                &format!("{:?}", receipt.transaction_hash),
+                receipt.block_number.map(|block| block.as_u64() as i64),
+                receipt.gas_used.map(|gas| gas.to_string()),
+            )
+            .await?;
+        Ok(())
+    }
+}
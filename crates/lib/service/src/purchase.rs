@@ -0,0 +1,152 @@
+//! Share purchases of issued invoice batches.
+//!
+//! Investors call `purchaseShares` from their own wallet, so they pay the stablecoin and hold
+//! the shares on chain themselves. `PurchaseService` stores a purchase that the caller has
+//! already verified on chain, credits it to the investor's `RbtHolding` and opens trading on
+//! the batch (`Issued` → `Trading`) with the first purchase.
+//!
+//! A holding is the sum of the investor's stored purchases in the batch rather than a running
+//! counter, so registering a transaction again after a failed credit completes the credit
+//! instead of being rejected as a duplicate.
+
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, Decimal128},
+    Database,
+};
+use thiserror::Error;
+
+use common::domain::entity::{InvoiceBatchStatus, SharePurchase};
+use common::utils::decimal::{sum_decimals, to_big_decimal};
+
+use crate::db::is_duplicate_key;
+use crate::lifecycle::{LifecycleError, LifecycleService};
+use crate::repository::{InvoiceBatchRepository, RbtHoldingRepository, SharePurchaseRepository};
+
+#[derive(Error, Debug)]
+pub enum PurchaseError {
+    #[error("Batch not found: {0}")]
+    BatchNotFound(ObjectId),
+
+    #[error("Batch is {0:?}, shares can only be purchased while Issued or Trading")]
+    NotPurchasable(InvoiceBatchStatus),
+
+    #[error("Transaction {0} is already registered as a purchase")]
+    DuplicateTransaction(String),
+
+    #[error("Shares held in batch {0} are out of range")]
+    AmountOutOfRange(ObjectId),
+
+    #[error(transparent)]
+    Lifecycle(#[from] LifecycleError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+/// Shares are sold once the batch is issued and while it trades.
+pub fn is_purchasable(status: &InvoiceBatchStatus) -> bool {
+    matches!(status, InvoiceBatchStatus::Issued | InvoiceBatchStatus::Trading)
+}
+
+// Whether a holding (None if there is none yet) lacks shares of `total`
+fn is_short(held: Option<&Decimal128>, total: &Decimal128) -> bool {
+    held.and_then(to_big_decimal) < to_big_decimal(total)
+}
+
+pub struct PurchaseService {
+    batches: InvoiceBatchRepository,
+    purchases: SharePurchaseRepository,
+    holdings: RbtHoldingRepository,
+    lifecycle: LifecycleService,
+}
+
+impl PurchaseService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            batches: InvoiceBatchRepository::new(db),
+            purchases: SharePurchaseRepository::new(db),
+            holdings: RbtHoldingRepository::new(db),
+            lifecycle: LifecycleService::new(db),
+        }
+    }
+
+    /// Record a verified purchase and credit the shares to the investor. Returns the stored purchase.
+    pub async fn record(
+        &self,
+        batch_id: ObjectId,
+        user_id: ObjectId,
+        buyer: &str,
+        amount: Decimal128,
+        transaction_hash: &str,
+        purchase_timestamp: DateTime,
+        actor: &str,
+    ) -> Result<SharePurchase, PurchaseError> {
+        let batch = self.batches.find_by_id(batch_id).await?.ok_or(PurchaseError::BatchNotFound(batch_id))?;
+        if let Some(existing) = self.purchases.find_by_transaction_hash(transaction_hash).await? {
+            // Finish a registration whose credit failed; a fully credited one is a duplicate
+            if existing.user_id == user_id && existing.batch_id == batch_id && self.credit(batch_id, &batch.status, user_id, actor).await? {
+                log::info!("Completed credit of purchase {} in batch {}", transaction_hash, batch_id);
+                return Ok(existing);
+            }
+            return Err(PurchaseError::DuplicateTransaction(transaction_hash.to_string()));
+        }
+        if !is_purchasable(&batch.status) {
+            return Err(PurchaseError::NotPurchasable(batch.status));
+        }
+
+        let purchase = SharePurchase::new(batch_id, user_id, buyer.to_string(), amount, transaction_hash.to_string(), purchase_timestamp);
+        let purchase = match self.purchases.create(purchase).await {
+            Ok(purchase) => purchase,
+            // The unique `transaction_hash` index caught a concurrent registration
+            Err(e) if is_duplicate_key(&e) => return Err(PurchaseError::DuplicateTransaction(transaction_hash.to_string())),
+            Err(e) => return Err(PurchaseError::Database(e)),
+        };
+        self.credit(batch_id, &batch.status, user_id, actor).await?;
+        Ok(purchase)
+    }
+
+    // Bring the investor's holding up to the sum of their purchases in the batch and open
+    // trading. Safe to repeat; returns whether the holding was missing shares.
+    async fn credit(&self, batch_id: ObjectId, status: &InvoiceBatchStatus, user_id: ObjectId, actor: &str) -> Result<bool, PurchaseError> {
+        let purchases = self.purchases.find_by_user_and_batch(user_id, batch_id).await?;
+        let amounts: Vec<Decimal128> = purchases.iter().map(|purchase| purchase.amount).collect();
+        let total = sum_decimals(&amounts).ok_or(PurchaseError::AmountOutOfRange(batch_id))?;
+
+        let held = self.holdings.find_by_user_and_batch(user_id, batch_id).await?;
+        let missing = is_short(held.as_ref().map(|holding| &holding.amount), &total);
+        if missing {
+            self.holdings.raise_amount(user_id, batch_id, total).await?;
+        }
+
+        // The first purchase opens trading on the batch
+        if *status == InvoiceBatchStatus::Issued {
+            if let Err(e) = self.lifecycle.transition_batch(batch_id, InvoiceBatchStatus::Trading, actor).await {
+                log::warn!("Batch {} could not move to Trading after purchase: {}", batch_id, e);
+            }
+        }
+        Ok(missing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purchasable_statuses() {
+        assert!(is_purchasable(&InvoiceBatchStatus::Issued));
+        assert!(is_purchasable(&InvoiceBatchStatus::Trading));
+        assert!(!is_purchasable(&InvoiceBatchStatus::Packaging));
+        assert!(!is_purchasable(&InvoiceBatchStatus::Repaying));
+        assert!(!is_purchasable(&InvoiceBatchStatus::Settled));
+    }
+
+    #[test]
+    fn short_holdings() {
+        let amount = |value: &str| value.parse::<Decimal128>().unwrap();
+        assert!(is_short(None, &amount("100")));
+        assert!(is_short(Some(&amount("60")), &amount("100")));
+        assert!(!is_short(Some(&amount("100")), &amount("100")));
+        assert!(!is_short(Some(&amount("150")), &amount("100")));
+    }
+}
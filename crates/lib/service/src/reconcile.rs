@@ -0,0 +1,212 @@
+//! Chain-to-database reconciliation for invoices.
+//!
+//! `ReconcileService::run` reads every invoice from `queryInvoices`, imports the ones missing
+//! from the `invoices` collection, refreshes the blockchain-mirrored fields of the ones that
+//! drifted and reports invoices that only exist in the database. Lifecycle `status` is never
+//! changed here; that stays with `LifecycleService`.
+
+use std::collections::{HashMap, HashSet};
+
+use mongodb::{bson::DateTime, Database};
+use thiserror::Error;
+
+use common::domain::dto::invoice_dto::InvoiceDataDto;
+use common::domain::dto::query_invoice_dto::QueryParamsDto;
+use common::domain::dto::reconcile_dto::{DriftKind, InvoiceDriftDto, ReconcileReportDto};
+use common::domain::entity::Invoice;
+use pharos_interact::ContractQuerier;
+
+use crate::repository::InvoiceRepository;
+
+#[derive(Error, Debug)]
+pub enum ReconcileError {
+    #[error("Chain query failed: {0}")]
+    Chain(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+/// Names of the fields where the stored invoice differs from the chain copy.
+pub fn invoice_drift(db: &Invoice, chain: &InvoiceDataDto) -> Vec<String> {
+    let mut fields = Vec::new();
+    if db.amount.to_string() != chain.amount {
+        fields.push("amount".to_string());
+    }
+    if (db.due_date.timestamp_millis() / 1000).to_string() != chain.due_date {
+        fields.push("due_date".to_string());
+    }
+    if !same_address(db.payee.as_deref(), &chain.payee) {
+        fields.push("payee".to_string());
+    }
+    if !same_address(db.payer.as_deref(), &chain.payer) {
+        fields.push("payer".to_string());
+    }
+    if db.ipfs_hash.as_deref() != Some(chain.ipfs_hash.as_str()) {
+        fields.push("ipfs_hash".to_string());
+    }
+    if db.contract_hash.as_deref() != Some(chain.contract_hash.as_str()) {
+        fields.push("contract_hash".to_string());
+    }
+    if db.token_batch.as_deref() != Some(chain.token_batch.as_str()) {
+        fields.push("token_batch".to_string());
+    }
+    if db.is_cleared != Some(chain.is_cleared) {
+        fields.push("is_cleared".to_string());
+    }
+    if db.is_valid != Some(chain.is_valid) {
+        fields.push("is_valid".to_string());
+    }
+    fields
+}
+
+fn same_address(db: Option<&str>, chain: &str) -> bool {
+    db.is_some_and(|db| db.eq_ignore_ascii_case(chain))
+}
+
+pub struct ReconcileService {
+    invoices: InvoiceRepository,
+}
+
+impl ReconcileService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            invoices: InvoiceRepository::new(db),
+        }
+    }
+
+    /// Run one reconciliation pass. `queryInvoices` has no offset/limit, so the full result is
+    /// fetched once and written back `page_size` invoices at a time.
+    /// Only invoices recorded on `deployment` are compared (plus unassigned ones for the default).
+    pub async fn run<Q: ContractQuerier + ?Sized>(
+        &self,
+        querier: &Q,
+        deployment: &str,
+        is_default: bool,
+        page_size: usize,
+    ) -> Result<ReconcileReportDto, ReconcileError> {
+        let started_at = DateTime::now();
+        let chain_invoices = querier
+            .query_invoices(QueryParamsDto::default())
+            .await
+            .map_err(|e| ReconcileError::Chain(e.to_string()))?;
+        let db_invoices: HashMap<String, Invoice> = self
+            .invoices
+            .find_all()
+            .await?
+            .into_iter()
+            .filter(|invoice| match invoice.deployment.as_deref() {
+                Some(name) => name == deployment,
+                None => is_default,
+            })
+            .map(|invoice| (invoice.invoice_number.clone(), invoice))
+            .collect();
+
+        let mut report = ReconcileReportDto {
+            deployment: deployment.to_string(),
+            started_at,
+            finished_at: started_at,
+            chain_count: chain_invoices.len(),
+            db_count: db_invoices.len(),
+            imported: 0,
+            updated: 0,
+            drifts: Vec::new(),
+        };
+
+        for page in chain_invoices.chunks(page_size.max(1)) {
+            for chain in page {
+                match db_invoices.get(&chain.invoice_number) {
+                    Some(db) => {
+                        let fields = invoice_drift(db, chain);
+                        if fields.is_empty() {
+                            continue;
+                        }
+                        if let Some(id) = db.id {
+                            self.invoices.sync_from_blockchain(id, chain).await?;
+                            report.updated += 1;
+                        }
+                        report.drifts.push(InvoiceDriftDto {
+                            invoice_number: chain.invoice_number.clone(),
+                            kind: DriftKind::FieldMismatch,
+                            fields,
+                        });
+                    }
+                    None => {
+                        // An unparseable invoice must not stop the rest of the run
+                        match self.invoices.create_from_blockchain(chain, Some(deployment)).await {
+                            Ok(_) => report.imported += 1,
+                            Err(e) => log::warn!("Failed to import chain invoice {}: {}", chain.invoice_number, e),
+                        }
+                        report.drifts.push(InvoiceDriftDto {
+                            invoice_number: chain.invoice_number.clone(),
+                            kind: DriftKind::ChainOnly,
+                            fields: Vec::new(),
+                        });
+                    }
+                }
+            }
+            tokio::task::yield_now().await;
+        }
+
+        let chain_numbers: HashSet<&str> = chain_invoices.iter().map(|invoice| invoice.invoice_number.as_str()).collect();
+        let mut db_only: Vec<&String> = db_invoices.keys().filter(|number| !chain_numbers.contains(number.as_str())).collect();
+        db_only.sort();
+        for invoice_number in db_only {
+            report.drifts.push(InvoiceDriftDto {
+                invoice_number: invoice_number.clone(),
+                kind: DriftKind::DbOnly,
+                fields: Vec::new(),
+            });
+        }
+
+        report.finished_at = DateTime::now();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::oid::ObjectId;
+
+    fn chain_invoice() -> InvoiceDataDto {
+        InvoiceDataDto {
+            invoice_number: "INV-1".to_string(),
+            payee: "0xAbC0000000000000000000000000000000000001".to_string(),
+            payer: "0x0000000000000000000000000000000000000002".to_string(),
+            amount: "1000".to_string(),
+            ipfs_hash: "Qm1".to_string(),
+            contract_hash: "0x01".to_string(),
+            timestamp: "1678886400".to_string(),
+            due_date: "1704067200".to_string(),
+            token_batch: "".to_string(),
+            is_cleared: false,
+            is_valid: true,
+        }
+    }
+
+    #[test]
+    fn invoice_drift_fields() {
+        let chain = chain_invoice();
+        let mut db = Invoice::new(
+            chain.invoice_number.clone(),
+            ObjectId::new(),
+            ObjectId::new(),
+            1000,
+            "USD".to_string(),
+            DateTime::from_millis(1704067200 * 1000),
+        );
+        db.payee = Some(chain.payee.to_lowercase());
+        db.payer = Some(chain.payer.clone());
+        db.ipfs_hash = Some(chain.ipfs_hash.clone());
+        db.contract_hash = Some(chain.contract_hash.clone());
+        db.token_batch = Some(chain.token_batch.clone());
+        db.is_cleared = Some(false);
+        db.is_valid = Some(true);
+        assert!(invoice_drift(&db, &chain).is_empty());
+
+        db.is_cleared = Some(true);
+        db.amount = 999;
+        assert_eq!(invoice_drift(&db, &chain), vec!["amount".to_string(), "is_cleared".to_string()]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Reorg re-check of recently confirmed contract writes.
+//!
+//! The outbox waits for a configurable confirmation depth, but a deeper reorg can still drop a
+//! transaction whose effect was already written to the database. `ReorgService::recheck`
+//! fetches the receipt of every `Confirmed` outbox entry mined within the last `depth` blocks
+//! again; when the transaction is gone or now reverts, the entry becomes `Reorged` and the
+//! `JobEffect` applied for it is rolled back. The write is not sent again; the entry stays
+//! visible in the outbox listing for an operator to resubmit.
+
+use mongodb::{bson::oid::ObjectId, Database};
+use thiserror::Error;
+
+use common::domain::dto::chain_job_dto::JobEffect;
+use common::domain::dto::contract_call_dto::ContractCall;
+use common::domain::entity::{InvoiceBatchStatus, TxOutboxEntry, TxOutboxStatus};
+use pharos_interact::RawTransactionSender;
+
+use crate::lifecycle::LifecycleService;
+use crate::repository::{InvoiceBatchRepository, InvoiceRepository, TxOutboxRepository};
+
+/// Actor recorded on status transitions made by a rollback
+const REORG_ACTOR: &str = "system:reorg";
+
+#[derive(Error, Debug)]
+pub enum ReorgError {
+    #[error("Chain query failed: {0}")]
+    Chain(String),
+
+    #[error("Rollback failed: {0}")]
+    Rollback(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+/// First block still re-checked when the chain head is at `head`.
+pub fn recheck_from(head: u64, depth: u64) -> u64 {
+    head.saturating_sub(depth)
+}
+
+pub struct ReorgService {
+    entries: TxOutboxRepository,
+    batches: InvoiceBatchRepository,
+    invoices: InvoiceRepository,
+    lifecycle: LifecycleService,
+}
+
+impl ReorgService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            entries: TxOutboxRepository::new(db),
+            batches: InvoiceBatchRepository::new(db),
+            invoices: InvoiceRepository::new(db),
+            lifecycle: LifecycleService::new(db),
+        }
+    }
+
+    /// Re-check the confirmed entries of `deployment` mined in the last `depth` blocks (entries
+    /// without a deployment belong to the default one). Returns how many were rolled back.
+    pub async fn recheck<S: RawTransactionSender + ?Sized>(&self, sender: &S, deployment: &str, is_default: bool, depth: u64) -> Result<usize, ReorgError> {
+        let head = sender.block_number().await.map_err(|e| ReorgError::Chain(e.to_string()))?;
+        let recent: Vec<TxOutboxEntry> = self
+            .entries
+            .find_confirmed_since(recheck_from(head, depth) as i64)
+            .await?
+            .into_iter()
+            .filter(|entry| match entry.deployment.as_deref() {
+                Some(name) => name == deployment,
+                None => is_default,
+            })
+            .collect();
+
+        let mut rolled_back = 0;
+        for entry in recent {
+            let (Some(id), Some(tx_hash)) = (entry.id, entry.tx_hash.clone()) else { continue };
+            let receipt = sender.get_receipt(&tx_hash).await.map_err(|e| ReorgError::Chain(e.to_string()))?;
+            let reason = match receipt {
+                Some(receipt) if receipt.status == Some(1.into()) => {
+                    // Still mined, possibly in another block after a shallow reorg
+                    let block = receipt.block_number.map(|block| block.as_u64() as i64);
+                    if block != entry.block_number {
+                        self.entries
+                            .mark_mined(id, TxOutboxStatus::Confirmed, &tx_hash, block, receipt.gas_used.map(|gas| gas.to_string()))
+                            .await?;
+                    }
+                    continue;
+                }
+                Some(_) => format!("Transaction {} reverted after a reorg", tx_hash),
+                None => format!("Transaction {} was dropped by a reorg", tx_hash),
+            };
+
+            log::error!("Outbox entry {} ({}) on {}: {}", id, entry.call.method_name(), deployment, reason);
+            if self.entries.mark_reorged(id, &reason).await?.matched_count == 0 {
+                continue; // Handled by another pass
+            }
+            if let Some(effect) = &entry.applied_effect {
+                if let Err(e) = self.rollback(&entry, effect, &tx_hash).await {
+                    log::error!("Failed to roll back effect of outbox entry {}: {}", id, e);
+                    self.entries.record_error(id, &format!("{}; rollback failed: {}", reason, e)).await?;
+                    continue;
+                }
+            }
+            rolled_back += 1;
+        }
+        Ok(rolled_back)
+    }
+
+    // Undo what `ChainJobRunner::apply_effect` wrote for the entry
+    async fn rollback(&self, entry: &TxOutboxEntry, effect: &JobEffect, tx_hash: &str) -> Result<(), ReorgError> {
+        match effect {
+            JobEffect::None => Ok(()),
+            JobEffect::ImportInvoices => {
+                let ContractCall::BatchCreateInvoices { invoices } = &entry.call else {
+                    return Err(ReorgError::Rollback("ImportInvoices requires a batchCreateInvoices call".to_string()));
+                };
+                for data in invoices {
+                    let Some(invoice) = self.invoices.find_by_invoice_number(&data.invoice_number).await? else { continue };
+                    // Only invoices this write imported that were not packaged since
+                    if invoice.batch_id.is_none() && invoice.created_at >= entry.created_at {
+                        if let Some(id) = invoice.id {
+                            self.invoices.delete(id).await?;
+                        }
+                    } else {
+                        log::warn!("Invoice {} is {:?} and was kept after its creation was reorged out", data.invoice_number, invoice.status);
+                    }
+                }
+                Ok(())
+            }
+            JobEffect::RecordChainBatch { batch_id, .. } => {
+                let batch_id = parse_id(batch_id)?;
+                self.batches.clear_chain_creation(batch_id, tx_hash).await?;
+                Ok(())
+            }
+            JobEffect::ConfirmBatchIssue { batch_id, .. } => {
+                let batch_id = parse_id(batch_id)?;
+                self.batches.clear_issuance(batch_id, tx_hash).await?;
+                self.lifecycle
+                    .rollback_batch(batch_id, InvoiceBatchStatus::Issued, InvoiceBatchStatus::Packaging, REORG_ACTOR)
+                    .await
+                    .map_err(|e| ReorgError::Rollback(e.to_string()))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_id(id: &str) -> Result<ObjectId, ReorgError> {
+    ObjectId::parse_str(id).map_err(|_| ReorgError::Rollback(format!("Invalid id in job effect: {}", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recheck_window_stops_at_genesis() {
+        assert_eq!(recheck_from(1000, 64), 936);
+        assert_eq!(recheck_from(10, 64), 0);
+    }
+}
@@ -0,0 +1,155 @@
+//! Repayment recording for issued invoice batches.
+//!
+//! `RepaymentService` stores a repayment that the caller has already verified on chain,
+//! sets the batch's `repaid_amount` to the sum of its repayments and drives the batch through
+//! `Repaying` → `Settled` via `LifecycleService`, which marks member invoices `Repaid`.
+//!
+//! Applying the repayments to the batch is safe to repeat, so registering a transaction again
+//! after a failed update completes it instead of being rejected as a duplicate.
+
+use mongodb::{
+    bson::{oid::ObjectId, DateTime, Decimal128},
+    Database,
+};
+use thiserror::Error;
+
+use common::domain::entity::{InvoiceBatch, InvoiceBatchStatus, Repayment};
+use common::utils::decimal::{sum_decimals, to_big_decimal};
+
+use crate::db::is_duplicate_key;
+use crate::lifecycle::{LifecycleError, LifecycleService};
+use crate::repository::{InvoiceBatchRepository, RepaymentRepository};
+
+#[derive(Error, Debug)]
+pub enum RepaymentError {
+    #[error("Batch not found: {0}")]
+    BatchNotFound(ObjectId),
+
+    #[error("Batch is {0:?} and does not accept repayments")]
+    NotRepayable(InvoiceBatchStatus),
+
+    #[error("Transaction {0} is already registered as a repayment")]
+    DuplicateTransaction(String),
+
+    #[error("Repaid amount of batch {0} is out of range")]
+    AmountOutOfRange(ObjectId),
+
+    #[error(transparent)]
+    Lifecycle(#[from] LifecycleError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+/// Repayments are accepted once the batch is issued and until it is settled or defaulted.
+pub fn is_repayable(status: &InvoiceBatchStatus) -> bool {
+    matches!(status, InvoiceBatchStatus::Issued | InvoiceBatchStatus::Trading | InvoiceBatchStatus::Repaying)
+}
+
+pub struct RepaymentService {
+    batches: InvoiceBatchRepository,
+    repayments: RepaymentRepository,
+    lifecycle: LifecycleService,
+}
+
+impl RepaymentService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            batches: InvoiceBatchRepository::new(db),
+            repayments: RepaymentRepository::new(db),
+            lifecycle: LifecycleService::new(db),
+        }
+    }
+
+    /// Record a verified repayment and advance the batch status. Returns the updated batch.
+    pub async fn record(
+        &self,
+        batch_id: ObjectId,
+        debtor_id: ObjectId,
+        amount: Decimal128,
+        transaction_hash: &str,
+        repayment_timestamp: DateTime,
+        actor: &str,
+    ) -> Result<InvoiceBatch, RepaymentError> {
+        let batch = self.find_batch(batch_id).await?;
+        if let Some(existing) = self.repayments.find_by_transaction_hash(transaction_hash).await? {
+            // Finish a registration whose batch update failed; a fully applied one is a duplicate
+            if existing.batch_id == batch_id {
+                let (batch, changed) = self.apply_repayments(batch_id, batch, actor).await?;
+                if changed {
+                    log::info!("Completed repayment {} of batch {}", transaction_hash, batch_id);
+                    return Ok(batch);
+                }
+            }
+            return Err(RepaymentError::DuplicateTransaction(transaction_hash.to_string()));
+        }
+        if !is_repayable(&batch.status) {
+            return Err(RepaymentError::NotRepayable(batch.status));
+        }
+
+        let repayment = Repayment::new(
+            batch_id,
+            debtor_id,
+            amount,
+            batch.accepted_currency.clone(),
+            transaction_hash.to_string(),
+            repayment_timestamp,
+        );
+        if let Err(e) = self.repayments.create(repayment).await {
+            // The unique `transaction_hash` index caught a concurrent registration
+            return Err(if is_duplicate_key(&e) {
+                RepaymentError::DuplicateTransaction(transaction_hash.to_string())
+            } else {
+                RepaymentError::Database(e)
+            });
+        }
+        let (batch, _) = self.apply_repayments(batch_id, batch, actor).await?;
+        Ok(batch)
+    }
+
+    pub async fn list_by_batch(&self, batch_id: ObjectId) -> Result<Vec<Repayment>, RepaymentError> {
+        Ok(self.repayments.find_by_batch(batch_id).await?)
+    }
+
+    // Bring the batch's repaid amount and status in line with its stored repayments. Safe to
+    // repeat; returns the updated batch and whether anything was missing.
+    async fn apply_repayments(&self, batch_id: ObjectId, batch: InvoiceBatch, actor: &str) -> Result<(InvoiceBatch, bool), RepaymentError> {
+        let repayments = self.repayments.find_by_batch(batch_id).await?;
+        let amounts: Vec<Decimal128> = repayments.iter().map(|repayment| repayment.amount).collect();
+        let repaid = sum_decimals(&amounts).ok_or(RepaymentError::AmountOutOfRange(batch_id))?;
+
+        let mut changed = false;
+        if batch.repaid_amount.as_ref().and_then(to_big_decimal) < to_big_decimal(&repaid) {
+            self.batches.raise_repaid_amount(batch_id, repaid).await?;
+            changed = true;
+        }
+        if matches!(batch.status, InvoiceBatchStatus::Issued | InvoiceBatchStatus::Trading) {
+            self.lifecycle.transition_batch(batch_id, InvoiceBatchStatus::Repaying, actor).await?;
+            changed = true;
+        }
+        let batch = self.find_batch(batch_id).await?;
+        if batch.status == InvoiceBatchStatus::Repaying && batch.is_fully_repaid() {
+            return Ok((self.lifecycle.transition_batch(batch_id, InvoiceBatchStatus::Settled, actor).await?, true));
+        }
+        Ok((batch, changed))
+    }
+
+    async fn find_batch(&self, batch_id: ObjectId) -> Result<InvoiceBatch, RepaymentError> {
+        self.batches.find_by_id(batch_id).await?.ok_or(RepaymentError::BatchNotFound(batch_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repayable_statuses() {
+        assert!(is_repayable(&InvoiceBatchStatus::Issued));
+        assert!(is_repayable(&InvoiceBatchStatus::Trading));
+        assert!(is_repayable(&InvoiceBatchStatus::Repaying));
+        assert!(!is_repayable(&InvoiceBatchStatus::Packaging));
+        assert!(!is_repayable(&InvoiceBatchStatus::Settled));
+        assert!(!is_repayable(&InvoiceBatchStatus::Defaulted));
+    }
+}
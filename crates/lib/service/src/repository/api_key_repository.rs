@@ -0,0 +1,66 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime},
+    options::FindOptions,
+    Collection, Database,
+};
+
+use common::domain::entity::ApiKey;
+
+pub struct ApiKeyRepository {
+    collection: Collection<ApiKey>,
+}
+
+impl ApiKeyRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<ApiKey>("api_keys"),
+        }
+    }
+
+    // Insert a key; the unique `key_id` index rejects collisions
+    pub async fn create(&self, key: ApiKey) -> Result<ApiKey, mongodb::error::Error> {
+        let result = self.collection.insert_one(&key).await?;
+
+        let mut created = key;
+        created.id = result.inserted_id.as_object_id();
+
+        Ok(created)
+    }
+
+    pub async fn find_by_key_id(&self, key_id: &str) -> Result<Option<ApiKey>, mongodb::error::Error> {
+        self.collection.find_one(doc! { "key_id": key_id }).await
+    }
+
+    // Keys of an enterprise, newest first, revoked ones included
+    pub async fn find_by_enterprise(&self, enterprise_id: ObjectId) -> Result<Vec<ApiKey>, mongodb::error::Error> {
+        let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let cursor = self.collection.find(doc! { "enterprise_id": enterprise_id }).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Replace the secret of an active key of the enterprise; None when there is no such key
+    pub async fn rotate(&self, enterprise_id: ObjectId, key_id: &str, salt: &str, secret_hash: &str) -> Result<Option<ApiKey>, mongodb::error::Error> {
+        let filter = doc! { "key_id": key_id, "enterprise_id": enterprise_id, "revoked_at": null };
+        let update = doc! { "$set": { "salt": salt, "secret_hash": secret_hash, "rotated_at": DateTime::now() } };
+        self.collection
+            .find_one_and_update(filter, update)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .await
+    }
+
+    // Revoke an active key of the enterprise; false when there is no such key
+    pub async fn revoke(&self, enterprise_id: ObjectId, key_id: &str) -> Result<bool, mongodb::error::Error> {
+        let filter = doc! { "key_id": key_id, "enterprise_id": enterprise_id, "revoked_at": null };
+        let update = doc! { "$set": { "revoked_at": DateTime::now() } };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn touch(&self, key_id: &str) -> Result<(), mongodb::error::Error> {
+        self.collection
+            .update_one(doc! { "key_id": key_id }, doc! { "$set": { "last_used_at": DateTime::now() } })
+            .await?;
+        Ok(())
+    }
+}
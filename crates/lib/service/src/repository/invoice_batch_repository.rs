@@ -0,0 +1,196 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Bson, DateTime, Decimal128},
+    options::FindOptions,
+    results::UpdateResult,
+    Collection, Database,
+};
+
+use common::domain::entity::{InvoiceBatch, InvoiceBatchStatus};
+
+pub struct InvoiceBatchRepository {
+    collection: Collection<InvoiceBatch>,
+}
+
+impl InvoiceBatchRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<InvoiceBatch>("invoice_batches"),
+        }
+    }
+
+    // Find batch by ID
+    pub async fn find_by_id(&self, id: ObjectId) -> Result<Option<InvoiceBatch>, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        self.collection.find_one(filter).await
+    }
+
+    // Find all batches, newest first
+    pub async fn find_all(&self) -> Result<Vec<InvoiceBatch>, mongodb::error::Error> {
+        let filter = doc! {};
+        let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Find batches packaged by a creditor enterprise
+    pub async fn find_by_creditor(&self, creditor_id: ObjectId) -> Result<Vec<InvoiceBatch>, mongodb::error::Error> {
+        let filter = doc! { "creditor_id": creditor_id };
+        let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Find batches an enterprise takes part in, as creditor or debtor
+    pub async fn find_by_enterprise(&self, enterprise_id: ObjectId) -> Result<Vec<InvoiceBatch>, mongodb::error::Error> {
+        let filter = doc! { "$or": [{ "creditor_id": enterprise_id }, { "debtor_id": enterprise_id }] };
+        let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Create a new batch in `Packaging` status with a zero total
+    pub async fn create(
+        &self,
+        creditor_id: ObjectId,
+        debtor_id: ObjectId,
+        accepted_currency: String,
+        interest_rate_apy: Decimal128,
+        default_interest_rate_apy: Decimal128,
+        maturity_date: DateTime,
+    ) -> Result<InvoiceBatch, mongodb::error::Error> {
+        let zero = "0".parse::<Decimal128>().map_err(|e| mongodb::error::Error::custom(format!("Failed to build zero amount: {}", e)))?;
+        let batch = InvoiceBatch::new(
+            creditor_id,
+            debtor_id,
+            zero,
+            accepted_currency,
+            interest_rate_apy,
+            default_interest_rate_apy,
+            maturity_date,
+        );
+
+        let result = self.collection.insert_one(&batch).await?;
+
+        let mut created_batch = batch;
+        created_batch.id = result.inserted_id.as_object_id();
+
+        Ok(created_batch)
+    }
+
+    // Overwrite the batch total (recomputed from its member invoices by the caller)
+    pub async fn update_total_amount(&self, id: ObjectId, total_amount: Decimal128) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! { "$set": { "total_amount": total_amount, "updated_at": DateTime::now() } };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Update batch status
+    pub async fn update_status(&self, id: ObjectId, status: InvoiceBatchStatus) -> Result<UpdateResult, mongodb::error::Error> {
+        let now = DateTime::now();
+        let filter = doc! { "_id": id };
+        let status_bson = bson::to_bson(&status).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
+        )?;
+        let update = doc! { "$set": { "status": status_bson, "updated_at": now } };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Move the batch to `to` only if it is still in `from`; matched_count is 0 when another writer won
+    pub async fn compare_and_set_status(
+        &self,
+        id: ObjectId,
+        from: InvoiceBatchStatus,
+        to: InvoiceBatchStatus,
+    ) -> Result<UpdateResult, mongodb::error::Error> {
+        let now = DateTime::now();
+        let from_bson = bson::to_bson(&from).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
+        )?;
+        let to_bson = bson::to_bson(&to).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
+        )?;
+        let filter = doc! { "_id": id, "status": from_bson };
+        let update = doc! { "$set": { "status": to_bson, "updated_at": now } };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Record the on-chain token batch created for this DB batch
+    pub async fn record_chain_creation(
+        &self,
+        id: ObjectId,
+        chain_batch_id: &str,
+        stable_token_address: &str,
+        tx_hash: &str,
+        deployment: &str,
+    ) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            "$set": {
+                "chain_batch_id": chain_batch_id,
+                "stable_token_address": stable_token_address,
+                "create_tx_hash": tx_hash,
+                "deployment": deployment,
+                "updated_at": DateTime::now()
+            }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Record the confirmed issuance of the RBT token for this batch; the address is None when no separate token exists
+    pub async fn record_issuance(&self, id: ObjectId, rbt_token_address: Option<&str>, tx_hash: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            "$set": {
+                "rbt_token_address": rbt_token_address,
+                "issue_tx_hash": tx_hash,
+                "issuance_date": DateTime::now(),
+                "updated_at": DateTime::now()
+            }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Forget the on-chain token batch recorded by `tx_hash`, e.g. after a reorg dropped it
+    pub async fn clear_chain_creation(&self, id: ObjectId, tx_hash: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id, "create_tx_hash": tx_hash };
+        let update = doc! {
+            "$set": {
+                "chain_batch_id": Bson::Null,
+                "stable_token_address": Bson::Null,
+                "create_tx_hash": Bson::Null,
+                "deployment": Bson::Null,
+                "updated_at": DateTime::now()
+            }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Forget the issuance recorded by `tx_hash`, e.g. after a reorg dropped it
+    pub async fn clear_issuance(&self, id: ObjectId, tx_hash: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id, "issue_tx_hash": tx_hash };
+        let update = doc! {
+            "$set": { "rbt_token_address": Bson::Null, "issue_tx_hash": Bson::Null, "updated_at": DateTime::now() }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Raise the batch's repaid amount to the sum of its repayments; never lowers it, so a
+    // stale total written by a concurrent registration cannot undo a newer one
+    pub async fn raise_repaid_amount(&self, id: ObjectId, repaid_amount: Decimal128) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            "$max": { "repaid_amount": repaid_amount },
+            "$set": { "updated_at": DateTime::now() }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+}
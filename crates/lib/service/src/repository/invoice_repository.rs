@@ -7,12 +7,13 @@ use mongodb::{
 };
 use serde::Serialize;
 
-use common::domain::entity::{Invoice, InvoiceStatus};
+use common::domain::entity::{Enterprise, Invoice, InvoiceStatus};
 use std::str::FromStr;
 use common::domain::dto::invoice_dto::InvoiceDataDto;
 
 pub struct InvoiceRepository {
     collection: Collection<Invoice>,
+    enterprises: Collection<Enterprise>,
 }
 
 /// Fields that can be updated for an Invoice.
@@ -27,11 +28,10 @@ pub struct UpdateInvoiceData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub due_date: Option<DateTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub status: Option<InvoiceStatus>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub ipfs_hash: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub batch_id: Option<ObjectId>,
+    // Status only moves through `LifecycleService` transitions, never through a generic update.
     // Blockchain related fields are generally not updated directly via this struct
     // They are typically set when creating from blockchain or via specific sync logic.
     // Add them here only if direct update through a general update endpoint is needed.
@@ -48,9 +48,21 @@ impl InvoiceRepository {
     pub fn new(db: &Database) -> Self {
         Self {
             collection: db.collection::<Invoice>("invoices"),
+            enterprises: db.collection::<Enterprise>("enterprises"),
         }
     }
 
+    // Resolve the internal enterprise id registered for a wallet address (case-insensitive)
+    async fn find_enterprise_id_by_wallet(&self, wallet_address: &str) -> Result<Option<ObjectId>, mongodb::error::Error> {
+        let filter = doc! {
+            "wallet_address": bson::Regex {
+                pattern: format!("^{}$", regex::escape(wallet_address)),
+                options: "i".to_string()
+            }
+        };
+        Ok(self.enterprises.find_one(filter).await?.and_then(|enterprise| enterprise.id))
+    }
+
     // Find invoice by ID
     pub async fn find_by_id(&self, id: ObjectId) -> Result<Option<Invoice>, mongodb::error::Error> {
         let filter = doc! { "_id": id };
@@ -88,13 +100,34 @@ impl InvoiceRepository {
         Ok(invoices)
     }
 
-    // Create new invoice from blockchain data
+    // Create new invoice from blockchain data; it always starts `Pending` whatever the chain flags say,
+    // verification and repayment are recorded by `LifecycleService` transitions
     pub async fn create_from_blockchain(
         &self,
-        data: &InvoiceDataDto
+        data: &InvoiceDataDto,
+        deployment: Option<&str>,
+    ) -> Result<Invoice, mongodb::error::Error> {
+        let invoice = self.build_from_blockchain(data, deployment).await?;
+
+        // Insert the invoice and get its ID
+        let result = self.collection.insert_one(&invoice).await?;
+        
+        let mut created_invoice = invoice;
+        created_invoice.id = result.inserted_id.as_object_id();
+        
+        Ok(created_invoice)
+    }
+
+    // Map blockchain data to an unsaved `Pending` invoice
+    pub async fn build_from_blockchain(
+        &self,
+        data: &InvoiceDataDto,
+        deployment: Option<&str>,
     ) -> Result<Invoice, mongodb::error::Error> {
         // Parse the amount from String to u64
-        let amount:u64 = data.amount.parse().unwrap();
+        let amount: u64 = data.amount.parse().map_err(|e| {
+            mongodb::error::Error::custom(format!("Invoice {} amount {} does not fit u64: {}", data.invoice_number, data.amount, e))
+        })?;
         
         // Attempt to parse due_date from string (assuming it's a Unix timestamp string)
         let due_date_timestamp = i64::from_str(&data.due_date).unwrap_or_else(|_| chrono::Utc::now().timestamp());
@@ -104,10 +137,10 @@ impl InvoiceRepository {
         let blockchain_timestamp_val = i64::from_str(&data.timestamp).unwrap_or_else(|_| chrono::Utc::now().timestamp());
         let blockchain_datetime = DateTime::from_millis(blockchain_timestamp_val * 1000);
         
-        // Create default ObjectIds for creditor/debtor for now
-        // TODO: Implement lookup or creation logic based on payee/payer addresses
-        let creditor_id = ObjectId::new(); 
-        let debtor_id = ObjectId::new();
+        // Link creditor/debtor to the enterprises registered with the payee/payer wallets.
+        // Unregistered wallets still get a placeholder id so the invoice can be stored.
+        let creditor_id = self.find_enterprise_id_by_wallet(&data.payee).await?.unwrap_or_else(ObjectId::new);
+        let debtor_id = self.find_enterprise_id_by_wallet(&data.payer).await?.unwrap_or_else(ObjectId::new);
         
         // Create a new invoice instance
         let mut invoice = Invoice::new(
@@ -128,22 +161,24 @@ impl InvoiceRepository {
         invoice.token_batch = Some(data.token_batch.clone());
         invoice.is_cleared = Some(data.is_cleared);
         invoice.is_valid = Some(data.is_valid);
-        
-        // Optionally update status based on blockchain data
-        if data.is_valid {
-            invoice.status = InvoiceStatus::Verified;
-        }
-        if data.is_cleared {
-            invoice.status = InvoiceStatus::Repaid;
+        invoice.deployment = deployment.map(str::to_string);
+
+        Ok(invoice)
+    }
+
+    // Insert invoices all or nothing: when one fails (e.g. a duplicate invoice number),
+    // the ones already written are deleted again before the error is returned
+    pub async fn insert_all(&self, mut invoices: Vec<Invoice>) -> Result<Vec<Invoice>, mongodb::error::Error> {
+        let ids: Vec<ObjectId> = invoices.iter_mut().map(|invoice| *invoice.id.get_or_insert_with(ObjectId::new)).collect();
+
+        if let Err(e) = self.collection.insert_many(&invoices).await {
+            if let Err(cleanup) = self.collection.delete_many(doc! { "_id": { "$in": &ids } }).await {
+                log::error!("Failed to roll back partially inserted invoices {:?}: {}", ids, cleanup);
+            }
+            return Err(e);
         }
 
-        // Insert the invoice and get its ID
-        let result = self.collection.insert_one(&invoice).await?;
-        
-        let mut created_invoice = invoice;
-        created_invoice.id = result.inserted_id.as_object_id();
-        
-        Ok(created_invoice)
+        Ok(invoices)
     }
 
     // Create new invoice (standard method)
@@ -176,6 +211,25 @@ impl InvoiceRepository {
     }
 
 
+    // Refresh the blockchain-mirrored fields of an invoice from chain data
+    pub async fn sync_from_blockchain(&self, id: ObjectId, data: &InvoiceDataDto) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            "$set": {
+                "payee": &data.payee,
+                "payer": &data.payer,
+                "ipfs_hash": &data.ipfs_hash,
+                "contract_hash": &data.contract_hash,
+                "token_batch": &data.token_batch,
+                "is_cleared": data.is_cleared,
+                "is_valid": data.is_valid,
+                "updated_at": DateTime::now()
+            }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
     // Generic Update invoice data
     pub async fn update(&self, id: ObjectId, data: UpdateInvoiceData) -> Result<UpdateResult, mongodb::error::Error> {
         let filter = doc! { "_id": id };
@@ -229,23 +283,13 @@ impl InvoiceRepository {
         Ok(invoices)
     }
 
-    // Update invoice status
-    pub async fn update_status(&self, id: ObjectId, status: InvoiceStatus) -> Result<UpdateResult, mongodb::error::Error> {
+    // Add a `Verified` invoice that is not yet packaged to a batch; matched_count is 0 otherwise
+    pub async fn add_to_batch(&self, id: ObjectId, batch_id: ObjectId) -> Result<UpdateResult, mongodb::error::Error> {
         let now = DateTime::now();
-        let filter = doc! { "_id": id };
-        // Ensure status is serialized correctly to BSON
-        let status_bson = bson::to_bson(&status).map_err(|e| 
+        let verified_bson = bson::to_bson(&InvoiceStatus::Verified).map_err(|e| 
             mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
         )?;
-        let update = doc! { "$set": { "status": status_bson, "updated_at": now } };
-        
-        self.collection.update_one(filter, update).await
-    }
-
-    // Add invoice to batch
-    pub async fn add_to_batch(&self, id: ObjectId, batch_id: ObjectId) -> Result<UpdateResult, mongodb::error::Error> {
-        let now = DateTime::now();
-        let filter = doc! { "_id": id };
+        let filter = doc! { "_id": id, "status": verified_bson, "batch_id": null };
         // Ensure status is serialized correctly to BSON
         let status_bson = bson::to_bson(&InvoiceStatus::Packaged).map_err(|e| 
             mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
@@ -260,4 +304,48 @@ impl InvoiceRepository {
         
         self.collection.update_one(filter, update).await
     }
-} 
\ No newline at end of file
+
+    // Move the invoice to `to` only if it is still in `from`; matched_count is 0 when another writer won
+    pub async fn compare_and_set_status(&self, id: ObjectId, from: InvoiceStatus, to: InvoiceStatus) -> Result<UpdateResult, mongodb::error::Error> {
+        let now = DateTime::now();
+        let from_bson = bson::to_bson(&from).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
+        )?;
+        let to_bson = bson::to_bson(&to).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
+        )?;
+        let filter = doc! { "_id": id, "status": from_bson };
+        let update = doc! { "$set": { "status": to_bson, "updated_at": now } };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Find invoices packaged into a batch
+    pub async fn find_by_batch(&self, batch_id: ObjectId) -> Result<Vec<Invoice>, mongodb::error::Error> {
+        let filter = doc! { "batch_id": batch_id };
+        let find_options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Take a `Packaged` invoice out of `batch_id` and put it back to `Verified`; matched_count is 0 otherwise
+    pub async fn remove_from_batch(&self, id: ObjectId, batch_id: ObjectId) -> Result<UpdateResult, mongodb::error::Error> {
+        let now = DateTime::now();
+        let packaged_bson = bson::to_bson(&InvoiceStatus::Packaged).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
+        )?;
+        let verified_bson = bson::to_bson(&InvoiceStatus::Verified).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize status: {}", e))
+        )?;
+        let filter = doc! { "_id": id, "status": packaged_bson, "batch_id": batch_id };
+        let update = doc! {
+            "$set": { "status": verified_bson, "updated_at": now },
+            "$unset": { "batch_id": "" }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+}
@@ -1,8 +1,24 @@
 pub mod user_repository;
 pub mod enterprise_repository;
 pub mod invoice_repository;
+pub mod invoice_batch_repository;
+pub mod status_transition_repository;
+pub mod rbt_holding_repository;
+pub mod repayment_repository;
+pub mod settlement_nft_repository;
+pub mod tx_outbox_repository;
+pub mod api_key_repository;
+pub mod share_purchase_repository;
 
 // Re-export for easier access
 pub use user_repository::UserRepository;
 pub use enterprise_repository::EnterpriseRepository;
-pub use invoice_repository::InvoiceRepository; 
\ No newline at end of file
+pub use invoice_repository::InvoiceRepository;
+pub use invoice_batch_repository::InvoiceBatchRepository;
+pub use status_transition_repository::StatusTransitionRepository;
+pub use rbt_holding_repository::RbtHoldingRepository;
+pub use repayment_repository::RepaymentRepository;
+pub use settlement_nft_repository::SettlementNftRepository;
+pub use tx_outbox_repository::TxOutboxRepository;
+pub use api_key_repository::ApiKeyRepository;
+pub use share_purchase_repository::SharePurchaseRepository;
@@ -0,0 +1,55 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime, Decimal128},
+    options::{FindOptions, UpdateOptions},
+    results::UpdateResult,
+    Collection, Database,
+};
+
+use common::domain::entity::RbtHolding;
+
+pub struct RbtHoldingRepository {
+    collection: Collection<RbtHolding>,
+}
+
+impl RbtHoldingRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<RbtHolding>("rbt_holdings"),
+        }
+    }
+
+    // Find all holdings of a user, most recently updated first
+    pub async fn find_by_user(&self, user_id: ObjectId) -> Result<Vec<RbtHolding>, mongodb::error::Error> {
+        let filter = doc! { "user_id": user_id };
+        let find_options = FindOptions::builder().sort(doc! { "updated_at": -1 }).build();
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Find all holders of a batch
+    pub async fn find_by_batch(&self, batch_id: ObjectId) -> Result<Vec<RbtHolding>, mongodb::error::Error> {
+        let filter = doc! { "batch_id": batch_id };
+        let cursor = self.collection.find(filter).await?;
+        cursor.try_collect().await
+    }
+
+    // Find the holding of a user in one batch
+    pub async fn find_by_user_and_batch(&self, user_id: ObjectId, batch_id: ObjectId) -> Result<Option<RbtHolding>, mongodb::error::Error> {
+        let filter = doc! { "user_id": user_id, "batch_id": batch_id };
+        self.collection.find_one(filter).await
+    }
+
+    // Raise (or create) the holding of a user in a batch to `amount`; never lowers it, so a
+    // stale total written by a concurrent registration cannot undo a newer one
+    pub async fn raise_amount(&self, user_id: ObjectId, batch_id: ObjectId, amount: Decimal128) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "user_id": user_id, "batch_id": batch_id };
+        let update = doc! {
+            "$max": { "amount": amount },
+            "$set": { "updated_at": DateTime::now() }
+        };
+        let options = UpdateOptions::builder().upsert(true).build();
+
+        self.collection.update_one(filter, update).with_options(options).await
+    }
+}
@@ -0,0 +1,44 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::FindOptions,
+    Collection, Database,
+};
+
+use common::domain::entity::Repayment;
+
+pub struct RepaymentRepository {
+    collection: Collection<Repayment>,
+}
+
+impl RepaymentRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<Repayment>("repayments"),
+        }
+    }
+
+    // Find all repayments of a batch, oldest first
+    pub async fn find_by_batch(&self, batch_id: ObjectId) -> Result<Vec<Repayment>, mongodb::error::Error> {
+        let filter = doc! { "batch_id": batch_id };
+        let find_options = FindOptions::builder().sort(doc! { "repayment_timestamp": 1 }).build();
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Find a repayment by its on-chain transaction hash
+    pub async fn find_by_transaction_hash(&self, transaction_hash: &str) -> Result<Option<Repayment>, mongodb::error::Error> {
+        let filter = doc! { "transaction_hash": transaction_hash };
+        self.collection.find_one(filter).await
+    }
+
+    // Insert a repayment; the unique `transaction_hash` index rejects a hash registered twice
+    pub async fn create(&self, repayment: Repayment) -> Result<Repayment, mongodb::error::Error> {
+        let result = self.collection.insert_one(&repayment).await?;
+
+        let mut created = repayment;
+        created.id = result.inserted_id.as_object_id();
+
+        Ok(created)
+    }
+}
@@ -0,0 +1,34 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    Collection, Database,
+};
+
+use common::domain::entity::SettlementNft;
+
+pub struct SettlementNftRepository {
+    collection: Collection<SettlementNft>,
+}
+
+impl SettlementNftRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<SettlementNft>("settlement_nfts"),
+        }
+    }
+
+    // Find the settlement record of a batch
+    pub async fn find_by_batch(&self, batch_id: ObjectId) -> Result<Option<SettlementNft>, mongodb::error::Error> {
+        let filter = doc! { "batch_id": batch_id };
+        self.collection.find_one(filter).await
+    }
+
+    // Insert a settlement record; the unique `batch_id` index allows one per batch
+    pub async fn create(&self, settlement: SettlementNft) -> Result<SettlementNft, mongodb::error::Error> {
+        let result = self.collection.insert_one(&settlement).await?;
+
+        let mut created = settlement;
+        created.id = result.inserted_id.as_object_id();
+
+        Ok(created)
+    }
+}
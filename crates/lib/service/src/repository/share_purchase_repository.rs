@@ -0,0 +1,42 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    Collection, Database,
+};
+
+use common::domain::entity::SharePurchase;
+
+pub struct SharePurchaseRepository {
+    collection: Collection<SharePurchase>,
+}
+
+impl SharePurchaseRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<SharePurchase>("share_purchases"),
+        }
+    }
+
+    // Find a purchase by its on-chain transaction hash
+    pub async fn find_by_transaction_hash(&self, transaction_hash: &str) -> Result<Option<SharePurchase>, mongodb::error::Error> {
+        let filter = doc! { "transaction_hash": transaction_hash };
+        self.collection.find_one(filter).await
+    }
+
+    // Find all purchases of a user in one batch
+    pub async fn find_by_user_and_batch(&self, user_id: ObjectId, batch_id: ObjectId) -> Result<Vec<SharePurchase>, mongodb::error::Error> {
+        let filter = doc! { "user_id": user_id, "batch_id": batch_id };
+        let cursor = self.collection.find(filter).await?;
+        cursor.try_collect().await
+    }
+
+    // Insert a purchase; the unique `transaction_hash` index rejects a hash registered twice
+    pub async fn create(&self, purchase: SharePurchase) -> Result<SharePurchase, mongodb::error::Error> {
+        let result = self.collection.insert_one(&purchase).await?;
+
+        let mut created = purchase;
+        created.id = result.inserted_id.as_object_id();
+
+        Ok(created)
+    }
+}
@@ -0,0 +1,42 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId},
+    options::FindOptions,
+    Collection, Database,
+};
+
+use common::domain::entity::{StatusTransition, TransitionEntity};
+
+pub struct StatusTransitionRepository {
+    collection: Collection<StatusTransition>,
+}
+
+impl StatusTransitionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<StatusTransition>("status_transitions"),
+        }
+    }
+
+    // Append a transition to the audit log
+    pub async fn record(&self, transition: StatusTransition) -> Result<StatusTransition, mongodb::error::Error> {
+        let result = self.collection.insert_one(&transition).await?;
+
+        let mut recorded = transition;
+        recorded.id = result.inserted_id.as_object_id();
+
+        Ok(recorded)
+    }
+
+    // Full history of one invoice or batch, oldest first
+    pub async fn find_by_entity(&self, entity_type: TransitionEntity, entity_id: ObjectId) -> Result<Vec<StatusTransition>, mongodb::error::Error> {
+        let entity_type_bson = bson::to_bson(&entity_type).map_err(|e|
+            mongodb::error::Error::custom(format!("Failed to serialize entity type: {}", e))
+        )?;
+        let filter = doc! { "entity_type": entity_type_bson, "entity_id": entity_id };
+        let find_options = FindOptions::builder().sort(doc! { "created_at": 1 }).build();
+
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+}
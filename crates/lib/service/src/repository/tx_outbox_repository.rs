@@ -0,0 +1,164 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Bson, DateTime},
+    options::FindOptions,
+    results::UpdateResult,
+    Collection, Database,
+};
+
+use common::domain::dto::chain_job_dto::JobEffect;
+use common::domain::entity::{TxOutboxEntry, TxOutboxStatus};
+
+pub struct TxOutboxRepository {
+    collection: Collection<TxOutboxEntry>,
+}
+
+fn status_bson(status: &TxOutboxStatus) -> Result<Bson, mongodb::error::Error> {
+    bson::to_bson(status).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))
+}
+
+impl TxOutboxRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<TxOutboxEntry>("tx_outbox"),
+        }
+    }
+
+    // Find outbox entry by ID
+    pub async fn find_by_id(&self, id: ObjectId) -> Result<Option<TxOutboxEntry>, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        self.collection.find_one(filter).await
+    }
+
+    // Find entries that have not reached a receipt or a final failure, oldest first
+    pub async fn find_open(&self) -> Result<Vec<TxOutboxEntry>, mongodb::error::Error> {
+        let open = [TxOutboxStatus::Pending, TxOutboxStatus::Signed, TxOutboxStatus::Submitted]
+            .iter()
+            .map(status_bson)
+            .collect::<Result<Vec<_>, _>>()?;
+        let filter = doc! { "status": { "$in": open } };
+        let find_options = FindOptions::builder().sort(doc! { "created_at": 1 }).build();
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Find `Confirmed` entries mined at or after `min_block`, oldest first
+    pub async fn find_confirmed_since(&self, min_block: i64) -> Result<Vec<TxOutboxEntry>, mongodb::error::Error> {
+        let filter = doc! { "status": status_bson(&TxOutboxStatus::Confirmed)?, "block_number": { "$gte": min_block } };
+        let find_options = FindOptions::builder().sort(doc! { "block_number": 1 }).build();
+        let cursor = self.collection.find(filter).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Find the most recent entries, newest first
+    pub async fn find_recent(&self, limit: i64) -> Result<Vec<TxOutboxEntry>, mongodb::error::Error> {
+        let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).limit(limit).build();
+        let cursor = self.collection.find(doc! {}).with_options(find_options).await?;
+        cursor.try_collect().await
+    }
+
+    // Insert a new `Pending` entry
+    pub async fn create(&self, entry: TxOutboxEntry) -> Result<TxOutboxEntry, mongodb::error::Error> {
+        let result = self.collection.insert_one(&entry).await?;
+
+        let mut created = entry;
+        created.id = result.inserted_id.as_object_id();
+
+        Ok(created)
+    }
+
+    // Persist the signed transaction; only a `Pending` entry can be signed
+    pub async fn mark_signed(&self, id: ObjectId, sender: &str, tx_hash: &str, nonce: i64, raw_tx: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id, "status": status_bson(&TxOutboxStatus::Pending)? };
+        let update = doc! {
+            "$set": {
+                "status": status_bson(&TxOutboxStatus::Signed)?,
+                "sender": sender,
+                "tx_hash": tx_hash,
+                "nonce": nonce,
+                "raw_tx": raw_tx,
+                "updated_at": DateTime::now()
+            }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Record a successful broadcast
+    pub async fn mark_submitted(&self, id: ObjectId) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            "$set": { "status": status_bson(&TxOutboxStatus::Submitted)?, "error": Bson::Null, "updated_at": DateTime::now() },
+            "$inc": { "attempts": 1 }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Swap in a replacement transaction with the same nonce; only if `old_tx_hash` is still current
+    pub async fn mark_replaced(&self, id: ObjectId, old_tx_hash: &str, tx_hash: &str, raw_tx: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id, "tx_hash": old_tx_hash };
+        let update = doc! {
+            "$set": { "tx_hash": tx_hash, "raw_tx": raw_tx, "updated_at": DateTime::now() },
+            "$push": { "replaced_tx_hashes": old_tx_hash }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Record the receipt of a mined transaction (`Confirmed` or `Reverted`); `tx_hash` is the one that was mined
+    pub async fn mark_mined(
+        &self,
+        id: ObjectId,
+        status: TxOutboxStatus,
+        tx_hash: &str,
+        block_number: Option<i64>,
+        gas_used: Option<String>,
+    ) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            "$set": {
+                "status": status_bson(&status)?,
+                "tx_hash": tx_hash,
+                "block_number": block_number,
+                "gas_used": gas_used,
+                "updated_at": DateTime::now()
+            }
+        };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Remember the database change made for a confirmed entry, so a reorg can undo it
+    pub async fn mark_effect_applied(&self, id: ObjectId, effect: &JobEffect) -> Result<UpdateResult, mongodb::error::Error> {
+        let effect = bson::to_bson(effect).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize effect: {}", e)))?;
+        let filter = doc! { "_id": id };
+        let update = doc! { "$set": { "applied_effect": effect, "updated_at": DateTime::now() } };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // A confirmed transaction was dropped by a reorg; only a `Confirmed` entry can be marked
+    pub async fn mark_reorged(&self, id: ObjectId, error: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id, "status": status_bson(&TxOutboxStatus::Confirmed)? };
+        let update = doc! { "$set": { "status": status_bson(&TxOutboxStatus::Reorged)?, "error": error, "updated_at": DateTime::now() } };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Give up on an entry
+    pub async fn mark_failed(&self, id: ObjectId, error: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! { "$set": { "status": status_bson(&TxOutboxStatus::Failed)?, "error": error, "updated_at": DateTime::now() } };
+
+        self.collection.update_one(filter, update).await
+    }
+
+    // Keep the entry open but remember why the last attempt did not finish
+    pub async fn record_error(&self, id: ObjectId, error: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = doc! { "$set": { "error": error, "updated_at": DateTime::now() } };
+
+        self.collection.update_one(filter, update).await
+    }
+}
@@ -64,6 +64,14 @@ impl UserRepository {
         }
     }
 
+    // Set the platform role of a user
+    pub async fn set_role(&self, user_id: ObjectId, role: UserRole) -> Result<(), mongodb::error::Error> {
+        let filter = doc! { "_id": user_id };
+        let update = doc! { "$set": { "role": bson::to_bson(&role)?, "updated_at": DateTime::now() } };
+        self.collection.update_one(filter, update).await?;
+        Ok(())
+    }
+
     // Bind a user to an enterprise
     pub async fn bind_enterprise(&self, user_wallet_address: &str, enterprise_id: ObjectId) -> Result<bool, mongodb::error::Error> {
         let filter = doc! { 
@@ -76,14 +84,12 @@ impl UserRepository {
             "$set": { 
                 "enterprise_id": enterprise_id,
                 "updated_at": DateTime::now()
-                // Optionally update role here if binding implies a role change
-                // "role": bson::to_bson(&common::domain::entity::UserRole::EnterpriseAdmin).unwrap()
             }
         };
 
         let result = self.collection.update_one(filter, update).await?;
         
-        // Return true if a document was modified, false otherwise
-        Ok(result.modified_count > 0)
+        // Return true if the user exists; binding it again to the same enterprise is not an error
+        Ok(result.matched_count > 0)
     }
 } 
\ No newline at end of file
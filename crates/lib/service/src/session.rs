@@ -0,0 +1,244 @@
+//! Login sessions, refresh-token rotation and access-token revocation.
+//!
+//! A login creates a session in Redis holding the hash of its current refresh token. Access
+//! tokens are short-lived JWTs naming the session. Refreshing swaps the refresh token for a new
+//! one atomically; presenting an already rotated token means it leaked, and the whole session is
+//! revoked. Revoking a session (logout, or the user killing it) deletes it and puts its id on a
+//! revocation list for one access-token lifetime, so tokens already handed out stop working.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use ethers::utils::{hex, keccak256};
+use redis::{AsyncCommands, Client, Script};
+use thiserror::Error;
+
+use common::domain::dto::session_dto::SessionDto;
+
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
+    #[error("Invalid or expired refresh token")]
+    InvalidToken,
+
+    #[error("Refresh token reused, session {0} revoked")]
+    Reused(String),
+}
+
+fn session_key(id: &str) -> String {
+    format!("rwa:session:{}", id)
+}
+
+fn user_sessions_key(address: &str) -> String {
+    format!("rwa:user_sessions:{}", address)
+}
+
+fn revoked_key(id: &str) -> String {
+    format!("rwa:revoked_session:{}", id)
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(keccak256(token.as_bytes()))
+}
+
+/// Session id of a `{session id}.{secret}` refresh token
+pub fn refresh_token_session(token: &str) -> Option<&str> {
+    match token.split_once('.') {
+        Some((id, secret)) if !id.is_empty() && !secret.is_empty() => Some(id),
+        _ => None,
+    }
+}
+
+// Swap the refresh token hash only if it is still the presented one
+const ROTATE_SCRIPT: &str = r#"
+local current = redis.call('HGET', KEYS[1], 'refresh_hash')
+if not current then return 0 end
+if current ~= ARGV[1] then return -1 end
+redis.call('HSET', KEYS[1], 'refresh_hash', ARGV[2], 'last_used_at', ARGV[3])
+redis.call('EXPIRE', KEYS[1], ARGV[4])
+return 1
+"#;
+
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub id: String,
+    pub user_address: String,
+    pub created_at: i64,   // Unix seconds
+    pub last_used_at: i64, // Last login or refresh, Unix seconds
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl Session {
+    pub fn to_dto(&self, current: bool) -> SessionDto {
+        SessionDto {
+            id: self.id.clone(),
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+            user_agent: self.user_agent.clone(),
+            ip: self.ip.clone(),
+            current,
+        }
+    }
+}
+
+pub struct SessionStore {
+    client: Arc<Client>,
+    access_ttl: Duration,
+    refresh_ttl: Duration,
+}
+
+impl SessionStore {
+    pub fn new(client: Arc<Client>, access_ttl: Duration, refresh_ttl: Duration) -> Self {
+        Self { client, access_ttl, refresh_ttl }
+    }
+
+    pub fn access_ttl(&self) -> Duration {
+        self.access_ttl
+    }
+
+    /// Start a session for a fresh login; returns it with its first refresh token
+    pub async fn create(&self, user_address: &str, user_agent: Option<&str>, ip: Option<&str>) -> Result<(Session, String), SessionError> {
+        let now = Utc::now().timestamp();
+        let session = Session {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            user_address: user_address.to_string(),
+            created_at: now,
+            last_used_at: now,
+            user_agent: user_agent.map(str::to_string),
+            ip: ip.map(str::to_string),
+        };
+        let refresh_token = new_refresh_token(&session.id);
+
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let key = session_key(&session.id);
+        let _: () = redis::pipe()
+            .atomic()
+            .hset_multiple(
+                &key,
+                &[
+                    ("user_address", session.user_address.clone()),
+                    ("refresh_hash", token_hash(&refresh_token)),
+                    ("created_at", now.to_string()),
+                    ("last_used_at", now.to_string()),
+                    ("user_agent", session.user_agent.clone().unwrap_or_default()),
+                    ("ip", session.ip.clone().unwrap_or_default()),
+                ],
+            )
+            .expire(&key, self.refresh_ttl.as_secs() as i64)
+            .sadd(user_sessions_key(user_address), &session.id)
+            .query_async(&mut conn)
+            .await?;
+        Ok((session, refresh_token))
+    }
+
+    /// Exchange a refresh token for a new one. A token that was already rotated revokes the session.
+    pub async fn rotate(&self, refresh_token: &str) -> Result<(Session, String), SessionError> {
+        let id = refresh_token_session(refresh_token).ok_or(SessionError::InvalidToken)?;
+        let next = new_refresh_token(id);
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let rotated: i64 = Script::new(ROTATE_SCRIPT)
+            .key(session_key(id))
+            .arg(token_hash(refresh_token))
+            .arg(token_hash(&next))
+            .arg(Utc::now().timestamp())
+            .arg(self.refresh_ttl.as_secs())
+            .invoke_async(&mut conn)
+            .await?;
+        match rotated {
+            1 => {}
+            -1 => {
+                let session = self.get(id).await?;
+                log::warn!("Rotated refresh token of session {} presented again, revoking it", id);
+                if let Some(session) = session {
+                    self.revoke(&session.user_address, id).await?;
+                }
+                return Err(SessionError::Reused(id.to_string()));
+            }
+            _ => return Err(SessionError::InvalidToken),
+        }
+        let session = self.get(id).await?.ok_or(SessionError::InvalidToken)?;
+        Ok((session, next))
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Session>, SessionError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let fields: std::collections::HashMap<String, String> = conn.hgetall(session_key(id)).await?;
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        let text = |name: &str| fields.get(name).cloned().filter(|value| !value.is_empty());
+        let number = |name: &str| fields.get(name).and_then(|value| value.parse().ok()).unwrap_or_default();
+        Ok(Some(Session {
+            id: id.to_string(),
+            user_address: text("user_address").unwrap_or_default(),
+            created_at: number("created_at"),
+            last_used_at: number("last_used_at"),
+            user_agent: text("user_agent"),
+            ip: text("ip"),
+        }))
+    }
+
+    /// Active sessions of a user, most recently used first
+    pub async fn list(&self, user_address: &str) -> Result<Vec<Session>, SessionError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let ids: Vec<String> = conn.smembers(user_sessions_key(user_address)).await?;
+        let mut sessions = Vec::new();
+        for id in ids {
+            match self.get(&id).await? {
+                Some(session) => sessions.push(session),
+                None => {
+                    // Expired since; drop it from the index
+                    let _: () = conn.srem(user_sessions_key(user_address), &id).await?;
+                }
+            }
+        }
+        sessions.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at));
+        Ok(sessions)
+    }
+
+    /// End a session of `user_address`; false when it does not exist or belongs to someone else
+    pub async fn revoke(&self, user_address: &str, id: &str) -> Result<bool, SessionError> {
+        match self.get(id).await? {
+            Some(session) if session.user_address == user_address => {}
+            _ => return Ok(false),
+        }
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .del(session_key(id))
+            .srem(user_sessions_key(user_address), id)
+            .set_ex(revoked_key(id), 1, self.access_ttl.as_secs().max(1))
+            .query_async(&mut conn)
+            .await?;
+        log::info!("Session {} of {} revoked", id, user_address);
+        Ok(true)
+    }
+
+    /// Whether access tokens of the session were revoked
+    pub async fn is_revoked(&self, id: &str) -> Result<bool, SessionError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        Ok(conn.exists(revoked_key(id)).await?)
+    }
+}
+
+fn new_refresh_token(session_id: &str) -> String {
+    format!("{}.{}{}", session_id, uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_token_names_its_session() {
+        let token = new_refresh_token("abc123");
+        assert_eq!(refresh_token_session(&token), Some("abc123"));
+        assert_ne!(token_hash(&token), token_hash(&new_refresh_token("abc123")));
+        assert_eq!(refresh_token_session("no-separator"), None);
+        assert_eq!(refresh_token_session(".secret"), None);
+    }
+}
@@ -0,0 +1,84 @@
+//! Settlement certificates for fully repaid invoice batches.
+//!
+//! The invoice contract has no NFT mint, so settlement is recorded off chain: one
+//! `SettlementNft` per `Settled` batch, pointing at the contract that issued the batch tokens
+//! and owned by the creditor and debtor enterprise wallets.
+
+use mongodb::{
+    bson::{oid::ObjectId, DateTime},
+    Database,
+};
+use thiserror::Error;
+
+use common::domain::entity::{InvoiceBatch, InvoiceBatchStatus, SettlementNft};
+
+use crate::db::is_duplicate_key;
+use crate::repository::{EnterpriseRepository, SettlementNftRepository};
+
+#[derive(Error, Debug)]
+pub enum SettlementError {
+    #[error("Batch {0} is {1:?}, only Settled batches have a settlement record")]
+    NotSettled(ObjectId, InvoiceBatchStatus),
+
+    #[error("Inconsistent state: {0}")]
+    Inconsistent(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+pub struct SettlementService {
+    settlements: SettlementNftRepository,
+    enterprises: EnterpriseRepository,
+}
+
+impl SettlementService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            settlements: SettlementNftRepository::new(db),
+            enterprises: EnterpriseRepository::new(db),
+        }
+    }
+
+    pub async fn find_by_batch(&self, batch_id: ObjectId) -> Result<Option<SettlementNft>, SettlementError> {
+        Ok(self.settlements.find_by_batch(batch_id).await?)
+    }
+
+    /// Return the settlement record of a `Settled` batch, creating it on first use.
+    pub async fn issue(&self, batch: &InvoiceBatch) -> Result<SettlementNft, SettlementError> {
+        let batch_id = batch.id.ok_or_else(|| SettlementError::Inconsistent("Batch has no id".to_string()))?;
+        if batch.status != InvoiceBatchStatus::Settled {
+            return Err(SettlementError::NotSettled(batch_id, batch.status.clone()));
+        }
+        if let Some(existing) = self.settlements.find_by_batch(batch_id).await? {
+            return Ok(existing);
+        }
+
+        let mut settlement = SettlementNft::new(
+            batch_id,
+            batch.rbt_token_address.clone().unwrap_or_default(),
+            batch.chain_batch_id.clone().unwrap_or_else(|| batch_id.to_hex()),
+            DateTime::now(),
+        );
+        settlement.creditor_owner_address = self.wallet_of(batch.creditor_id).await?;
+        settlement.debtor_owner_address = self.wallet_of(batch.debtor_id).await?;
+
+        match self.settlements.create(settlement).await {
+            Ok(created) => {
+                log::info!("Settlement record {} issued for batch {}", created.token_id, batch_id);
+                Ok(created)
+            }
+            // Another caller issued it concurrently; return theirs
+            Err(e) if is_duplicate_key(&e) => self
+                .settlements
+                .find_by_batch(batch_id)
+                .await?
+                .ok_or_else(|| SettlementError::Inconsistent("Settlement record vanished after duplicate insert".to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn wallet_of(&self, enterprise_id: ObjectId) -> Result<Option<String>, SettlementError> {
+        Ok(self.enterprises.find_by_id(enterprise_id).await?.map(|enterprise| enterprise.wallet_address))
+    }
+}
@@ -0,0 +1,395 @@
+//! Sign-In with Ethereum (EIP-4361) messages.
+//!
+//! The login challenge is a full SIWE message binding the wallet signature to our domain, URI,
+//! chain id, a one-time nonce and a validity window. `SiweMessage` renders the message the wallet
+//! signs, parses the text sent back on login and checks it against what the server expects.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use ethers::types::{Address, Signature};
+use ethers::utils::{hash_message, to_checksum};
+use moka::future::Cache;
+use pharos_interact::{is_eip6492, verify_signature, ContractError, ContractResult, RawTransactionSender};
+use thiserror::Error;
+
+const PREAMBLE_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+
+/// Clock skew tolerated between the server and the wallet
+const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SiweError {
+    #[error("Malformed SIWE message: {0}")]
+    Malformed(String),
+
+    #[error("Domain mismatch: expected {expected}, got {actual}")]
+    DomainMismatch { expected: String, actual: String },
+
+    #[error("URI mismatch: expected {expected}, got {actual}")]
+    UriMismatch { expected: String, actual: String },
+
+    #[error("Chain id mismatch: expected {expected}, got {actual}")]
+    ChainMismatch { expected: u64, actual: u64 },
+
+    #[error("Nonce mismatch")]
+    NonceMismatch,
+
+    #[error("Message issued in the future")]
+    IssuedInFuture,
+
+    #[error("Message not valid yet")]
+    NotYetValid,
+
+    #[error("Message expired")]
+    Expired,
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Message signed by {signer}, not by {address}")]
+    SignerMismatch { signer: String, address: String },
+
+    #[error("Chain unavailable for contract wallet verification: {0}")]
+    Chain(String),
+}
+
+/// What a login message must match, from configuration and the issued challenge
+#[derive(Debug, Clone)]
+pub struct SiweExpectation<'a> {
+    pub domain: &'a str,
+    pub uri: &'a str,
+    pub chain_id: u64,
+    pub nonce: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: Address,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub request_id: Option<String>,
+}
+
+impl SiweMessage {
+    /// Login challenge for `address`, valid for `ttl` from `issued_at`
+    pub fn new(domain: &str, address: Address, statement: Option<&str>, uri: &str, chain_id: u64, nonce: &str, issued_at: DateTime<Utc>, ttl: Duration) -> Self {
+        Self {
+            domain: domain.to_string(),
+            address,
+            statement: statement.filter(|s| !s.is_empty()).map(str::to_string),
+            uri: uri.to_string(),
+            version: "1".to_string(),
+            chain_id,
+            nonce: nonce.to_string(),
+            issued_at,
+            expiration_time: Some(issued_at + ttl),
+            not_before: None,
+            request_id: None,
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        self.request_id = Some(request_id.to_string());
+        self
+    }
+
+    /// Check domain, URI, chain, nonce and the validity window at `now`
+    pub fn validate(&self, expected: &SiweExpectation, now: DateTime<Utc>) -> Result<(), SiweError> {
+        if self.domain != expected.domain {
+            return Err(SiweError::DomainMismatch { expected: expected.domain.to_string(), actual: self.domain.clone() });
+        }
+        if self.uri != expected.uri {
+            return Err(SiweError::UriMismatch { expected: expected.uri.to_string(), actual: self.uri.clone() });
+        }
+        if self.chain_id != expected.chain_id {
+            return Err(SiweError::ChainMismatch { expected: expected.chain_id, actual: self.chain_id });
+        }
+        if self.nonce != expected.nonce {
+            return Err(SiweError::NonceMismatch);
+        }
+        let skew = Duration::seconds(MAX_CLOCK_SKEW_SECS);
+        if self.issued_at > now + skew {
+            return Err(SiweError::IssuedInFuture);
+        }
+        if self.not_before.is_some_and(|not_before| not_before > now + skew) {
+            return Err(SiweError::NotYetValid);
+        }
+        if self.expiration_time.is_some_and(|expiration| expiration <= now) {
+            return Err(SiweError::Expired);
+        }
+        Ok(())
+    }
+
+    /// Verify the EIP-191 signature of the rendered message was made by `self.address`
+    pub fn verify_signature(&self, signature: &Signature) -> Result<(), SiweError> {
+        let signer = signature.recover(self.to_string()).map_err(|e| SiweError::InvalidSignature(e.to_string()))?;
+        if signer != self.address {
+            return Err(SiweError::SignerMismatch { signer: format!("{:?}", signer), address: format!("{:?}", self.address) });
+        }
+        Ok(())
+    }
+
+    /// Verify a signature of `self.address` that may come from an EOA or a contract wallet.
+    /// EOA signatures are recovered locally. Only an EIP-6492 wrapped signature or an address
+    /// with contract code is checked on `chain` (EIP-1271 / EIP-6492); any other signature that
+    /// does not recover is invalid, also when the chain cannot be reached.
+    pub async fn verify_wallet_signature<S: RawTransactionSender + ?Sized>(
+        &self,
+        signature: &[u8],
+        chain: &S,
+        codes: &WalletCodeCache,
+    ) -> Result<(), SiweError> {
+        if let Ok(parsed) = Signature::try_from(signature) {
+            if self.verify_signature(&parsed).is_ok() {
+                return Ok(());
+            }
+        }
+        if !is_eip6492(signature) {
+            match codes.has_code(chain, self.address).await {
+                Ok(true) => {}
+                Ok(false) => return Err(SiweError::InvalidSignature(format!("not a valid signature of {:?}", self.address))),
+                Err(e) => return Err(SiweError::InvalidSignature(format!("{:?} has no valid EOA signature and its code could not be read: {}", self.address, e))),
+            }
+        }
+        // A contract wallet only exists on the chain the message names
+        let chain_id = chain.chain_id().await.map_err(|e| SiweError::Chain(e.to_string()))?;
+        if chain_id != self.chain_id {
+            return Err(SiweError::ChainMismatch { expected: chain_id, actual: self.chain_id });
+        }
+        let hash = hash_message(self.to_string());
+        match verify_signature(chain, self.address, hash, signature).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(SiweError::InvalidSignature(format!("not a valid signature of {:?}", self.address))),
+            Err(ContractError::InvalidInput(e)) => Err(SiweError::InvalidSignature(e)),
+            Err(e) => Err(SiweError::Chain(e.to_string())),
+        }
+    }
+}
+
+/// Remembers for a while which addresses have contract code, so logins with bad signatures
+/// do not query the RPC node every time.
+pub struct WalletCodeCache {
+    cache: Cache<Address, bool>,
+}
+
+impl WalletCodeCache {
+    pub fn new(ttl: std::time::Duration) -> Self {
+        Self { cache: Cache::builder().time_to_live(ttl).max_capacity(10_000).build() }
+    }
+
+    /// Whether `address` is a deployed contract on `chain`; lookup failures are not cached
+    pub async fn has_code<S: RawTransactionSender + ?Sized>(&self, chain: &S, address: Address) -> ContractResult<bool> {
+        if let Some(has_code) = self.cache.get(&address).await {
+            return Ok(has_code);
+        }
+        let has_code = !chain.code_at(address).await?.is_empty();
+        self.cache.insert(address, has_code).await;
+        Ok(has_code)
+    }
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl fmt::Display for SiweMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}{}", self.domain, PREAMBLE_SUFFIX)?;
+        writeln!(f, "{}", to_checksum(&self.address, None))?;
+        writeln!(f)?;
+        if let Some(statement) = &self.statement {
+            writeln!(f, "{}", statement)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "URI: {}", self.uri)?;
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Chain ID: {}", self.chain_id)?;
+        writeln!(f, "Nonce: {}", self.nonce)?;
+        write!(f, "Issued At: {}", timestamp(&self.issued_at))?;
+        if let Some(expiration) = &self.expiration_time {
+            write!(f, "\nExpiration Time: {}", timestamp(expiration))?;
+        }
+        if let Some(not_before) = &self.not_before {
+            write!(f, "\nNot Before: {}", timestamp(not_before))?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, "\nRequest ID: {}", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SiweMessage {
+    type Err = SiweError;
+
+    fn from_str(message: &str) -> Result<Self, Self::Err> {
+        let malformed = |what: &str| SiweError::Malformed(what.to_string());
+        let mut lines = message.split('\n').peekable();
+
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(PREAMBLE_SUFFIX))
+            .filter(|domain| !domain.is_empty())
+            .ok_or_else(|| malformed("missing preamble"))?
+            .to_string();
+        let address_line = lines.next().ok_or_else(|| malformed("missing address"))?;
+        let address = Address::from_str(address_line).map_err(|_| malformed("invalid address"))?;
+        // EIP-4361 requires the EIP-55 checksummed form
+        if address_line != to_checksum(&address, None) {
+            return Err(malformed("address is not EIP-55 checksummed"));
+        }
+        if lines.next() != Some("") {
+            return Err(malformed("expected an empty line after the address"));
+        }
+        let statement = match lines.peek() {
+            Some(line) if !line.starts_with("URI: ") => {
+                let statement = lines.next().unwrap_or_default().to_string();
+                if lines.next() != Some("") {
+                    return Err(malformed("expected an empty line after the statement"));
+                }
+                Some(statement)
+            }
+            _ => None,
+        };
+
+        let mut field = |name: &str| -> Result<String, SiweError> {
+            let line = lines.next().ok_or_else(|| SiweError::Malformed(format!("missing {}", name)))?;
+            line.strip_prefix(&format!("{}: ", name))
+                .map(str::to_string)
+                .ok_or_else(|| SiweError::Malformed(format!("expected {}", name)))
+        };
+        let uri = field("URI")?;
+        let version = field("Version")?;
+        if version != "1" {
+            return Err(malformed("unsupported version"));
+        }
+        let chain_id = field("Chain ID")?.parse::<u64>().map_err(|_| malformed("invalid chain id"))?;
+        let nonce = field("Nonce")?;
+        if nonce.len() < 8 || !nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(malformed("nonce must be at least 8 alphanumeric characters"));
+        }
+        let issued_at = parse_time(&field("Issued At")?)?;
+
+        let mut parsed = Self {
+            domain,
+            address,
+            statement,
+            uri,
+            version,
+            chain_id,
+            nonce,
+            issued_at,
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+        };
+        for line in lines {
+            if let Some(value) = line.strip_prefix("Expiration Time: ") {
+                parsed.expiration_time = Some(parse_time(value)?);
+            } else if let Some(value) = line.strip_prefix("Not Before: ") {
+                parsed.not_before = Some(parse_time(value)?);
+            } else if let Some(value) = line.strip_prefix("Request ID: ") {
+                parsed.request_id = Some(value.to_string());
+            } else if line == "Resources:" || line.starts_with("- ") {
+                continue; // Resources are not used by the platform
+            } else {
+                return Err(SiweError::Malformed(format!("unexpected line '{}'", line)));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, SiweError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| SiweError::Malformed(format!("invalid timestamp '{}'", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+
+    fn wallet() -> LocalWallet {
+        "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap()
+    }
+
+    fn message(address: Address, issued_at: DateTime<Utc>) -> SiweMessage {
+        SiweMessage::new("app.example.com", address, Some("Sign in to the platform."), "https://app.example.com", 688688, "a1b2c3d4e5f6", issued_at, Duration::minutes(5))
+            .with_request_id("req-1")
+    }
+
+    fn expected() -> SiweExpectation<'static> {
+        SiweExpectation { domain: "app.example.com", uri: "https://app.example.com", chain_id: 688688, nonce: "a1b2c3d4e5f6" }
+    }
+
+    #[tokio::test]
+    async fn siwe_message_round_trips_and_verifies() {
+        let wallet = wallet();
+        let issued_at = DateTime::parse_from_rfc3339("2025-05-01T08:00:00Z").unwrap().with_timezone(&Utc);
+        let message = message(wallet.address(), issued_at);
+        let text = message.to_string();
+        assert!(text.starts_with("app.example.com wants you to sign in with your Ethereum account:\n"));
+        assert!(text.contains("\nChain ID: 688688\nNonce: a1b2c3d4e5f6\nIssued At: 2025-05-01T08:00:00Z\nExpiration Time: 2025-05-01T08:05:00Z"));
+
+        let parsed: SiweMessage = text.parse().unwrap();
+        assert_eq!(parsed, message);
+        assert_eq!(parsed.validate(&expected(), issued_at + Duration::minutes(1)), Ok(()));
+
+        let signature = wallet.sign_message(&text).await.unwrap();
+        assert_eq!(parsed.verify_signature(&signature), Ok(()));
+        let other: LocalWallet = "0000000000000000000000000000000000000000000000000000000000000001".parse().unwrap();
+        let other = other.sign_message(&text).await.unwrap();
+        assert!(matches!(parsed.verify_signature(&other), Err(SiweError::SignerMismatch { .. })));
+    }
+
+    #[test]
+    fn siwe_validation_rejects_wrong_binding() {
+        let issued_at = DateTime::parse_from_rfc3339("2025-05-01T08:00:00Z").unwrap().with_timezone(&Utc);
+        let message = message(wallet().address(), issued_at);
+        let now = issued_at + Duration::minutes(1);
+
+        let phishing = SiweExpectation { domain: "evil.example.com", ..expected() };
+        assert!(matches!(message.validate(&phishing, now), Err(SiweError::DomainMismatch { .. })));
+        let mainnet = SiweExpectation { chain_id: 1, ..expected() };
+        assert!(matches!(message.validate(&mainnet, now), Err(SiweError::ChainMismatch { .. })));
+        let replay = SiweExpectation { nonce: "zzzzzzzzzzzz", ..expected() };
+        assert_eq!(message.validate(&replay, now), Err(SiweError::NonceMismatch));
+        assert_eq!(message.validate(&expected(), issued_at + Duration::minutes(5)), Err(SiweError::Expired));
+        assert_eq!(message.validate(&expected(), issued_at - Duration::minutes(5)), Err(SiweError::IssuedInFuture));
+
+        let lowercase = message.to_string().replace(&to_checksum(&message.address, None), &format!("{:?}", message.address));
+        assert!(matches!(lowercase.parse::<SiweMessage>(), Err(SiweError::Malformed(_))));
+    }
+
+    #[tokio::test]
+    async fn contract_wallet_signature_is_checked_on_chain() {
+        use pharos_interact::fake::{FakeInvoiceContract, FAKE_CHAIN_ID};
+
+        let owner = wallet();
+        let safe = Address::from_low_u64_be(0x5afe);
+        let chain = FakeInvoiceContract::default();
+        let codes = WalletCodeCache::new(std::time::Duration::from_secs(60));
+        let message = SiweMessage::new("app.example.com", safe, None, "https://app.example.com", FAKE_CHAIN_ID, "a1b2c3d4e5f6", Utc::now(), Duration::minutes(5));
+        let signature = owner.sign_message(message.to_string()).await.unwrap().to_vec();
+        assert!(matches!(message.verify_wallet_signature(&signature, &chain, &codes).await, Err(SiweError::InvalidSignature(_))));
+
+        chain.add_contract_wallet(safe, owner.address(), None);
+        // The address was seen without code a moment ago
+        assert!(matches!(message.verify_wallet_signature(&signature, &chain, &codes).await, Err(SiweError::InvalidSignature(_))));
+        let codes = WalletCodeCache::new(std::time::Duration::from_secs(60));
+        assert_eq!(message.verify_wallet_signature(&signature, &chain, &codes).await, Ok(()));
+        let elsewhere = SiweMessage { chain_id: 688688, ..message };
+        assert!(matches!(elsewhere.verify_wallet_signature(&signature, &chain, &codes).await, Err(SiweError::ChainMismatch { .. })));
+    }
+}